@@ -0,0 +1,5 @@
+//! The binaural beat generator as a library, so that other programs can play sessions without the interactive prompts.
+//! See `modules::bb_generator::BinauralPlayer` to start a session and control it while it plays.
+//! Use `modules::bb_generator::render_samples` to get the audio of a session without opening an audio device.
+
+pub mod modules;
@@ -6,75 +6,900 @@
 extern crate cpal;
 use colored::Colorize;
 use crossterm::event::{self, Event, KeyCode, KeyEventKind};
-use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::process::ExitCode;
+use std::sync::{Arc, Mutex, mpsc};
+use std::time::Duration as StdDuration;
 
 use anyhow::Error;
-use inquire::Select;
+use clap::Parser;
+use inquire::{Confirm, CustomType, InquireError, MultiSelect, Select, Text};
+use jiff::Timestamp;
+use jiff::tz::TimeZone;
+use serde_json::json;
+use uuid::Uuid;
 
-use crate::modules::bb_generator::generate_binaural_beats;
-use crate::modules::duration::duration::duration_list;
-use crate::modules::preset::{BinauralPresetGroup, preset_list};
-
-mod modules;
+use binaural_beat_generator_cli::modules::bb_generator::{
+    BeatMode, BinauralPlayer, PlaybackOptions, PlaybackOutcome, SessionEvent, SessionRepeat,
+};
+use binaural_beat_generator_cli::modules::calibration::{CalibrationOutcome, run_calibration};
+use binaural_beat_generator_cli::modules::cancel_token::{CancelToken, STOP_FADE_OUT};
+use binaural_beat_generator_cli::modules::cli::{Cli, Command};
+#[cfg(feature = "tui")]
+use binaural_beat_generator_cli::modules::dashboard::run_dashboard;
+use binaural_beat_generator_cli::modules::duration::duration::{
+    Duration, DurationChoice, MAX_CUSTOM_MINUTES, duration_choice_index, duration_choice_list,
+    parse_minutes,
+};
+use binaural_beat_generator_cli::modules::duration::duration_common::ToMinutes;
+use binaural_beat_generator_cli::modules::events::{
+    emit_event, emit_session_event, report_progress,
+};
+use binaural_beat_generator_cli::modules::exit_status::{ExitStatus, PlaybackError};
+use binaural_beat_generator_cli::modules::frequency::frequency_common::ToFrequency;
+use binaural_beat_generator_cli::modules::history::{
+    ListeningStats, SessionRecord, append_record, history_path, listening_stats, read_history,
+};
+use binaural_beat_generator_cli::modules::manual::render_man_page;
+use binaural_beat_generator_cli::modules::output::cpal_output::{
+    OutputDeviceInfo, default_output_device_name, list_output_devices, output_device_names,
+};
+use binaural_beat_generator_cli::modules::output::output_common::AudioOutput;
+use binaural_beat_generator_cli::modules::preset::{
+    BinauralPresetGroup, Preset, presets_with_tags,
+};
+use binaural_beat_generator_cli::modules::progress::{
+    format_clock, show_progress, show_progress_on,
+};
+use binaural_beat_generator_cli::modules::queue::{
+    QueueTransition, check_transition, queue_playback_options, wait_for_next,
+};
+use binaural_beat_generator_cli::modules::saved_preset::{
+    NameConflict, PresetChoice, SavedPreset, SavedPresetStore, preset_choice_list,
+    preset_group_choice_list,
+};
+use binaural_beat_generator_cli::modules::self_check::{CheckReport, run_checks};
+use binaural_beat_generator_cli::modules::settings_warning::{SettingsWarning, settings_warnings};
+use binaural_beat_generator_cli::modules::start_time::{StartTime, WaitOutcome, wait_for_start};
+use binaural_beat_generator_cli::modules::synth::ambient::{Ambient, AmbientSound};
 
 /// This is the entry point to the program.
-fn main() -> Result<(), Error> {
-    let preset_options = preset_list();
-    let duration_options = duration_list();
-    
-    print_program_info();
+/// The exit code tells scripts how the program finished, see `ExitStatus` for the meaning of each code.
+fn main() -> ExitCode {
+    let cli = Cli::parse();
 
-    let chosen_preset = Select::new("Choose a preset: ", preset_options)
-        .with_page_size(7)
-        .prompt();
+    match run(&cli) {
+        Ok(status) => status.into(),
+        Err(err) => {
+            let status = ExitStatus::from(&err);
+            // In JSON mode the event is the whole report, a wrapper reading stderr shouldn't get it twice.
+            if cli.json {
+                emit_event(
+                    "error",
+                    json!({ "message": format!("{:#}", err), "exit_code": status.code() }),
+                );
+            } else {
+                eprintln!("Error: {:?}", err);
+            }
+            status.into()
+        }
+    }
+}
+
+/// Runs the interactive prompts and the chosen session, returning the status the program should exit with.
+fn run(cli: &Cli) -> Result<ExitStatus, Error> {
+    match cli.command {
+        Some(Command::ListPresets) => {
+            print_preset_list(&presets_with_tags(&cli.tag));
+            return Ok(ExitStatus::Completed);
+        }
+        Some(Command::ListDevices { json }) => {
+            print_device_list(&list_output_devices(), json);
+            return Ok(ExitStatus::Completed);
+        }
+        Some(Command::Stats) => {
+            let path = history_path()
+                .ok_or_else(|| anyhow::anyhow!("The data directory can't be found."))?;
+            print_stats(&listening_stats(&read_history(&path)?, &TimeZone::system()));
+            return Ok(ExitStatus::Completed);
+        }
+        Some(Command::Check { no_audio }) => {
+            return Ok(print_check_report(&run_checks(!no_audio)));
+        }
+        Some(Command::Man) => {
+            render_man_page(&mut std::io::stdout())?;
+            return Ok(ExitStatus::Completed);
+        }
+        None => {}
+    }
+
+    let preset_options = presets_with_tags(&cli.tag);
+
+    if preset_options.is_empty() {
+        eprintln!("No preset carries all of the tags: {}", cli.tag.join(", "));
+        return Ok(ExitStatus::InvalidArguments);
+    }
+
+    if cli.prints_text() {
+        print_program_info();
+    }
+
+    let device_name = match &cli.device {
+        Some(Some(name)) => Some(name.clone()),
+        Some(None) => match prompt_device()? {
+            Ok(name) => Some(name),
+            Err(err) => {
+                eprintln!(
+                    "There was an error choosing the device, please try again. {}",
+                    err
+                );
+                return Ok(prompt_error_status(&err));
+            }
+        },
+        None => None,
+    };
+
+    let ambient = ambient_from(cli)?;
+
+    let mut saved_presets = match SavedPresetStore::open_default() {
+        Ok(store) => Some(store),
+        Err(err) => {
+            eprintln!(
+                "The saved presets can't be loaded, only the built in presets are offered. {}",
+                err
+            );
+            None
+        }
+    };
+    let preset_choices = preset_choice_list(
+        saved_presets
+            .as_ref()
+            .map(SavedPresetStore::presets)
+            .unwrap_or_default(),
+        &preset_options,
+    );
+
+    if let Some(queued_presets) = &cli.queue {
+        return run_queue(
+            cli,
+            queued_presets,
+            &preset_choices,
+            device_name.as_deref(),
+            ambient.as_ref(),
+        );
+    }
+
+    // A preset given on the command line is played without asking, so that other programs can start a session.
+    let chosen_preset = match cli.preset {
+        Some(preset) => Ok(PresetChoice::BuiltIn(preset)),
+        None => prompt_preset(&preset_choices),
+    };
 
     match chosen_preset {
-        Ok(preset) => {
-            let mut binaural_preset_options = BinauralPresetGroup::from(preset);
+        Ok(choice) => {
+            if cli.prints_text() {
+                println!("{}\n", choice.description().italic());
+            }
 
-            let starting_duration_index = duration_options
-                .iter()
-                .position(|&x| x == binaural_preset_options.duration)
-                .unwrap();
+            let mut binaural_preset_options = choice.preset_options();
 
-            let chosen_duration = Select::new("Choose a duration: ", duration_options)
-                .with_starting_cursor(starting_duration_index)
-                .prompt();
+            let duration_prompted = cli.preset.is_none() && cli.duration.is_none();
+            let chosen_duration = match cli.duration {
+                Some(duration) => Ok(duration),
+                None if cli.preset.is_some() => Ok(binaural_preset_options.duration),
+                None => prompt_duration(binaural_preset_options.duration),
+            };
 
             match chosen_duration {
                 Ok(duration) => {
+                    let duration_changed = duration != binaural_preset_options.duration;
                     //Get the chosen duration if it has changed.
                     binaural_preset_options.duration = duration;
-                    run_binaural_beat(binaural_preset_options)?;
+
+                    // Other programs reading the JSON events can't be asked questions they didn't expect,
+                    // and saving would print in the middle of the piped audio.
+                    if duration_changed
+                        && duration_prompted
+                        && cli.prints_text()
+                        && let Some(store) = saved_presets.as_mut()
+                    {
+                        offer_to_save_preset(store, binaural_preset_options);
+                    }
+
+                    let mut playback_options = playback_options_from(cli);
+
+                    let warnings = settings_warnings(binaural_preset_options, &playback_options);
+                    if !confirm_warnings(&warnings, cli) {
+                        return Ok(ExitStatus::CancelledByUser);
+                    }
+
+                    let start_time = match start_time_from(cli) {
+                        Ok(start_time) => start_time,
+                        Err(err) => {
+                            eprintln!(
+                                "There was an error choosing the start time, please try again. {}",
+                                err
+                            );
+                            return Ok(prompt_error_status(&err));
+                        }
+                    };
+
+                    if cli.calibrate {
+                        match run_calibration(
+                            binaural_preset_options,
+                            playback_options.volume,
+                            playback_options.waveform,
+                            device_name.as_deref(),
+                        )? {
+                            CalibrationOutcome::Accepted(volume) => {
+                                playback_options.volume = volume
+                            }
+                            CalibrationOutcome::Cancelled => {
+                                return Ok(ExitStatus::CancelledByUser);
+                            }
+                        }
+                    }
+
+                    if let Some(start_time) = start_time
+                        && wait_for_start(start_time)? == WaitOutcome::Cancelled
+                    {
+                        return Ok(ExitStatus::CancelledByUser);
+                    }
+
+                    let started_at = Timestamp::now();
+                    let (session_id, outcome, played) = run_binaural_beat(
+                        binaural_preset_options,
+                        playback_options,
+                        ambient,
+                        &cli.audio_output(device_name.as_deref()),
+                        cli.wants_dashboard(),
+                        cli.json,
+                    )?;
+
+                    record_session(&SessionRecord {
+                        session_id,
+                        started_at,
+                        name: choice.name(),
+                        preset_options: binaural_preset_options,
+                        requested: match playback_options.repeat {
+                            SessionRepeat::Times(times) => Some(StdDuration::from_secs(
+                                binaural_preset_options.duration.to_minutes() as u64
+                                    * 60
+                                    * times as u64,
+                            )),
+                            SessionRepeat::Forever => None,
+                        },
+                        played,
+                        outcome,
+                    });
+                    Ok(ExitStatus::from(outcome))
                 }
-                Err(err) => eprintln!(
-                    "There was an error choosing the duration, please try again. {}",
-                    err
-                ),
+                Err(err) => {
+                    eprintln!(
+                        "There was an error choosing the duration, please try again. {}",
+                        err
+                    );
+                    Ok(prompt_error_status(&err))
+                }
+            }
+        }
+        Err(err) => {
+            eprintln!("There was an error, please try again. {}", err);
+            Ok(prompt_error_status(&err))
+        }
+    }
+}
+
+/// Returns the playback options chosen on the command line.
+fn playback_options_from(cli: &Cli) -> PlaybackOptions {
+    PlaybackOptions {
+        volume: cli.volume as f32 / 100.0,
+        ease_in: StdDuration::from_secs(cli.ease_in.unwrap_or(0)),
+        ease_in_curve: cli.ease_in_curve,
+        alternate_every: cli
+            .alternate
+            .map(|minutes| StdDuration::from_secs(minutes * 60)),
+        ramp_to_hz: cli.ramp_to,
+        ramp_curve: cli.ramp_curve,
+        waveform: cli.waveform,
+        fade_in: StdDuration::ZERO,
+        repeat: match cli.repeat {
+            None => SessionRepeat::Times(1),
+            Some(Some(times)) => SessionRepeat::Times(times),
+            Some(None) => SessionRepeat::Forever,
+        },
+        fade_out: cli
+            .fade_out
+            .map(|minutes| StdDuration::from_secs(minutes * 60)),
+        beat_mode: match cli.bilateral {
+            Some(rate_hz) => BeatMode::Bilateral { rate_hz },
+            None => BeatMode::Binaural,
+        },
+    }
+}
+
+/// Loads the recording chosen with `--ambient`, so that a file that can't be played is reported before any prompts.
+fn ambient_from(cli: &Cli) -> Result<Option<Ambient>, Error> {
+    let Some(path) = &cli.ambient else {
+        return Ok(None);
+    };
+    let sound = AmbientSound::load(path)?;
+
+    if cli.prints_text() {
+        println!(
+            "Looping {} ({}) underneath the tones at {}%.\n",
+            path.display(),
+            format_clock(sound.length()),
+            cli.ambient_volume
+        );
+    }
+
+    Ok(Some(Ambient {
+        sound: Arc::new(sound),
+        gain: cli.ambient_volume as f32 / 100.0,
+    }))
+}
+
+/// Returns the time of day chosen on the command line, asking for it when `--start-at` was given without one.
+fn start_time_from(cli: &Cli) -> Result<Option<StartTime>, InquireError> {
+    match cli.start_at {
+        Some(Some(start_time)) => Ok(Some(start_time)),
+        Some(None) => prompt_start_time().map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Asks the user for a preset, first choosing the group it's listed under when the presets fall into more than one.
+/// Esc in a group's list goes back to the groups.
+fn prompt_preset(preset_choices: &[PresetChoice]) -> Result<PresetChoice, InquireError> {
+    let group_choices = preset_group_choice_list(preset_choices);
+    if group_choices.len() < 2 {
+        return select_preset(preset_choices.to_vec(), None);
+    }
+
+    loop {
+        let chosen_group = Select::new("Choose a group: ", group_choices.clone()).prompt()?;
+        let choices_in_group = preset_choices
+            .iter()
+            .filter(|choice| chosen_group.contains(choice))
+            .cloned()
+            .collect();
+
+        match select_preset(
+            choices_in_group,
+            Some("↑↓ to move, enter to select, type to filter, esc to go back"),
+        ) {
+            Err(InquireError::OperationCanceled) => continue,
+            chosen_preset => return chosen_preset,
+        }
+    }
+}
+
+/// Asks the user to pick one of the presets, with the given help message in place of the usual one.
+fn select_preset(
+    preset_choices: Vec<PresetChoice>,
+    help_message: Option<&str>,
+) -> Result<PresetChoice, InquireError> {
+    let mut select = Select::new("Choose a preset: ", preset_choices)
+        .with_page_size(7)
+        // Typing filters on the preset name and tags, scoring by position keeps the usual order.
+        .with_scorer(&|filter, choice: &PresetChoice, _, index| {
+            choice.matches_filter(filter).then_some(-(index as i64))
+        });
+
+    if let Some(help_message) = help_message {
+        select = select.with_help_message(help_message);
+    }
+
+    select.prompt()
+}
+
+/// Asks the user for the session's duration, starting on the preset's default.
+/// Choosing the custom entry asks for the number of minutes or hours to be typed in.
+fn prompt_duration(default_duration: Duration) -> Result<Duration, InquireError> {
+    let chosen_duration = Select::new("Choose a duration: ", duration_choice_list())
+        .with_starting_cursor(duration_choice_index(default_duration))
+        .prompt()?;
+
+    match chosen_duration {
+        DurationChoice::Listed(duration) => Ok(duration),
+        DurationChoice::Custom => {
+            let error_message = format!(
+                "Enter minutes or hours, like 90 or 8h, up to {} hours.",
+                MAX_CUSTOM_MINUTES / 60
+            );
+
+            let mut prompt = CustomType::<u32>::new("Enter a duration: ")
+                .with_help_message("For example 90, 90m or 1h 30m")
+                .with_parser(&|text| parse_minutes(text).ok_or(()))
+                .with_formatter(&|minutes| Duration::Custom(minutes).to_string())
+                .with_default_value_formatter(&|minutes| Duration::Custom(minutes).to_string())
+                .with_error_message(&error_message);
+
+            // A saved custom duration is offered again, so Enter keeps it.
+            if let Duration::Custom(minutes) = default_duration {
+                prompt = prompt.with_default(minutes);
             }
+
+            prompt.prompt().map(Duration::Custom)
+        }
+    }
+}
+
+/// Shows the warnings about unusual settings and asks whether to play anyway, returning false when the user doesn't.
+/// In `--json` mode the warnings are reported as events and the session goes ahead, there is nobody to ask.
+/// The same goes for piped audio, where the warnings are only printed.
+fn confirm_warnings(warnings: &[SettingsWarning], cli: &Cli) -> bool {
+    if warnings.is_empty() {
+        return true;
+    }
+
+    if cli.json {
+        for warning in warnings {
+            emit_event("warning", json!({ "message": warning.to_string() }));
         }
-        Err(err) => eprintln!("There was an error, please try again. {}", err),
+        return true;
+    }
+
+    // The warnings go to stderr next to the question, so that they don't end up in piped audio.
+    for warning in warnings {
+        eprintln!("{}", warning.to_string().yellow());
     }
 
-    Ok(())
+    if !cli.prints_text() {
+        return true;
+    }
+
+    Confirm::new("Play anyway?")
+        .with_default(false)
+        .prompt()
+        .unwrap_or(false)
+}
+
+/// Asks the user for the time of day to start the session at.
+fn prompt_start_time() -> Result<StartTime, InquireError> {
+    CustomType::<StartTime>::new("Start at: ")
+        .with_help_message("A time on the 24 hour clock, like 22:30")
+        .with_error_message("Enter the hours and minutes, like 22:30 or 7:05.")
+        .prompt()
+}
+
+/// Offers to save a combination that differs from the chosen preset under a name of the user's own,
+/// so that it shows at the top of the preset menu next time.
+/// Leaving the prompts or failing to write the file only skips saving, the session still starts.
+fn offer_to_save_preset(store: &mut SavedPresetStore, preset_options: BinauralPresetGroup) {
+    let wants_to_save = Confirm::new("Save this combination as a preset?")
+        .with_default(false)
+        .prompt()
+        .unwrap_or(false);
+
+    if !wants_to_save {
+        return;
+    }
+
+    loop {
+        let Ok(name) = Text::new("Preset name: ").prompt() else {
+            return;
+        };
+        let name = name.trim().to_string();
+
+        match store.name_conflict(&name) {
+            Some(NameConflict::Empty) => {
+                eprintln!("The name can't be empty.");
+                continue;
+            }
+            Some(NameConflict::BuiltIn) => {
+                eprintln!(
+                    "\"{}\" is a built in preset, please choose another name.",
+                    name
+                );
+                continue;
+            }
+            Some(NameConflict::Saved) => {
+                let replace = Confirm::new(&format!("Replace the saved preset \"{}\"?", name))
+                    .with_default(false)
+                    .prompt()
+                    .unwrap_or(false);
+
+                if !replace {
+                    continue;
+                }
+            }
+            None => {}
+        }
+
+        match store.save(SavedPreset {
+            name: name.clone(),
+            preset_options,
+        }) {
+            Ok(()) => println!("Saved the preset \"{}\".", name),
+            Err(err) => eprintln!("The preset couldn't be saved. {}", err),
+        }
+        return;
+    }
+}
+
+/// Asks the user which output device to play through, starting on the default device.
+/// Listing the devices can fail before the prompt is shown, which is reported as the outer error.
+fn prompt_device() -> Result<Result<String, InquireError>, Error> {
+    let device_options = output_device_names()?;
+
+    if device_options.is_empty() {
+        return Err(PlaybackError::NoDevice("No output device available.".to_string()).into());
+    }
+
+    let starting_device_index = default_output_device_name()
+        .and_then(|default_name| device_options.iter().position(|name| *name == default_name))
+        .unwrap_or(0);
+
+    Ok(Select::new("Choose an output device: ", device_options)
+        .with_starting_cursor(starting_device_index)
+        .prompt())
+}
+
+/// A helper function that treats leaving a prompt with escape or Ctrl+C as a cancellation by the user.
+fn prompt_error_status(err: &InquireError) -> ExitStatus {
+    match err {
+        InquireError::OperationCanceled | InquireError::OperationInterrupted => {
+            ExitStatus::CancelledByUser
+        }
+        _ => ExitStatus::Failure,
+    }
 }
 
 /// A helper funciton that sets off the running of the binaural beat tones.
-/// It also spawns a new thread in order to watch for early completion.
-fn run_binaural_beat(preset_options: BinauralPresetGroup) -> Result<(), Error> {
-    let cancel_token = Arc::new(AtomicBool::new(false));
-    let cancel_token_clone = Arc::clone(&cancel_token);
+/// It also spawns a new thread in order to watch for early completion, unless the dashboard reads the keyboard itself.
+/// With `as_json` set the session is reported as JSON events instead, and it can only be stopped by ending the program.
+/// When the audio is piped to stdout, everything shown to the user goes to stderr.
+/// Returns the session's ID, how it ended and how long it played for.
+fn run_binaural_beat(
+    preset_options: BinauralPresetGroup,
+    playback_options: PlaybackOptions,
+    ambient: Option<Ambient>,
+    output: &AudioOutput,
+    show_dashboard: bool,
+    as_json: bool,
+) -> Result<(Uuid, PlaybackOutcome, StdDuration), Error> {
+    let mut player =
+        BinauralPlayer::start_with_ambient(preset_options, playback_options, ambient, output)?;
+    let session_id = player.session_id();
 
-    // 2. Start a separate thread to listen for user input
-    std::thread::spawn(move || {
-        println!("Press Enter to stop playback.");
+    if as_json {
+        emit_event("session_started", player.settings().to_json());
+        report_progress(&mut player, StdDuration::ZERO)?;
+        let played = player.played();
+        let outcome = player.wait()?;
+        emit_session_event(
+            "session_ended",
+            session_id,
+            json!({ "outcome": outcome.name(), "played_seconds": played.as_secs() }),
+        );
+        return Ok((session_id, outcome, played));
+    }
+
+    if let AudioOutput::Pipe {
+        sample_rate,
+        format,
+    } = output
+    {
+        eprintln!("{}", player.settings());
+        eprintln!(
+            "Writing {} Hz stereo {} PCM to stdout, `ffplay -f {} -ar {} -ac 2 -` plays it.",
+            sample_rate, format, format, sample_rate
+        );
+        eprintln!("Press Enter to stop playback.");
+        listen_for_enter(player.cancel_token());
+
+        show_progress_on(&mut player, StdDuration::ZERO, &mut std::io::stderr())?;
+        let played = player.played();
+        let outcome = player.wait()?;
+        if outcome == PlaybackOutcome::Cancelled {
+            eprintln!("Playback cancelled by user.");
+        }
+        return Ok((session_id, outcome, played));
+    }
+
+    println!("{}", player.settings());
 
+    #[cfg(feature = "tui")]
+    if show_dashboard {
+        run_dashboard(&mut player, preset_options)?;
+        let played = player.played();
+        return finish_session(player, played);
+    }
+    #[cfg(not(feature = "tui"))]
+    let _ = show_dashboard;
+
+    // Print the hint before the progress line starts redrawing.
+    println!("Press Enter to stop playback.");
+    listen_for_enter(player.cancel_token());
+
+    show_progress(&mut player, StdDuration::ZERO)?;
+    let played = player.played();
+    finish_session(player, played)
+}
+
+/// Starts a thread that listens for user input, fading the session out once Enter is pressed.
+fn listen_for_enter(cancel_token: CancelToken) {
+    std::thread::spawn(move || {
         loop {
             match event::read() {
                 Ok(Event::Key(key_event)) => {
                     if key_event.kind == KeyEventKind::Press && key_event.code == KeyCode::Enter {
-                        cancel_token_clone.store(true, Ordering::Relaxed);
+                        cancel_token.cancel_with_fade(STOP_FADE_OUT);
+                    }
+                }
+                Ok(_) => {} // Ignore other events
+                Err(err) => eprintln!("There was an error, please try again. {}", err),
+            }
+        }
+    });
+}
+
+/// Waits for the player to stop, telling the user when they stopped the session themselves.
+/// Returns the session's ID along with how it ended and how long it played for.
+fn finish_session(
+    player: BinauralPlayer,
+    played: StdDuration,
+) -> Result<(Uuid, PlaybackOutcome, StdDuration), Error> {
+    let session_id = player.session_id();
+    let outcome = player.wait()?;
+
+    if outcome == PlaybackOutcome::Cancelled {
+        println!("Playback cancelled by user.");
+    }
+    Ok((session_id, outcome, played))
+}
+
+/// Waits for the player to stop like `BinauralPlayer::wait`, reporting any change in the output's health
+/// while it finishes, as an event in `--json` mode.
+fn wait_reporting_health(player: BinauralPlayer, as_json: bool) -> Result<PlaybackOutcome, Error> {
+    let session_id = player.session_id();
+    let (events, received_events) = mpsc::channel();
+
+    // The player can't leave this thread, so the events are reported from another one as they come in.
+    let reporter = std::thread::spawn(move || {
+        for event in received_events {
+            if let SessionEvent::OutputHealth(output_health) = event {
+                if as_json {
+                    emit_session_event(
+                        "output_health",
+                        session_id,
+                        json!({ "message": output_health.to_string() }),
+                    );
+                } else {
+                    println!("{}", output_health);
+                }
+            }
+        }
+    });
+
+    let outcome = player.wait_with_events(events);
+    let _ = reporter.join();
+    outcome
+}
+
+/// A session of the queue that is playing, with what is needed to add it to the history once it ends.
+struct QueuedSession {
+    player: BinauralPlayer,
+    name: String,
+    preset_options: BinauralPresetGroup,
+    started_at: Timestamp,
+}
+
+/// Plays the queued presets one after the other, each for its default duration, asking for them when none were given.
+/// Every preset is added to the history on its own. Enter stops the whole queue, `n` followed by Enter skips
+/// to the next preset, and with `as_json` set the presets are reported as JSON events instead.
+fn run_queue(
+    cli: &Cli,
+    queued_presets: &[Preset],
+    preset_choices: &[PresetChoice],
+    device_name: Option<&str>,
+    ambient: Option<&Ambient>,
+) -> Result<ExitStatus, Error> {
+    let queue = if queued_presets.is_empty() {
+        let chosen_presets =
+            MultiSelect::new("Choose the presets to queue: ", preset_choices.to_vec())
+                .with_page_size(7)
+                .prompt();
+
+        match chosen_presets {
+            Ok(queue) => queue,
+            Err(err) => {
+                eprintln!(
+                    "There was an error choosing the presets, please try again. {}",
+                    err
+                );
+                return Ok(prompt_error_status(&err));
+            }
+        }
+    } else {
+        queued_presets
+            .iter()
+            .map(|preset| PresetChoice::BuiltIn(*preset))
+            .collect()
+    };
+
+    if queue.is_empty() {
+        eprintln!("Choose at least one preset to queue.");
+        return Ok(ExitStatus::InvalidArguments);
+    }
+
+    let transition = QueueTransition::from_seconds(cli.gap, cli.crossfade);
+    let queue_options: Vec<BinauralPresetGroup> =
+        queue.iter().map(PresetChoice::preset_options).collect();
+    check_transition(&queue_options, transition)?;
+
+    let playback_options = playback_options_from(cli);
+    let warnings: Vec<SettingsWarning> = queue_options
+        .iter()
+        .flat_map(|preset_options| settings_warnings(*preset_options, &playback_options))
+        .collect();
+    if !confirm_warnings(&warnings, cli) {
+        return Ok(ExitStatus::CancelledByUser);
+    }
+
+    let start_time = match start_time_from(cli) {
+        Ok(start_time) => start_time,
+        Err(err) => {
+            eprintln!(
+                "There was an error choosing the start time, please try again. {}",
+                err
+            );
+            return Ok(prompt_error_status(&err));
+        }
+    };
+    if let Some(start_time) = start_time
+        && wait_for_start(start_time)? == WaitOutcome::Cancelled
+    {
+        return Ok(ExitStatus::CancelledByUser);
+    }
+
+    let output = cli.audio_output(device_name);
+    let queue_stop = CancelToken::new();
+    // The session the keyboard skips, replaced each time the queue moves on.
+    let current_session = Arc::new(Mutex::new(CancelToken::new()));
+
+    if !cli.json {
+        println!("Playing {} presets {}.", queue.len(), transition);
+        println!(
+            "Press Enter to stop playback, or type n and press Enter to skip to the next preset."
+        );
+        listen_for_queue_keys(queue_stop.clone(), Arc::clone(&current_session));
+    }
+
+    // When crossfading, the session before keeps fading out while the next one plays.
+    let mut fading_out: Option<QueuedSession> = None;
+
+    for (index, choice) in queue.iter().enumerate() {
+        if index > 0
+            && let QueueTransition::Gap(gap) = transition
+            && !gap.is_zero()
+        {
+            if !cli.json {
+                println!("Next up in {} seconds: {}", gap.as_secs(), choice.name());
+            }
+            if !wait_for_next(gap, &queue_stop) {
+                break;
+            }
+        }
+        if queue_stop.is_cancelled() {
+            break;
+        }
+
+        let preset_options = choice.preset_options();
+        let hand_over = if index + 1 < queue.len() {
+            transition.hand_over()
+        } else {
+            StdDuration::ZERO
+        };
+        let mut session = QueuedSession {
+            player: BinauralPlayer::start_with_ambient(
+                preset_options,
+                queue_playback_options(playback_options, transition, index, queue.len()),
+                ambient.cloned(),
+                &output,
+            )?,
+            name: choice.name(),
+            preset_options,
+            started_at: Timestamp::now(),
+        };
+        *current_session.lock().unwrap() = session.player.cancel_token();
+
+        if cli.json {
+            let mut fields = session.player.settings().to_json();
+            fields["queue_position"] = json!(index + 1);
+            fields["queue_length"] = json!(queue.len());
+            emit_event("session_started", fields);
+            report_progress(&mut session.player, hand_over)?;
+        } else {
+            println!(
+                "\n{} of {}: {}",
+                index + 1,
+                queue.len(),
+                choice.description().italic()
+            );
+            println!("{}", session.player.settings());
+            show_progress(&mut session.player, hand_over)?;
+        }
+
+        if let Some(faded_out) = fading_out.take() {
+            finish_queued_session(faded_out, cli.json)?;
+        }
+
+        if session.player.is_playing() {
+            fading_out = Some(session);
+        } else if finish_queued_session(session, cli.json)? == PlaybackOutcome::Cancelled
+            && !queue_stop.is_cancelled()
+            && !cli.json
+        {
+            println!("Skipped to the next preset.");
+        }
+    }
+
+    if let Some(faded_out) = fading_out.take() {
+        finish_queued_session(faded_out, cli.json)?;
+    }
+
+    if queue_stop.is_cancelled() {
+        if !cli.json {
+            println!("Playback cancelled by user.");
+        }
+        Ok(ExitStatus::CancelledByUser)
+    } else {
+        Ok(ExitStatus::Completed)
+    }
+}
+
+/// Waits for a session of the queue to end, reports it in `--json` mode and adds it to the history.
+fn finish_queued_session(session: QueuedSession, as_json: bool) -> Result<PlaybackOutcome, Error> {
+    let session_id = session.player.session_id();
+    let played = session.player.played();
+    let outcome = wait_reporting_health(session.player, as_json)?;
+
+    if as_json {
+        emit_session_event(
+            "session_ended",
+            session_id,
+            json!({ "outcome": outcome.name(), "played_seconds": played.as_secs() }),
+        );
+    }
+    record_session(&SessionRecord {
+        session_id,
+        started_at: session.started_at,
+        name: session.name,
+        preset_options: session.preset_options,
+        requested: Some(StdDuration::from_secs(
+            session.preset_options.duration.to_minutes() as u64 * 60,
+        )),
+        played,
+        outcome,
+    });
+
+    Ok(outcome)
+}
+
+/// Starts a thread that reads the keyboard while a queue plays. The terminal hands over whole lines,
+/// so Enter on its own stops the queue and a line starting with `n` skips the session playing.
+fn listen_for_queue_keys(queue_stop: CancelToken, current_session: Arc<Mutex<CancelToken>>) {
+    std::thread::spawn(move || {
+        let mut skip = false;
+
+        loop {
+            match event::read() {
+                Ok(Event::Key(key_event)) if key_event.kind == KeyEventKind::Press => {
+                    match key_event.code {
+                        KeyCode::Char('n') | KeyCode::Char('N') => skip = true,
+                        KeyCode::Enter => {
+                            if !skip {
+                                queue_stop.cancel();
+                            }
+                            current_session
+                                .lock()
+                                .unwrap()
+                                .cancel_with_fade(STOP_FADE_OUT);
+                            skip = false;
+                        }
+                        _ => {}
                     }
                 }
                 Ok(_) => {} // Ignore other events
@@ -82,10 +907,130 @@ fn run_binaural_beat(preset_options: BinauralPresetGroup) -> Result<(), Error> {
             }
         }
     });
+}
 
-    generate_binaural_beats(preset_options, Arc::clone(&cancel_token))?;
+/// Adds the session to the history that the `stats` command reads.
+/// The session has already played, so failing to record it is only reported.
+fn record_session(record: &SessionRecord) {
+    let Some(path) = history_path() else {
+        eprintln!("The session can't be added to the history, the data directory can't be found.");
+        return;
+    };
 
-    Ok(())
+    if let Err(err) = append_record(&path, record) {
+        eprintln!("The session can't be added to the history. {}", err);
+    }
+}
+
+/// A helper function that prints the given presets as a table with their settings and tags.
+fn print_preset_list(presets: &[Preset]) {
+    println!(
+        "{:<32}{:>12}{:>11}{:>10}  Tags",
+        "Preset", "Carrier", "Beat", "Duration"
+    );
+
+    for preset in presets {
+        let preset_options = BinauralPresetGroup::from(*preset);
+        println!(
+            "{:<32}{:>9.2} Hz{:>8.2} Hz{:>6} min  {}",
+            preset.to_string(),
+            preset_options.carrier.to_hz(),
+            preset_options.beat.to_hz(),
+            preset_options.duration.to_minutes(),
+            preset.tags().join(", ")
+        );
+    }
+}
+
+/// A helper function that prints the time listened in total, then as tables per preset and per week.
+fn print_stats(stats: &ListeningStats) {
+    if stats.total.sessions == 0 {
+        println!("No sessions have been played yet.");
+        return;
+    }
+
+    println!(
+        "Listened for {} over {} sessions.\n",
+        format_clock(stats.total.listened),
+        stats.total.sessions
+    );
+
+    println!("{:<32}{:>10}{:>12}", "Preset", "Sessions", "Listened");
+    for (name, total) in &stats.per_preset {
+        println!(
+            "{:<32}{:>10}{:>12}",
+            name,
+            total.sessions,
+            format_clock(total.listened)
+        );
+    }
+
+    println!("\n{:<32}{:>10}{:>12}", "Week", "Sessions", "Listened");
+    for (week, total) in &stats.per_week {
+        println!(
+            "{:<32}{:>10}{:>12}",
+            week,
+            total.sessions,
+            format_clock(total.listened)
+        );
+    }
+}
+
+/// A helper function that prints the given output devices as a table with one row per supported configuration,
+/// or as a JSON array. The default device is marked with `*`.
+fn print_device_list(devices: &[OutputDeviceInfo], as_json: bool) {
+    if as_json {
+        let devices: Vec<_> = devices.iter().map(OutputDeviceInfo::to_json).collect();
+        println!("{:#}", serde_json::Value::Array(devices));
+        return;
+    }
+
+    println!(
+        "{:<8}{:<40}{:>9}{:>20}  Format",
+        "Host", "Device", "Channels", "Sample Rates"
+    );
+
+    for device in devices {
+        let name = if device.is_default {
+            format!("{} *", device.name)
+        } else {
+            device.name.clone()
+        };
+
+        if device.configs.is_empty() {
+            println!("{:<8}{:<40}{:>9}{:>20}  -", device.host, name, "-", "-");
+        }
+
+        for config in &device.configs {
+            let sample_rates = if config.min_sample_rate == config.max_sample_rate {
+                format!("{} Hz", config.min_sample_rate)
+            } else {
+                format!("{}-{} Hz", config.min_sample_rate, config.max_sample_rate)
+            };
+
+            println!(
+                "{:<8}{:<40}{:>9}{:>20}  {}",
+                device.host, name, config.channels, sample_rates, config.sample_format
+            );
+        }
+    }
+}
+
+/// A helper function that prints one line for each check and returns the status for the whole report,
+/// which fails if any of the checks did.
+fn print_check_report(reports: &[CheckReport]) -> ExitStatus {
+    for report in reports {
+        println!("{}", report);
+    }
+
+    let failed = reports.iter().filter(|report| !report.passed()).count();
+    if failed == 0 {
+        println!("All {} checks passed.", reports.len());
+        ExitStatus::Completed
+    } else {
+        println!("{} of {} checks failed.", failed, reports.len());
+        ExitStatus::Failure
+    }
 }
 
 /// A helper function that just prints out the program name and author.
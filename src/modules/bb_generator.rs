@@ -1,147 +1,3035 @@
 //! A module that contains the bulk of the code that allows the program to run.
 
 use anyhow::Error;
-use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use std::sync::{Arc, Mutex};
-use std::thread;
+use rtrb::{Producer, RingBuffer};
+use serde_json::{Value, json};
+use std::fmt;
+use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
 use std::time::{Duration as StdDuration, Instant}; // Alias to avoid conflict with enum variant
+use uuid::Uuid;
 
 //Cancellation support
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 
+use crate::modules::cancel_token::CancelToken;
 use crate::modules::duration::duration_common::ToMinutes;
+use crate::modules::exit_status::PlaybackError;
+use crate::modules::frequency::easing::Easing;
 use crate::modules::frequency::frequency_common::ToFrequency;
+use crate::modules::output::cpal_output::CpalSink;
+use crate::modules::output::output_common::{AudioOutput, AudioSink, panic_safe};
+use crate::modules::output::output_watchdog::{
+    FrameCounter, OutputHealth, OutputWatchdog, PeakMeter,
+};
 use crate::modules::preset::BinauralPresetGroup;
+use crate::modules::progress::SessionProgress;
+use crate::modules::synth::ambient::{Ambient, AmbientLoop};
+use crate::modules::synth::limiter::soft_limit;
+use crate::modules::synth::oscillator::Oscillator;
+use crate::modules::synth::waveform::Waveform;
 
-/// A function that wats for the chosen time limit to end before exiting.
-/// The function will constantly check if the user wants to stop running of the program.
-///
-fn wait_until_end(cancel_token: Arc<AtomicBool>, duration_minutes: u32) {
-    let total_duration = StdDuration::from_secs((duration_minutes * 60) as u64);
-    let start_time = Instant::now();
+/// How many seconds of audio the ring buffer between the synthesis thread and the output stream can hold.
+const RING_BUFFER_SECONDS: f64 = 0.2;
+
+/// How long the synthesis thread sleeps when the ring buffer is already full.
+const SYNTHESIS_IDLE_SLEEP: StdDuration = StdDuration::from_millis(5);
+
+/// How much of the distance to a new volume level is covered on each sample, so volume changes don't click.
+const VOLUME_SMOOTHING: f32 = 0.001;
+
+/// How often a waiting player checks whether the session has ended.
+const WAIT_POLL_INTERVAL: StdDuration = StdDuration::from_millis(500);
+
+/// How often a player sending session events checks on the session, progress is only sent once a second.
+pub(crate) const EVENT_POLL_INTERVAL: StdDuration = StdDuration::from_millis(100);
+
+/// How long a repeating ramp takes to glide from its end beat back to its starting beat.
+const REPEAT_GLIDE: StdDuration = StdDuration::from_secs(5);
+
+/// How long the ears take to glide over to each other's frequency when the beat direction alternates.
+const ALTERNATION_CROSSFADE: StdDuration = StdDuration::from_secs(5);
+
+/// How many times opening a new output device is tried after the old one was lost before the session gives up.
+const RECONNECT_ATTEMPTS: u32 = 5;
+
+/// How long to leave between attempts, a device that was just lost can take a moment to be replaced as the default.
+const RECONNECT_DELAY: StdDuration = StdDuration::from_secs(1);
+
+/// How far the beat frequency moves each time it is nudged up or down while playing.
+pub const BEAT_NUDGE_HZ: f32 = 0.5;
+
+/// How fast the beat glides to a nudged frequency, so that a nudge is a short sweep rather than a jump.
+const BEAT_GLIDE_HZ_PER_SECOND: f64 = 1.0;
+
+/// The slowest and fastest the tone can move between the ears in bilateral mode, in sweeps per second.
+pub const BILATERAL_RATE_RANGE: std::ops::RangeInclusive<f32> = 0.5..=2.0;
+
+/// The peak amplitude of each ear at full volume, leaving headroom so the tones don't clip.
+pub(crate) const CHANNEL_GAIN: f32 = 0.5;
+
+/// A volume level between 0.0 and 1.0 that can be changed while the synthesis thread is playing it.
+#[derive(Debug, Clone)]
+pub struct SharedVolume(Arc<AtomicU32>);
+
+impl SharedVolume {
+    pub fn new(level: f32) -> Self {
+        SharedVolume(Arc::new(AtomicU32::new(level.clamp(0.0, 1.0).to_bits())))
+    }
 
-    while start_time.elapsed() < total_duration {
-        // Break the loop immediately if the user requested cancellation
-        if cancel_token.load(Ordering::Relaxed) {
-            println!("Playback cancelled by user.");
-            break;
+    /// Returns the current volume level.
+    pub fn get(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed))
+    }
+
+    /// Changes the volume level, clamping it between 0.0 and 1.0.
+    pub fn set(&self, level: f32) {
+        self.0
+            .store(level.clamp(0.0, 1.0).to_bits(), Ordering::Relaxed);
+    }
+}
+
+/// How far the beat has been nudged away from the session's own beat, in Hz, shared with the synthesis thread
+/// so that it can be changed while playing.
+#[derive(Debug, Clone, Default)]
+pub struct SharedBeatOffset(Arc<AtomicU32>);
+
+impl SharedBeatOffset {
+    /// Returns the current offset, negative when the beat has been nudged down.
+    pub fn get(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed))
+    }
+
+    pub fn set(&self, offset_hz: f32) {
+        self.0.store(offset_hz.to_bits(), Ordering::Relaxed);
+    }
+}
+
+/// How many times a session plays back to back.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SessionRepeat {
+    /// The session plays this many times, at least once.
+    Times(u32),
+    /// The session keeps starting over until it is stopped.
+    Forever,
+}
+
+/// This formatter returns how many times the session plays.
+impl fmt::Display for SessionRepeat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionRepeat::Times(1) => write!(f, "once"),
+            SessionRepeat::Times(times) => write!(f, "{} times", times),
+            SessionRepeat::Forever => write!(f, "until stopped"),
         }
-        // Sleep for a short period to avoid high CPU usage
-        thread::sleep(StdDuration::from_millis(500));
     }
 }
 
-/// Generates and plays binaural beat tones based on specified carrier frequency,
-/// beat frequency, and duration.
-///
-/// # Arguments
-/// - `preset_options`: Specifies the binaural beat options choosen by the user to execute.
-/// - `cancel_token`: An atomic instance of a boolean that controls the stopping of the program before the timelimit.
-///
-/// # Returns
-/// `Result<(), anyhow::Error>` indicating success or failure.
-pub fn generate_binaural_beats(
+/// How the tones played in the two ears make the beat.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BeatMode {
+    /// Each ear hears its own frequency and the beat is the difference between them.
+    Binaural,
+    /// A single tone at the carrier frequency sweeps from the left ear to the right and back this many times a second,
+    /// the alternating stimulation used in EMDR. The preset's beat isn't played.
+    Bilateral { rate_hz: f32 },
+}
+
+/// This structure groups the playback settings that aren't part of a preset.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlaybackOptions {
+    /// The output volume between 0.0 and 1.0, where 1.0 is the loudest the tones are played.
+    pub volume: f32,
+    /// How long the beat takes to grow from 0 Hz to its full frequency at the start of the session.
+    pub ease_in: StdDuration,
+    /// The shape of the beat's growth during the ease in.
+    pub ease_in_curve: Easing,
+    /// How often the ears swap which one carries the higher frequency, if they do at all.
+    pub alternate_every: Option<StdDuration>,
+    /// The beat frequency to move towards over the whole session, if the beat should change at all.
+    pub ramp_to_hz: Option<f32>,
+    /// The shape of the beat's change when ramping.
+    pub ramp_curve: Easing,
+    /// The shape of the tone played in each ear.
+    pub waveform: Waveform,
+    /// How long the tones take to fade up from silence at the start of the session.
+    pub fade_in: StdDuration,
+    /// How many times the session plays back to back, without a gap in between.
+    pub repeat: SessionRepeat,
+    /// How long the tones take to fade to silence at the end of the session, `None` uses the preset's own fade.
+    pub fade_out: Option<StdDuration>,
+    /// How the tones in the two ears make the beat.
+    pub beat_mode: BeatMode,
+}
+
+/// The default options play at full volume with the full beat from the start.
+impl Default for PlaybackOptions {
+    fn default() -> Self {
+        PlaybackOptions {
+            volume: 1.0,
+            ease_in: StdDuration::ZERO,
+            ease_in_curve: Easing::Linear,
+            alternate_every: None,
+            ramp_to_hz: None,
+            ramp_curve: Easing::Linear,
+            waveform: Waveform::Sine,
+            fade_in: StdDuration::ZERO,
+            repeat: SessionRepeat::Times(1),
+            fade_out: None,
+            beat_mode: BeatMode::Binaural,
+        }
+    }
+}
+
+/// This structure describes how the beat frequency moves from its starting value to another one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct BeatRamp {
+    /// The beat frequency reached at the end of the ramp.
+    pub end_beat_hz: f32,
+    /// How long the ramp takes, the beat stays at `end_beat_hz` afterwards unless the ramp repeats.
+    pub length: StdDuration,
+    pub curve: Easing,
+    /// Whether the ramp starts over after each length, gliding back to the starting beat.
+    pub repeat: bool,
+}
+
+/// This structure describes how the tones fade to silence at the end of the session.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct EndFade {
+    /// When the session ends, counted from its start, which is when the tones fall silent.
+    pub ends_at: StdDuration,
+    /// How long before the end the fade starts.
+    pub length: StdDuration,
+}
+
+/// This structure describes the pair of tones the synthesis thread plays.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct ToneSettings {
+    pub carrier_hz: f32,
+    pub beat_hz: f32,
+    /// How long the beat takes to grow from 0 Hz to `beat_hz`, both ears start on the carrier.
+    pub ease_in: StdDuration,
+    /// The shape of the beat's growth during the ease in.
+    pub ease_in_curve: Easing,
+    /// How often the ears swap which one carries the higher frequency, the right ear starts higher.
+    pub alternate_every: Option<StdDuration>,
+    /// How the beat moves away from `beat_hz` while playing, if it does at all.
+    pub ramp: Option<BeatRamp>,
+    /// The shape of the tone played in each ear.
+    pub waveform: Waveform,
+    /// How long the tones take to fade up from silence at the start of the session.
+    pub fade_in: StdDuration,
+    /// How the tones fade out at the end of the session, if they do at all.
+    pub end_fade: Option<EndFade>,
+    /// How the tones in the two ears make the beat.
+    pub beat_mode: BeatMode,
+}
+
+impl ToneSettings {
+    /// Returns a steady pair of sine tones for the carrier and beat, with no ease in, fades, ramp or alternation.
+    pub fn steady(carrier_hz: f32, beat_hz: f32) -> Self {
+        ToneSettings {
+            carrier_hz,
+            beat_hz,
+            ease_in: StdDuration::ZERO,
+            ease_in_curve: Easing::Linear,
+            alternate_every: None,
+            ramp: None,
+            waveform: Waveform::Sine,
+            fade_in: StdDuration::ZERO,
+            end_fade: None,
+            beat_mode: BeatMode::Binaural,
+        }
+    }
+}
+
+/// Generates the left and right ear tones one frame at a time.
+/// Each ear keeps its own wrapped phase so that the frequencies can change smoothly while playing.
+struct ToneGenerator {
+    settings: ToneSettings,
+    sample_rate: f64,
+    ease_in_frames: u64,
+    alternation_frames: Option<u64>,
+    ramp_frames: u64,
+    fade_in_frames: u64,
+    /// The frame the session ends on and how many frames the fade before it takes.
+    end_fade_frames: Option<(u64, u64)>,
+    frame: u64,
+    /// How far the beat is nudged on the current frame, gliding towards `target_beat_offset_hz`.
+    beat_offset_hz: f64,
+    target_beat_offset_hz: f64,
+    left: Oscillator,
+    right: Oscillator,
+    /// The soundscape looping underneath the tones, if there is one.
+    ambient: Option<AmbientLoop>,
+}
+
+impl ToneGenerator {
+    fn new(settings: ToneSettings, sample_rate: f64) -> Self {
+        ToneGenerator {
+            settings,
+            sample_rate,
+            ease_in_frames: (settings.ease_in.as_secs_f64() * sample_rate) as u64,
+            alternation_frames: settings
+                .alternate_every
+                .map(|every| ((every.as_secs_f64() * sample_rate) as u64).max(1)),
+            ramp_frames: settings.ramp.map_or(1, |ramp| {
+                ((ramp.length.as_secs_f64() * sample_rate) as u64).max(1)
+            }),
+            fade_in_frames: (settings.fade_in.as_secs_f64() * sample_rate) as u64,
+            end_fade_frames: settings.end_fade.map(|fade| {
+                let end_frame = (fade.ends_at.as_secs_f64() * sample_rate) as u64;
+                let fade_frames = (fade.length.as_secs_f64() * sample_rate) as u64;
+                (end_frame, fade_frames.min(end_frame).max(1))
+            }),
+            frame: 0,
+            beat_offset_hz: 0.0,
+            target_beat_offset_hz: 0.0,
+            left: Oscillator::new(settings.waveform),
+            right: Oscillator::new(settings.waveform),
+            ambient: None,
+        }
+    }
+
+    /// Returns the beat frequency for the current frame.
+    /// The beat follows the ramp if there is one, and grows from 0 Hz along the ease in curve at the start.
+    fn current_beat_hz(&self) -> f64 {
+        let beat_hz = match self.settings.ramp {
+            Some(ramp) => self.ramp_beat_hz(ramp),
+            None => self.settings.beat_hz as f64,
+        };
+
+        if self.frame >= self.ease_in_frames {
+            beat_hz
+        } else {
+            let progress = self.frame as f64 / self.ease_in_frames as f64;
+            self.settings
+                .ease_in_curve
+                .interpolate(0.0, beat_hz, progress)
+        }
+    }
+
+    /// Returns the ramp's beat frequency for the current frame.
+    /// A repeating ramp starts over after each length, gliding back from the end beat over the first few seconds.
+    fn ramp_beat_hz(&self, ramp: BeatRamp) -> f64 {
+        let start_beat_hz = self.settings.beat_hz as f64;
+        let end_beat_hz = ramp.end_beat_hz as f64;
+
+        if !ramp.repeat || self.frame < self.ramp_frames {
+            let progress = self.frame as f64 / self.ramp_frames as f64;
+            return ramp.curve.interpolate(start_beat_hz, end_beat_hz, progress);
+        }
+
+        let position = self.frame % self.ramp_frames;
+        let ramp_hz = ramp.curve.interpolate(
+            start_beat_hz,
+            end_beat_hz,
+            position as f64 / self.ramp_frames as f64,
+        );
+        let glide_frames =
+            ((REPEAT_GLIDE.as_secs_f64() * self.sample_rate) as u64).min(self.ramp_frames / 2);
+
+        if position >= glide_frames {
+            ramp_hz
+        } else {
+            let progress = position as f64 / glide_frames as f64;
+            Easing::EaseInOut.interpolate(end_beat_hz, ramp_hz, progress)
+        }
+    }
+
+    /// Returns 1.0 while the right ear carries the higher frequency and -1.0 while the left ear does.
+    /// When the ears swap, the direction glides between the two over the crossfade so the tones never jump.
+    fn beat_direction(&self) -> f64 {
+        let Some(period_frames) = self.alternation_frames else {
+            return 1.0;
+        };
+
+        let segment = self.frame / period_frames;
+        let position = self.frame % period_frames;
+        let direction = if segment.is_multiple_of(2) { 1.0 } else { -1.0 };
+        let crossfade_frames = ((ALTERNATION_CROSSFADE.as_secs_f64() * self.sample_rate) as u64)
+            .min(period_frames / 2);
+
+        if segment == 0 || position >= crossfade_frames {
+            direction
+        } else {
+            let progress = position as f64 / crossfade_frames as f64;
+            Easing::EaseInOut.interpolate(-direction, direction, progress)
+        }
+    }
+
+    /// Moves the nudged beat one frame closer to its target, at the glide rate.
+    fn glide_beat_offset(&mut self) {
+        let max_step = BEAT_GLIDE_HZ_PER_SECOND / self.sample_rate;
+        self.beat_offset_hz +=
+            (self.target_beat_offset_hz - self.beat_offset_hz).clamp(-max_step, max_step);
+    }
+
+    /// Returns the level of the tones for the current frame, which eases up from silence to 1.0
+    /// over the fade in at the start of the session.
+    fn fade_in_gain(&self) -> f32 {
+        if self.frame >= self.fade_in_frames {
+            1.0
+        } else {
+            let progress = self.frame as f64 / self.fade_in_frames as f64;
+            Easing::EaseInOut.interpolate(0.0, 1.0, progress) as f32
+        }
+    }
+
+    /// Returns the level of the tones for the current frame, which eases from 1.0 down to silence
+    /// over the fade at the end of the session.
+    fn end_fade_gain(&self) -> f32 {
+        let Some((end_frame, fade_frames)) = self.end_fade_frames else {
+            return 1.0;
+        };
+
+        let remaining_frames = end_frame.saturating_sub(self.frame);
+        if remaining_frames >= fade_frames {
+            1.0
+        } else {
+            let progress = remaining_frames as f64 / fade_frames as f64;
+            Easing::EaseInOut.interpolate(0.0, 1.0, progress) as f32
+        }
+    }
+
+    /// Returns the next left and right samples, at full scale between the fades at the start and end of the session.
+    /// The soundscape, if there is one, is mixed in underneath the tones and fades with them.
+    fn next_frame(&mut self) -> (f32, f32) {
+        let (mut left_sample, mut right_sample) = self.next_tones();
+
+        if let Some(ambient) = self.ambient.as_mut() {
+            let (ambient_left, ambient_right) = ambient.next_frame();
+            left_sample += ambient_left;
+            right_sample += ambient_right;
+        }
+
+        let gain = self.fade_in_gain() * self.end_fade_gain();
+        self.frame += 1;
+
+        (left_sample * gain, right_sample * gain)
+    }
+
+    /// Returns the left and right tones for the current frame at full scale.
+    fn next_tones(&mut self) -> (f32, f32) {
+        let carrier_hz = self.settings.carrier_hz as f64;
+
+        if let BeatMode::Bilateral { rate_hz } = self.settings.beat_mode {
+            let sample = self.left.next_sample(carrier_hz, self.sample_rate);
+            let (left_gain, right_gain) =
+                bilateral_gains(self.frame as f64 / self.sample_rate, rate_hz);
+
+            return (sample * left_gain, sample * right_gain);
+        }
+
+        self.glide_beat_offset();
+        // A nudge can't take the beat below zero or either ear below half the carrier.
+        let beat_hz = (self.current_beat_hz() + self.beat_offset_hz).clamp(0.0, carrier_hz)
+            * self.beat_direction();
+
+        let left_sample = self
+            .left
+            .next_sample(carrier_hz - beat_hz / 2.0, self.sample_rate);
+        let right_sample = self
+            .right
+            .next_sample(carrier_hz + beat_hz / 2.0, self.sample_rate);
+
+        (left_sample, right_sample)
+    }
+}
+
+/// Returns the left and right gains for a tone that sweeps between the ears `rate_hz` times a second,
+/// starting fully in the left ear. The gains follow a constant power pan law, so the tone doesn't get
+/// quieter as it passes through the middle.
+fn bilateral_gains(seconds: f64, rate_hz: f32) -> (f32, f32) {
+    let position = (1.0 - (std::f64::consts::TAU * rate_hz as f64 * seconds).cos()) / 2.0;
+    let angle = position * std::f64::consts::FRAC_PI_2;
+
+    (angle.cos() as f32, angle.sin() as f32)
+}
+
+/// The ways a session can finish without an error.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlaybackOutcome {
+    /// The session played for its whole duration.
+    Completed,
+    /// The user stopped the session early.
+    Cancelled,
+}
+
+impl PlaybackOutcome {
+    /// Returns the name the outcome is written as in the session history and the JSON events.
+    pub fn name(&self) -> &'static str {
+        match self {
+            PlaybackOutcome::Completed => "completed",
+            PlaybackOutcome::Cancelled => "cancelled",
+        }
+    }
+}
+
+/// What happens during a session, sent to a front end that shows the session itself instead of the printed output.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SessionEvent {
+    /// How far the session has got, sent once a second.
+    Progress(SessionProgress),
+    /// A repeating session started another round, the first round is 1.
+    RoundStarted(u32),
+    /// The output's health changed.
+    OutputHealth(OutputHealth),
+    /// The session ended, the last event sent.
+    Finished(PlaybackOutcome),
+}
+
+/// Returns the sample for one channel of a frame at the given volume.
+/// Mono gets both ears mixed together and channels past the first two stay silent.
+fn mix_sample(
+    left_sample: f32,
+    right_sample: f32,
+    channels: usize,
+    channel: usize,
+    volume: f32,
+) -> f32 {
+    let sample = match (channels, channel) {
+        (1, _) => (left_sample + right_sample) * 0.5 * CHANNEL_GAIN * volume, // For mono, sum and reduce further
+        (_, 0) => left_sample * CHANNEL_GAIN * volume, // Reduce amplitude to avoid clipping
+        (_, 1) => right_sample * CHANNEL_GAIN * volume,
+        _ => 0.0,
+    };
+
+    // The limiter only bends levels louder than the tones alone, so layers mixed in can't clip.
+    soft_limit(sample)
+}
+
+/// Runs on a dedicated thread and keeps the ring buffer topped up with interleaved samples until told to stop.
+/// Only whole frames are written so that the left and right channels never get out of step.
+fn run_synthesis(
+    mut producer: Producer<f32>,
+    mut generator: ToneGenerator,
+    channels: usize,
+    volume: SharedVolume,
+    beat_offset: SharedBeatOffset,
+    running: Arc<AtomicBool>,
+) {
+    let mut current_volume = volume.get();
+
+    while running.load(Ordering::Relaxed) {
+        let free_frames = producer.slots() / channels;
+
+        if free_frames == 0 {
+            thread::sleep(SYNTHESIS_IDLE_SLEEP);
+            continue;
+        }
+
+        let target_volume = volume.get();
+        generator.target_beat_offset_hz = beat_offset.get() as f64;
+
+        for _ in 0..free_frames {
+            current_volume += (target_volume - current_volume) * VOLUME_SMOOTHING;
+
+            let (left_sample, right_sample) = generator.next_frame();
+
+            for channel in 0..channels {
+                let sample =
+                    mix_sample(left_sample, right_sample, channels, channel, current_volume);
+                // The free slots were counted above, so this can't fail.
+                let _ = producer.push(sample);
+            }
+        }
+    }
+}
+
+/// Fades interleaved frames towards silence for a session that was stopped with a fade.
+/// `faded_frames` counts how far the fade has got across calls, frames past its end are silenced.
+fn apply_stop_fade(samples: &mut [f32], channels: usize, faded_frames: &mut u64, fade_frames: u64) {
+    for frame in samples.chunks_mut(channels) {
+        let progress = (*faded_frames as f64 / fade_frames.max(1) as f64).min(1.0);
+        let gain = Easing::EaseInOut.interpolate(1.0, 0.0, progress) as f32;
+
+        for sample in frame.iter_mut() {
+            *sample *= gain;
+        }
+        *faded_frames += 1;
+    }
+}
+
+/// The audio that is currently being synthesized and handed to a sink.
+pub(crate) struct Playback {
+    synthesis_running: Arc<AtomicBool>,
+    synthesis_thread: JoinHandle<()>,
+    errors: Receiver<Error>,
+    /// How many frames of real audio the sink has taken so far.
+    frames_played: FrameCounter,
+    /// The loudest sample the sink has taken since the watchdog last looked.
+    peak: PeakMeter,
+    /// The volume the synthesis thread is playing at, it can be changed while playing.
+    volume: SharedVolume,
+    /// How far the beat is nudged, it can be changed while playing.
+    beat_offset: SharedBeatOffset,
+    /// The soundscape mixed in underneath the tones, kept so that it can be started again on another device.
+    ambient: Option<Ambient>,
+    /// The settings the tones were started with, kept so that they can be started again on another device.
+    settings: ToneSettings,
+}
+
+impl Playback {
+    /// Stops the synthesis thread and waits for it to finish.
+    pub(crate) fn stop(self) -> Result<(), Error> {
+        self.synthesis_running.store(false, Ordering::Relaxed);
+        self.synthesis_thread
+            .join()
+            .map_err(|_| anyhow::anyhow!("The synthesis thread stopped unexpectedly."))
+    }
+}
+
+/// Validates the chosen settings and turns them into the settings of the tones played in each ear.
+fn session_tone_settings(
     preset_options: BinauralPresetGroup,
-    cancel_token: Arc<AtomicBool>,
-) -> Result<(), Error> {
+    playback_options: PlaybackOptions,
+) -> Result<ToneSettings, Error> {
     // Extract concrete values from generic parameters
     let carrier_hz = preset_options.carrier.to_hz();
     let beat_hz = preset_options.beat.to_hz();
     let duration_minutes = preset_options.duration.to_minutes();
 
     // Calculate left and right ear frequencies
-    let f_left = carrier_hz - (beat_hz / 2.0);
-    let f_right = carrier_hz + (beat_hz / 2.0);
+    let (f_left, f_right) = ear_frequencies(carrier_hz, beat_hz, playback_options.beat_mode);
 
     // Basic validation for frequencies
     if f_left <= 0.0 || f_right <= 0.0 {
-        return Err(anyhow::anyhow!(
+        return Err(PlaybackError::InvalidSettings(
             "Calculated frequency for one ear is zero or negative. Adjust carrier or beat frequency."
-        ));
+                .to_string(),
+        )
+        .into());
     }
     if duration_minutes == 0 {
-        return Err(anyhow::anyhow!(
-            "Duration must be greater than zero minutes."
-        ));
+        return Err(PlaybackError::InvalidSettings(
+            "Duration must be greater than zero minutes.".to_string(),
+        )
+        .into());
+    }
+    if playback_options.repeat == SessionRepeat::Times(0) {
+        return Err(PlaybackError::InvalidSettings(
+            "A session has to play at least once.".to_string(),
+        )
+        .into());
+    }
+    if let BeatMode::Bilateral { rate_hz } = playback_options.beat_mode
+        && !BILATERAL_RATE_RANGE.contains(&rate_hz)
+    {
+        return Err(PlaybackError::InvalidSettings(format!(
+            "The bilateral panning rate must be between {} Hz and {} Hz.",
+            BILATERAL_RATE_RANGE.start(),
+            BILATERAL_RATE_RANGE.end()
+        ))
+        .into());
+    }
+    if let Some(ramp_to_hz) = playback_options.ramp_to_hz
+        && (ramp_to_hz < 0.0 || carrier_hz - ramp_to_hz / 2.0 <= 0.0)
+    {
+        return Err(PlaybackError::InvalidSettings(
+            "The beat frequency to ramp to must not be negative or take either ear to zero. Adjust the ramp or carrier frequency."
+                .to_string(),
+        )
+        .into());
     }
 
-    println!("--- Binaural Beat Settings ---");
-    println!("Preset {}", preset_options.preset);
-    println!("Carrier Frequency: {:.2} Hz", carrier_hz);
-    println!("Beat Frequency: {:.2} Hz", beat_hz);
-    println!("Left Ear Frequency: {:.2} Hz", f_left);
-    println!("Right Ear Frequency: {:.2} Hz", f_right);
-    println!("Duration: {} minutes", duration_minutes);
-    println!("----------------------------");
-
-    let host = cpal::default_host();
-
-    let device = host
-        .default_output_device()
-        .ok_or_else(|| anyhow::anyhow!("No output device available."))?;
-
-    let config = device.default_output_config()?;
-
-    let sample_rate_val = config.sample_rate().0 as f64;
-    let channels_val = config.channels() as usize;
-
-    let sample_clock_left = Arc::new(Mutex::new(0f64));
-    let sample_clock_right = Arc::new(Mutex::new(0f64));
-
-    let sample_clock_left_for_closure = Arc::clone(&sample_clock_left);
-    let sample_clock_right_for_closure = Arc::clone(&sample_clock_right);
-    let stream_cancel_token = Arc::clone(&cancel_token); // Clone for the stream closure
-
-    let stream = device.build_output_stream(
-        &config.clone().into(), // Clone config for the stream builder
-        move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
-            // Check the token's state inside the audio loop
-            if stream_cancel_token.load(Ordering::Relaxed) {
-                // If the token is true, fill the buffer with silence and return
-                for frame in data.chunks_mut(channels_val) {
-                    if channels_val == 2 {
-                        frame[0] = 0.0;
-                        frame[1] = 0.0;
-                    } else {
-                        frame[0] = 0.0;
-                    }
-                }
-                return;
-            }
+    let fade_out = playback_options
+        .fade_out
+        .unwrap_or_else(|| preset_options.preset.end_fade());
+
+    Ok(ToneSettings {
+        carrier_hz,
+        beat_hz,
+        ease_in: playback_options.ease_in,
+        ease_in_curve: playback_options.ease_in_curve,
+        alternate_every: playback_options.alternate_every,
+        waveform: playback_options.waveform,
+        fade_in: playback_options.fade_in,
+        ramp: playback_options.ramp_to_hz.map(|end_beat_hz| BeatRamp {
+            end_beat_hz,
+            length: StdDuration::from_secs(duration_minutes as u64 * 60),
+            curve: playback_options.ramp_curve,
+            repeat: playback_options.repeat != SessionRepeat::Times(1),
+        }),
+        // A session that repeats until it's stopped has no end to fade towards.
+        end_fade: match playback_options.repeat {
+            SessionRepeat::Times(times) if !fade_out.is_zero() => Some(EndFade {
+                ends_at: StdDuration::from_secs(duration_minutes as u64 * 60 * times as u64),
+                length: fade_out,
+            }),
+            _ => None,
+        },
+        beat_mode: playback_options.beat_mode,
+    })
+}
+
+/// Returns the frequencies the left and right ears play at, which are both the carrier in bilateral mode.
+fn ear_frequencies(carrier_hz: f32, beat_hz: f32, beat_mode: BeatMode) -> (f32, f32) {
+    match beat_mode {
+        BeatMode::Binaural => (carrier_hz - beat_hz / 2.0, carrier_hz + beat_hz / 2.0),
+        BeatMode::Bilateral { .. } => (carrier_hz, carrier_hz),
+    }
+}
 
-            let mut current_sample_clock_left = sample_clock_left_for_closure.lock().unwrap();
-            let mut current_sample_clock_right = sample_clock_right_for_closure.lock().unwrap();
+/// The settings a session plays with, shown to the user before it starts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SessionSettings {
+    pub session_id: Uuid,
+    pub preset_options: BinauralPresetGroup,
+    pub playback_options: PlaybackOptions,
+    /// How long the tones fade out over at the end of the session, if they do.
+    pub fade_out: Option<StdDuration>,
+}
 
-            for frame in data.chunks_mut(channels_val) {
-                //Always keep the final sample outputs as f32 but make the calculations using f64 so that we don't lose the signal.
-                let left_sample =
-                    ((2.0 * std::f64::consts::PI * f_left as f64 * *current_sample_clock_left
-                        / sample_rate_val)
-                        .sin()) as f32;
-                *current_sample_clock_left += 1.0;
+impl SessionSettings {
+    /// Returns the settings of a session, or the reason they can't be played.
+    fn new(
+        session_id: Uuid,
+        preset_options: BinauralPresetGroup,
+        playback_options: PlaybackOptions,
+    ) -> Result<Self, Error> {
+        let tone_settings = session_tone_settings(preset_options, playback_options)?;
 
-                let right_sample =
-                    ((2.0 * std::f64::consts::PI * f_right as f64 * *current_sample_clock_right
-                        / sample_rate_val)
-                        .sin()) as f32;
-                *current_sample_clock_right += 1.0;
+        Ok(SessionSettings {
+            session_id,
+            preset_options,
+            playback_options,
+            fade_out: tone_settings.end_fade.map(|end_fade| end_fade.length),
+        })
+    }
 
-                if channels_val == 2 {
-                    frame[0] = left_sample * 0.5; // Reduce amplitude to avoid clipping
-                    frame[1] = right_sample * 0.5;
-                } else {
-                    frame[0] = (left_sample + right_sample) * 0.25; // For mono, sum and reduce further
-                }
+    /// Returns the settings as a JSON object, with the frequency each ear plays at.
+    pub fn to_json(&self) -> Value {
+        let carrier_hz = self.preset_options.carrier.to_hz();
+        let beat_hz = self.preset_options.beat.to_hz();
+        let playback_options = self.playback_options;
+        let (left_hz, right_hz) = ear_frequencies(carrier_hz, beat_hz, playback_options.beat_mode);
+
+        json!({
+            "session_id": self.session_id.to_string(),
+            "preset": self.preset_options.preset.slug(),
+            "carrier_hz": carrier_hz,
+            "beat_hz": beat_hz,
+            "left_hz": left_hz,
+            "right_hz": right_hz,
+            "bilateral_rate_hz": match playback_options.beat_mode {
+                BeatMode::Binaural => None,
+                BeatMode::Bilateral { rate_hz } => Some(rate_hz),
+            },
+            "duration_minutes": self.preset_options.duration.to_minutes(),
+            "volume": playback_options.volume,
+            "waveform": playback_options.waveform.to_string(),
+            "ease_in_seconds": playback_options.ease_in.as_secs(),
+            "ramp_to_hz": playback_options.ramp_to_hz,
+            "repeat": match playback_options.repeat {
+                SessionRepeat::Times(times) => Some(times),
+                SessionRepeat::Forever => None,
+            },
+            "alternate_every_seconds": playback_options.alternate_every.map(|every| every.as_secs()),
+            "fade_in_seconds": playback_options.fade_in.as_secs(),
+            "fade_out_seconds": self.fade_out.map(|fade_out| fade_out.as_secs()),
+        })
+    }
+}
+
+/// This formatter lists the settings one per line, leaving out the options that aren't in use.
+impl fmt::Display for SessionSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let carrier_hz = self.preset_options.carrier.to_hz();
+        let beat_hz = self.preset_options.beat.to_hz();
+        let playback_options = self.playback_options;
+
+        writeln!(f, "--- Binaural Beat Settings ---")?;
+        writeln!(f, "Session ID: {}", self.session_id)?;
+        writeln!(f, "Preset {}", self.preset_options.preset)?;
+        writeln!(f, "Carrier Frequency: {:.2} Hz", carrier_hz)?;
+        match playback_options.beat_mode {
+            BeatMode::Binaural => {
+                writeln!(f, "Beat Frequency: {:.2} Hz", beat_hz)?;
+                writeln!(
+                    f,
+                    "Left Ear Frequency: {:.2} Hz",
+                    carrier_hz - (beat_hz / 2.0)
+                )?;
+                writeln!(
+                    f,
+                    "Right Ear Frequency: {:.2} Hz",
+                    carrier_hz + (beat_hz / 2.0)
+                )?;
             }
-        },
-        |err| eprintln!("An error occurred on stream: {}", err),
+            BeatMode::Bilateral { rate_hz } => {
+                writeln!(
+                    f,
+                    "Bilateral: the tone sweeps between the ears {:.2} times a second",
+                    rate_hz
+                )?;
+            }
+        }
+        writeln!(
+            f,
+            "Duration: {} minutes",
+            self.preset_options.duration.to_minutes()
+        )?;
+        writeln!(f, "Volume: {:.0}%", playback_options.volume * 100.0)?;
+        if playback_options.waveform != Waveform::Sine {
+            writeln!(f, "Waveform: {}", playback_options.waveform)?;
+        }
+        if !playback_options.ease_in.is_zero() {
+            writeln!(
+                f,
+                "Ease In: {} seconds ({})",
+                playback_options.ease_in.as_secs(),
+                playback_options.ease_in_curve
+            )?;
+        }
+        if let Some(ramp_to_hz) = playback_options.ramp_to_hz {
+            writeln!(
+                f,
+                "Ramp: {:.2} Hz to {:.2} Hz over the session ({})",
+                beat_hz, ramp_to_hz, playback_options.ramp_curve
+            )?;
+        }
+        if playback_options.repeat != SessionRepeat::Times(1) {
+            writeln!(f, "Repeat: {}", playback_options.repeat)?;
+        }
+        if let Some(alternate_every) = playback_options.alternate_every {
+            writeln!(
+                f,
+                "Alternate Ears: every {} minutes",
+                alternate_every.as_secs() / 60
+            )?;
+        }
+        if !playback_options.fade_in.is_zero() {
+            writeln!(
+                f,
+                "Fade In: over the first {} seconds",
+                playback_options.fade_in.as_secs()
+            )?;
+        }
+        if let Some(fade_out) = self.fade_out {
+            if fade_out.as_secs() % 60 == 0 {
+                writeln!(
+                    f,
+                    "Fade Out: over the last {} minutes",
+                    fade_out.as_secs() / 60
+                )?;
+            } else {
+                writeln!(f, "Fade Out: over the last {} seconds", fade_out.as_secs())?;
+            }
+        }
+        write!(f, "----------------------------")
+    }
+}
+
+/// Validates the chosen settings and starts feeding the tones into the given sink.
+/// The sink keeps playing until the returned playback is stopped and the sink is dropped.
+fn start_playback(
+    preset_options: BinauralPresetGroup,
+    playback_options: PlaybackOptions,
+    ambient: Option<Ambient>,
+    cancel_token: CancelToken,
+    sink: &mut dyn AudioSink,
+) -> Result<Playback, Error> {
+    start_tones_at(
+        session_tone_settings(preset_options, playback_options)?,
+        StdDuration::ZERO,
+        SharedVolume::new(playback_options.volume),
+        SharedBeatOffset::default(),
+        ambient,
+        cancel_token,
+        sink,
+    )
+}
+
+/// Renders the start of a session with the default playback options into interleaved stereo samples,
+/// without opening an audio device. See `render_samples_with` for the details.
+pub fn render_samples(
+    preset_options: BinauralPresetGroup,
+    sample_rate: u32,
+    frames: usize,
+) -> Result<Vec<f32>, Error> {
+    render_samples_with(
+        preset_options,
+        PlaybackOptions::default(),
+        sample_rate,
+        frames,
+    )
+}
+
+/// Renders the start of a session into interleaved stereo samples without opening an audio device,
+/// so that tests, analysis tools or other programs can use the exact audio a session would play.
+///
+/// # Arguments
+/// - `preset_options`: Specifies the binaural beat options to render.
+/// - `playback_options`: Specifies the playback settings, like the volume and ease in, to render with.
+/// - `sample_rate`: The number of frames per second to render at.
+/// - `frames`: The number of frames to render, each one a left and a right sample.
+///
+/// # Returns
+/// `Result<Vec<f32>, anyhow::Error>` with `frames * 2` samples, left first, or the reason the settings can't be played.
+pub fn render_samples_with(
+    preset_options: BinauralPresetGroup,
+    playback_options: PlaybackOptions,
+    sample_rate: u32,
+    frames: usize,
+) -> Result<Vec<f32>, Error> {
+    if sample_rate == 0 {
+        return Err(PlaybackError::InvalidSettings(
+            "The sample rate must be greater than zero.".to_string(),
+        )
+        .into());
+    }
+
+    let tone_settings = session_tone_settings(preset_options, playback_options)?;
+    let mut generator = ToneGenerator::new(tone_settings, sample_rate as f64);
+    let volume = playback_options.volume.clamp(0.0, 1.0);
+
+    let mut samples = Vec::with_capacity(frames * 2);
+    for _ in 0..frames {
+        let (left_sample, right_sample) = generator.next_frame();
+        samples.push(mix_sample(left_sample, right_sample, 2, 0, volume));
+        samples.push(mix_sample(left_sample, right_sample, 2, 1, volume));
+    }
+
+    Ok(samples)
+}
+
+/// Starts feeding a pair of tones into the given sink, one frequency for each ear.
+/// The sink keeps playing until the returned playback is stopped and the sink is dropped.
+pub(crate) fn start_tones(
+    tone_settings: ToneSettings,
+    volume: SharedVolume,
+    cancel_token: CancelToken,
+    sink: &mut dyn AudioSink,
+) -> Result<Playback, Error> {
+    start_tones_at(
+        tone_settings,
+        StdDuration::ZERO,
+        volume,
+        SharedBeatOffset::default(),
         None,
-    )?;
+        cancel_token,
+        sink,
+    )
+}
 
-    stream.play()?;
+/// Starts feeding the tones into the given sink as they sound `start_at` into the session,
+/// so that the ease in, ramp and alternation carry on from there, with the beat already nudged by `beat_offset`.
+/// The soundscape, if there is one, loops underneath the tones from the same point on.
+pub(crate) fn start_tones_at(
+    tone_settings: ToneSettings,
+    start_at: StdDuration,
+    volume: SharedVolume,
+    beat_offset: SharedBeatOffset,
+    ambient: Option<Ambient>,
+    cancel_token: CancelToken,
+    sink: &mut dyn AudioSink,
+) -> Result<Playback, Error> {
+    let sample_rate_val = sink.sample_rate() as f64;
+    let channels_val = sink.channels();
 
-    // The main thread now waits for EITHER the timer to expire OR the cancel token to be set.
-    wait_until_end(cancel_token, duration_minutes);
+    let (producer, mut consumer) =
+        RingBuffer::<f32>::new((sample_rate_val * RING_BUFFER_SECONDS) as usize * channels_val);
+
+    let synthesis_running = Arc::new(AtomicBool::new(true));
+    let synthesis_running_for_thread = Arc::clone(&synthesis_running);
+
+    // All of the tone math happens on its own thread so the real-time audio callback only copies samples.
+    let synthesis_volume = volume.clone();
+    let mut generator = ToneGenerator::new(tone_settings, sample_rate_val);
+    generator.frame = (start_at.as_secs_f64() * sample_rate_val) as u64;
+    generator.beat_offset_hz = beat_offset.get() as f64;
+    generator.ambient = ambient
+        .as_ref()
+        .map(|ambient| ambient.at_rate(sink.sample_rate(), generator.frame));
+    let synthesis_beat_offset = beat_offset.clone();
+    let synthesis_thread = thread::spawn(move || {
+        run_synthesis(
+            producer,
+            generator,
+            channels_val,
+            synthesis_volume,
+            synthesis_beat_offset,
+            synthesis_running_for_thread,
+        );
+    });
+
+    let (error_sender, error_receiver) = mpsc::channel();
+    let frames_played = FrameCounter::default();
+    let peak = PeakMeter::default();
+
+    let playback = Playback {
+        synthesis_running,
+        synthesis_thread,
+        errors: error_receiver,
+        frames_played: frames_played.clone(),
+        peak: peak.clone(),
+        volume,
+        beat_offset,
+        ambient,
+        settings: tone_settings,
+    };
+
+    let stream_cancel_token = cancel_token.clone(); // Clone for the stream closure
+    let mut stop_faded_frames = 0;
+
+    let render = panic_safe(
+        Box::new(move |data: &mut [f32]| {
+            // Check the token's state inside the audio loop, a cancelled session fades out over the token's fade
+            let stop_fade_frames = stream_cancel_token
+                .is_cancelled()
+                .then(|| (stream_cancel_token.fade().as_secs_f64() * sample_rate_val) as u64);
+            if stop_fade_frames.is_some_and(|fade_frames| stop_faded_frames >= fade_frames) {
+                // Once the fade is over, fill the buffer with silence and return
+                data.fill(0.0);
+                return data.len();
+            }
+
+            // Never block the audio thread, output silence if the synthesis thread falls behind.
+            // Only take whole frames that are already buffered so the channels can't be shifted by a partial read.
+            let written = consumer.slots().min(data.len()) / channels_val * channels_val;
+            let (filled, padding) = data.split_at_mut(written);
+            for sample in filled.iter_mut() {
+                *sample = consumer.pop().unwrap_or(0.0);
+            }
+            padding.fill(0.0);
+            if let Some(fade_frames) = stop_fade_frames {
+                apply_stop_fade(filled, channels_val, &mut stop_faded_frames, fade_frames);
+            }
+            frames_played.add((written / channels_val) as u64);
+            peak.record(filled);
+            written
+        }),
+        error_sender.clone(),
+    );
+
+    let started = sink.start(render, error_sender);
+
+    if let Err(err) = started {
+        playback.stop()?;
+        return Err(err);
+    }
+
+    Ok(playback)
+}
+
+/// A binaural beat session playing in the background.
+/// Starting a player returns straight away, so the caller can keep running its own interface while checking on the session or stopping it.
+/// Dropping the player stops the session.
+pub struct BinauralPlayer {
+    settings: SessionSettings,
+    cancel_token: CancelToken,
+    /// When the session was first seen cancelled, it ends once the tones have faded out from there.
+    cancelled_at: Option<Instant>,
+    /// How long one play of the session takes.
+    cycle_length: StdDuration,
+    repeat: SessionRepeat,
+    started: Instant,
+    playback: Option<Playback>,
+    watchdog: OutputWatchdog,
+    outcome: Option<Result<PlaybackOutcome, Error>>,
+    /// The latest change in the output's health that hasn't been shown to the user yet.
+    output_health: Option<OutputHealth>,
+    /// Opens the device to carry on playing through when the output device is lost, if the session can move at all.
+    reopen_sink: Option<SinkOpener>,
+    /// The tones of a session whose output device was lost, waiting to be started again on a new one.
+    reconnecting: Option<Reconnect>,
+    // Keeps the output stream open for as long as the player exists.
+    _sink: Box<dyn AudioSink>,
+}
+
+/// A session waiting to move onto a new output device after its device was lost.
+/// The player tries once on each check after `next_attempt`, so that checking on the session never blocks.
+struct Reconnect {
+    /// The settings, volume, beat offset and soundscape the tones were playing with on the lost device.
+    settings: ToneSettings,
+    volume: SharedVolume,
+    beat_offset: SharedBeatOffset,
+    ambient: Option<Ambient>,
+    next_attempt: Instant,
+    attempts_left: u32,
+}
+
+/// Opens a new audio sink to move a session onto.
+type SinkOpener = Box<dyn Fn() -> Result<Box<dyn AudioSink>, Error>>;
+
+/// Opens the default output device for a session whose device was lost, which is usually where the sound has moved to.
+fn reopen_default_device() -> Result<Box<dyn AudioSink>, Error> {
+    Ok(Box::new(CpalSink::open(None)?))
+}
+
+impl BinauralPlayer {
+    /// Validates the chosen settings and starts playing the session, `settings` returns them so they can be shown.
+    ///
+    /// # Arguments
+    /// - `preset_options`: Specifies the binaural beat options choosen by the user to execute.
+    /// - `playback_options`: Specifies the playback settings, like the volume, that aren't part of the preset.
+    /// - `device_name`: The name of the output device to play through, or `None` for the default device.
+    ///
+    /// # Returns
+    /// `Result<BinauralPlayer, anyhow::Error>` with the playing session, or the reason it couldn't start.
+    pub fn start(
+        preset_options: BinauralPresetGroup,
+        playback_options: PlaybackOptions,
+        device_name: Option<&str>,
+    ) -> Result<Self, Error> {
+        BinauralPlayer::start_with_ambient(
+            preset_options,
+            playback_options,
+            None,
+            &AudioOutput::Device(device_name.map(str::to_string)),
+        )
+    }
+
+    /// Starts playing the session like `start` through the chosen output,
+    /// with the soundscape looping underneath the tones if there is one.
+    pub fn start_with_ambient(
+        preset_options: BinauralPresetGroup,
+        playback_options: PlaybackOptions,
+        ambient: Option<Ambient>,
+        output: &AudioOutput,
+    ) -> Result<Self, Error> {
+        let mut player = BinauralPlayer::start_with_sink(
+            preset_options,
+            playback_options,
+            output.open()?,
+            CancelToken::new(),
+            ambient,
+        )?;
+
+        // Only a device can move, a pipe whose reader went away has nowhere else to go.
+        if let AudioOutput::Device(_) = output {
+            player.reopen_sink = Some(Box::new(reopen_default_device));
+        }
+        Ok(player)
+    }
+
+    fn start_with_sink(
+        preset_options: BinauralPresetGroup,
+        playback_options: PlaybackOptions,
+        mut sink: Box<dyn AudioSink>,
+        cancel_token: CancelToken,
+        ambient: Option<Ambient>,
+    ) -> Result<Self, Error> {
+        // Every session gets its own ID so that its output can be told apart from other sessions.
+        let session_id = Uuid::new_v4();
+        let settings = SessionSettings::new(session_id, preset_options, playback_options)?;
+
+        let playback = start_playback(
+            preset_options,
+            playback_options,
+            ambient,
+            cancel_token.clone(),
+            sink.as_mut(),
+        )?;
+        let watchdog = OutputWatchdog::new(
+            playback.frames_played.clone(),
+            playback.peak.clone(),
+            sink.sample_rate(),
+        );
+
+        Ok(BinauralPlayer {
+            settings,
+            cancel_token,
+            cancelled_at: None,
+            cycle_length: StdDuration::from_secs(preset_options.duration.to_minutes() as u64 * 60),
+            repeat: playback_options.repeat,
+            started: Instant::now(),
+            playback: Some(playback),
+            watchdog,
+            outcome: None,
+            output_health: None,
+            reopen_sink: None,
+            reconnecting: None,
+            _sink: sink,
+        })
+    }
+
+    /// Returns the ID the session was given when it started.
+    pub fn session_id(&self) -> Uuid {
+        self.settings.session_id
+    }
+
+    /// Returns the settings the session plays with.
+    pub fn settings(&self) -> SessionSettings {
+        self.settings
+    }
+
+    /// Returns the token that stops the session once it is cancelled, so that other threads can stop it too.
+    pub fn cancel_token(&self) -> CancelToken {
+        self.cancel_token.clone()
+    }
+
+    /// Returns how far the current play of the session has got, the elapsed time stops at the end of the last play.
+    pub fn progress(&self) -> SessionProgress {
+        let elapsed = self.started.elapsed().as_nanos();
+        let cycle_length = self.cycle_length.as_nanos().max(1);
+        let round = (elapsed / cycle_length) as u32 + 1;
+        let elapsed_in_round = StdDuration::from_nanos((elapsed % cycle_length) as u64);
+
+        match self.repeat {
+            SessionRepeat::Times(times) if round > times => SessionProgress {
+                elapsed: self.cycle_length,
+                length: self.cycle_length,
+                round: times,
+                rounds: Some(times),
+            },
+            SessionRepeat::Times(times) => SessionProgress {
+                elapsed: elapsed_in_round,
+                length: self.cycle_length,
+                round,
+                rounds: Some(times),
+            },
+            SessionRepeat::Forever => SessionProgress {
+                elapsed: elapsed_in_round,
+                length: self.cycle_length,
+                round,
+                rounds: None,
+            },
+        }
+    }
+
+    /// Returns how long the session has played across all of its rounds, which stops growing at the end of the last one.
+    pub fn played(&self) -> StdDuration {
+        let elapsed = self.started.elapsed();
+
+        match self.repeat {
+            SessionRepeat::Times(times) => elapsed.min(self.cycle_length * times),
+            SessionRepeat::Forever => elapsed,
+        }
+    }
+
+    /// Returns the volume the session is playing at, between 0.0 and 1.0.
+    pub fn volume(&self) -> f32 {
+        self.shared_controls()
+            .map_or(0.0, |(volume, _)| volume.get())
+    }
+
+    /// Changes the volume while the session plays, the level is kept between 0.0 and 1.0 and faded in smoothly.
+    pub fn set_volume(&self, level: f32) {
+        if let Some((volume, _)) = self.shared_controls() {
+            volume.set(level);
+        }
+    }
+
+    /// Returns how far the beat has been nudged away from the session's own beat while playing, in Hz.
+    pub fn beat_offset(&self) -> f32 {
+        self.shared_controls()
+            .map_or(0.0, |(_, beat_offset)| beat_offset.get())
+    }
+
+    /// Nudges the beat while the session plays, the tones glide over to it rather than jumping.
+    /// The offset is kept so that the preset's beat plus the offset stays between 0 Hz and the carrier frequency.
+    pub fn set_beat_offset(&self, offset_hz: f32) {
+        let carrier_hz = self.settings.preset_options.carrier.to_hz();
+        let beat_hz = self.settings.preset_options.beat.to_hz();
+
+        if let Some((_, beat_offset)) = self.shared_controls() {
+            beat_offset.set(offset_hz.clamp(-beat_hz, carrier_hz - beat_hz));
+        }
+    }
+
+    /// Returns the volume and beat offset the tones play with, which are kept while the session waits for a new device.
+    fn shared_controls(&self) -> Option<(&SharedVolume, &SharedBeatOffset)> {
+        match (&self.playback, &self.reconnecting) {
+            (Some(playback), _) => Some((&playback.volume, &playback.beat_offset)),
+            (None, Some(reconnect)) => Some((&reconnect.volume, &reconnect.beat_offset)),
+            (None, None) => None,
+        }
+    }
+
+    /// Stops the session early and `wait` returns `PlaybackOutcome::Cancelled`. The tones fade out over the
+    /// cancel token's `fade()` and then go silent, which is straight away unless the token was already cancelled with a fade.
+    pub fn stop(&self) {
+        self.cancel_token.cancel();
+    }
+
+    /// Stops the session early, fading the tones to silence over `fade` first.
+    /// The session keeps playing until the fade is over, then `wait` returns `PlaybackOutcome::Cancelled`.
+    pub fn stop_with_fade(&self, fade: StdDuration) {
+        self.cancel_token.cancel_with_fade(fade);
+    }
 
-    Ok(())
+    /// Returns whether the session is still playing.
+    /// A session stops playing once its time is up, it is cancelled or the audio output reports an error.
+    pub fn is_playing(&mut self) -> bool {
+        self.update();
+        self.outcome.is_none()
+    }
+
+    /// Returns true while the session plays and more than `hand_over` of it is left to play,
+    /// so that the next session of a queue can start while this one fades out. A session repeating until
+    /// stopped never hands over.
+    pub fn is_playing_until(&mut self, hand_over: StdDuration) -> bool {
+        let left_to_play = match self.repeat {
+            SessionRepeat::Times(times) => {
+                (self.cycle_length * times).saturating_sub(self.played())
+            }
+            SessionRepeat::Forever => StdDuration::MAX,
+        };
+
+        self.is_playing() && (hand_over.is_zero() || left_to_play > hand_over)
+    }
+
+    /// Blocks until the session ends, then stops the synthesis.
+    /// Nothing is reported on the way, changes in the output's health are left for `take_output_health`
+    /// before the wait, or sent as events by `wait_with_events`.
+    ///
+    /// # Returns
+    /// `Result<PlaybackOutcome, anyhow::Error>` with how the session finished, or the first error reported by the audio output.
+    pub fn wait(mut self) -> Result<PlaybackOutcome, Error> {
+        while self.is_playing() {
+            // Sleep for a short period to avoid high CPU usage
+            thread::sleep(WAIT_POLL_INTERVAL);
+        }
+
+        if let Some(playback) = self.playback.take() {
+            playback.stop()?;
+        }
+
+        self.outcome
+            .take()
+            .expect("The session has finished, so it has an outcome.")
+    }
+
+    /// Blocks until the session ends like `wait`, sending its progress, new rounds and output health to `events`
+    /// instead of printing them, followed by how it finished.
+    /// A receiver that has gone away doesn't stop the session, the events are just dropped.
+    pub fn wait_with_events(
+        mut self,
+        events: Sender<SessionEvent>,
+    ) -> Result<PlaybackOutcome, Error> {
+        let mut last_progress: Option<SessionProgress> = None;
+
+        while self.is_playing() {
+            if let Some(output_health) = self.take_output_health() {
+                let _ = events.send(SessionEvent::OutputHealth(output_health));
+            }
+
+            let progress = self.progress();
+            if let Some(last_progress) = last_progress
+                && progress.round > last_progress.round
+            {
+                let _ = events.send(SessionEvent::RoundStarted(progress.round));
+            }
+            if last_progress.is_none_or(|last_progress| {
+                last_progress.elapsed.as_secs() != progress.elapsed.as_secs()
+                    || last_progress.round != progress.round
+            }) {
+                let _ = events.send(SessionEvent::Progress(progress));
+                last_progress = Some(progress);
+            }
+
+            thread::sleep(EVENT_POLL_INTERVAL);
+        }
+
+        let outcome = self.wait()?;
+        let _ = events.send(SessionEvent::Finished(outcome));
+        Ok(outcome)
+    }
+
+    /// Returns the latest change in the output's health once, so that it can be shown to the user.
+    /// The output is watched because a muted or vanished device fails silently.
+    pub fn take_output_health(&mut self) -> Option<OutputHealth> {
+        self.update();
+        self.output_health.take()
+    }
+
+    /// Checks on the session once, recording how it finished if it has.
+    fn update(&mut self) {
+        if self.outcome.is_some() {
+            return;
+        }
+        if self.reconnecting.is_some() {
+            self.try_reconnect();
+            return;
+        }
+
+        let error = match &self.playback {
+            Some(playback) => playback.errors.try_recv().ok(),
+            None => return,
+        };
+
+        if self.cancel_token.is_cancelled() {
+            // Errors while the tones fade out don't matter any more, the session is ending anyway.
+            let cancelled_at = *self.cancelled_at.get_or_insert_with(Instant::now);
+            if cancelled_at.elapsed() >= self.cancel_token.fade() {
+                self.outcome = Some(Ok(PlaybackOutcome::Cancelled));
+            }
+        } else if let Some(err) = error {
+            let device_lost = matches!(
+                err.downcast_ref::<PlaybackError>(),
+                Some(PlaybackError::DeviceLost(_))
+            );
+
+            if device_lost && self.reopen_sink.is_some() {
+                self.lose_device();
+            } else {
+                self.outcome = Some(Err(err));
+            }
+        } else if self.time_is_up() {
+            self.outcome = Some(Ok(PlaybackOutcome::Completed));
+        } else if let Some(output_health) = self.watchdog.check(self.tones_audible()) {
+            self.output_health = Some(output_health);
+        }
+    }
+
+    /// Returns whether the tones should be heard right now, which they aren't during the fades or at a volume of 0.
+    fn tones_audible(&self) -> bool {
+        let Some(playback) = &self.playback else {
+            return false;
+        };
+        let position = self.started.elapsed();
+        let fading_out = playback
+            .settings
+            .end_fade
+            .is_some_and(|end_fade| position + end_fade.length >= end_fade.ends_at);
+
+        playback.volume.get() > 0.0 && position >= playback.settings.fade_in && !fading_out
+    }
+
+    /// Returns whether the session has played for as long as it was asked to, which is never for one repeating until stopped.
+    fn time_is_up(&self) -> bool {
+        match self.repeat {
+            SessionRepeat::Times(times) => self.started.elapsed() >= self.cycle_length * times,
+            SessionRepeat::Forever => false,
+        }
+    }
+
+    /// Stops the tones on the output device that was lost and makes the first attempt at moving them to a new one.
+    fn lose_device(&mut self) {
+        let Some(lost_playback) = self.playback.take() else {
+            return;
+        };
+
+        self.reconnecting = Some(Reconnect {
+            settings: lost_playback.settings,
+            volume: lost_playback.volume.clone(),
+            beat_offset: lost_playback.beat_offset.clone(),
+            ambient: lost_playback.ambient.clone(),
+            next_attempt: Instant::now(),
+            attempts_left: RECONNECT_ATTEMPTS,
+        });
+
+        match lost_playback.stop() {
+            Ok(()) => self.try_reconnect(),
+            Err(err) => {
+                self.reconnecting = None;
+                self.outcome = Some(Err(err));
+            }
+        }
+    }
+
+    /// Tries once to move the session onto a newly opened output device if the next attempt is due,
+    /// starting the tones again at the point the session has reached. After the last failed attempt
+    /// the session ends with its error. A session stopped or run out while waiting ends straight away,
+    /// there is nothing playing to fade out.
+    fn try_reconnect(&mut self) {
+        let Some(mut reconnect) = self.reconnecting.take() else {
+            return;
+        };
+
+        if self.cancel_token.is_cancelled() {
+            self.outcome = Some(Ok(PlaybackOutcome::Cancelled));
+            return;
+        }
+        if self.time_is_up() {
+            self.outcome = Some(Ok(PlaybackOutcome::Completed));
+            return;
+        }
+        if Instant::now() < reconnect.next_attempt {
+            self.reconnecting = Some(reconnect);
+            return;
+        }
+
+        match self.restart_tones(&reconnect) {
+            Ok(()) => self.output_health = Some(OutputHealth::Reconnected),
+            Err(err) => {
+                reconnect.attempts_left -= 1;
+                if reconnect.attempts_left == 0 {
+                    self.outcome = Some(Err(err));
+                } else {
+                    reconnect.next_attempt = Instant::now() + RECONNECT_DELAY;
+                    self.reconnecting = Some(reconnect);
+                }
+            }
+        }
+    }
+
+    /// Opens a new output device and starts the tones of the lost one on it, at the point the session has reached.
+    fn restart_tones(&mut self, reconnect: &Reconnect) -> Result<(), Error> {
+        let Some(reopen_sink) = &self.reopen_sink else {
+            return Err(PlaybackError::NoDevice("No output device to move to.".to_string()).into());
+        };
+
+        let mut sink = reopen_sink()?;
+        let playback = start_tones_at(
+            reconnect.settings,
+            self.started.elapsed(),
+            reconnect.volume.clone(),
+            reconnect.beat_offset.clone(),
+            reconnect.ambient.clone(),
+            self.cancel_token.clone(),
+            sink.as_mut(),
+        )?;
+
+        self.watchdog = OutputWatchdog::new(
+            playback.frames_played.clone(),
+            playback.peak.clone(),
+            sink.sample_rate(),
+        );
+        self.playback = Some(playback);
+        self._sink = sink;
+        Ok(())
+    }
+}
+
+/// Stopping the synthesis thread here keeps it from running on after a player is dropped without waiting.
+impl Drop for BinauralPlayer {
+    fn drop(&mut self) {
+        if let Some(playback) = self.playback.take() {
+            let _ = playback.stop();
+        }
+    }
+}
+
+/// Generates and plays binaural beat tones based on specified carrier frequency,
+/// beat frequency, and duration, blocking until the session ends.
+///
+/// # Arguments
+/// - `preset_options`: Specifies the binaural beat options choosen by the user to execute.
+/// - `playback_options`: Specifies the playback settings, like the volume, that aren't part of the preset.
+/// - `device_name`: The name of the output device to play through, or `None` for the default device.
+/// - `cancel_token`: A token that stops the program before the timelimit once it is cancelled, fading out if asked to.
+/// - `events`: Where to send the session's progress and how it finished, or `None` to print them instead.
+///
+/// # Returns
+/// `Result<PlaybackOutcome, anyhow::Error>` with how the session finished, or the failure.
+pub fn generate_binaural_beats(
+    preset_options: BinauralPresetGroup,
+    playback_options: PlaybackOptions,
+    device_name: Option<&str>,
+    cancel_token: CancelToken,
+    events: Option<Sender<SessionEvent>>,
+) -> Result<PlaybackOutcome, Error> {
+    let mut player = BinauralPlayer::start_with_sink(
+        preset_options,
+        playback_options,
+        Box::new(CpalSink::open(device_name)?),
+        cancel_token,
+        None,
+    )?;
+    player.reopen_sink = Some(Box::new(reopen_default_device));
+
+    if let Some(events) = events {
+        return player.wait_with_events(events);
+    }
+
+    println!("{}", player.settings());
+
+    // The main thread now waits for EITHER the timer to expire OR the cancel token to be set.
+    while player.is_playing() {
+        if let Some(output_health) = player.take_output_health() {
+            println!("{}", output_health);
+        }
+        thread::sleep(WAIT_POLL_INTERVAL);
+    }
+
+    let outcome = player.wait();
+    if let Ok(PlaybackOutcome::Cancelled) = outcome {
+        println!("Playback cancelled by user.");
+    }
+    outcome
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::modules::duration::duration::Duration;
+    use crate::modules::frequency::beat_frequency::BeatFrequency;
+    use crate::modules::frequency::carrier_frequency::CarrierFrequency;
+    use crate::modules::output::mock_output::MockSink;
+    use crate::modules::preset::Preset;
+    use crate::modules::synth::ambient::AmbientSound;
+    use std::f32::consts::FRAC_1_SQRT_2;
+
+    const SAMPLE_RATE: u32 = 48_000;
+
+    /// How many frames each golden reference segment holds.
+    const GOLDEN_FRAMES: usize = 4_096;
+
+    /// How far a rendered sample may drift from its golden reference.
+    const GOLDEN_TOLERANCE: f32 = 1e-4;
+
+    macro_rules! test_golden_output_cases {
+        ($($name:ident:($a:expr, $b:expr),)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    assert_matches_golden(stringify!($name), $a, $b)
+                }
+            )*
+        };
+    }
+
+    fn preset_group(carrier: CarrierFrequency, beat: BeatFrequency) -> BinauralPresetGroup {
+        BinauralPresetGroup {
+            preset: Preset::Focus,
+            carrier,
+            beat,
+            duration: Duration::FiveMinutes,
+        }
+    }
+
+    /// Returns every sample of one channel from an interleaved buffer.
+    fn channel(samples: &[f32], channels: usize, index: usize) -> Vec<f32> {
+        samples
+            .iter()
+            .skip(index)
+            .step_by(channels)
+            .copied()
+            .collect()
+    }
+
+    /// Estimates the frequency of a tone from the number of upward zero crossings.
+    fn estimate_hz(samples: &[f32], sample_rate: u32) -> f32 {
+        let crossings = samples
+            .windows(2)
+            .filter(|pair| pair[0] < 0.0 && pair[1] >= 0.0)
+            .count();
+        crossings as f32 * sample_rate as f32 / samples.len() as f32
+    }
+
+    /// Renders the first frames of a preset through the full playback path.
+    fn render(preset_options: BinauralPresetGroup, channels: usize, frames: usize) -> Vec<f32> {
+        render_with(preset_options, PlaybackOptions::default(), channels, frames)
+    }
+
+    /// Renders the first frames of a preset with the playback options through the full playback path.
+    fn render_with(
+        preset_options: BinauralPresetGroup,
+        playback_options: PlaybackOptions,
+        channels: usize,
+        frames: usize,
+    ) -> Vec<f32> {
+        let mut sink = MockSink::new(SAMPLE_RATE, channels);
+        let cancel_token = CancelToken::new();
+        let playback = start_playback(
+            preset_options,
+            playback_options,
+            None,
+            cancel_token,
+            &mut sink,
+        )
+        .unwrap();
+
+        let samples = sink.capture(frames);
+        playback.stop().unwrap();
+
+        samples
+    }
+
+    /// Compares a rendered segment against the stored golden WAV file.
+    /// Run the tests with `UPDATE_GOLDEN=1` to rewrite the reference files after an intended change to the sound.
+    fn assert_matches_golden(
+        name: &str,
+        preset_options: BinauralPresetGroup,
+        playback_options: PlaybackOptions,
+    ) {
+        let path = format!("{}/tests/golden/{}.wav", env!("CARGO_MANIFEST_DIR"), name);
+        let samples = render_with(preset_options, playback_options, 2, GOLDEN_FRAMES);
+
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            let spec = hound::WavSpec {
+                channels: 2,
+                sample_rate: SAMPLE_RATE,
+                bits_per_sample: 32,
+                sample_format: hound::SampleFormat::Float,
+            };
+            let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+            for sample in &samples {
+                writer.write_sample(*sample).unwrap();
+            }
+            writer.finalize().unwrap();
+            return;
+        }
+
+        let mut reader = hound::WavReader::open(&path)
+            .unwrap_or_else(|err| panic!("Missing golden file {}: {}", path, err));
+        let expected: Vec<f32> = reader
+            .samples::<f32>()
+            .map(|sample| sample.unwrap())
+            .collect();
+
+        assert_eq!(samples.len(), expected.len());
+        for (index, (actual, expected)) in samples.iter().zip(expected.iter()).enumerate() {
+            assert!(
+                (actual - expected).abs() <= GOLDEN_TOLERANCE,
+                "Sample {} differs from {}: {} != {}",
+                index,
+                path,
+                actual,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn capture_returns_requested_frame_count() {
+        let samples = render(
+            preset_group(CarrierFrequency::Beta, BeatFrequency::Beta),
+            2,
+            1_000,
+        );
+
+        assert_eq!(samples.len(), 2_000);
+    }
+
+    #[test]
+    fn each_ear_plays_its_own_frequency() {
+        let samples = render(
+            preset_group(CarrierFrequency::Beta, BeatFrequency::Beta),
+            2,
+            SAMPLE_RATE as usize,
+        );
+
+        // The Beta carrier is 400 Hz with a 20 Hz beat, so 390 Hz left and 410 Hz right.
+        assert!((estimate_hz(&channel(&samples, 2, 0), SAMPLE_RATE) - 390.0).abs() <= 1.0);
+        assert!((estimate_hz(&channel(&samples, 2, 1), SAMPLE_RATE) - 410.0).abs() <= 1.0);
+    }
+
+    #[test]
+    fn extra_channels_are_silent() {
+        let samples = render(
+            preset_group(CarrierFrequency::Alpha, BeatFrequency::Alpha),
+            4,
+            4_800,
+        );
+
+        assert!(channel(&samples, 4, 0).iter().any(|sample| *sample != 0.0));
+        assert!(channel(&samples, 4, 2).iter().all(|sample| *sample == 0.0));
+        assert!(channel(&samples, 4, 3).iter().all(|sample| *sample == 0.0));
+    }
+
+    #[test]
+    fn mono_output_mixes_both_ears_without_clipping() {
+        let samples = render(
+            preset_group(CarrierFrequency::Alpha, BeatFrequency::Alpha),
+            1,
+            SAMPLE_RATE as usize,
+        );
+
+        assert!(samples.iter().all(|sample| sample.abs() <= 0.5));
+        assert!(samples.iter().any(|sample| sample.abs() > 0.4));
+    }
+
+    #[test]
+    fn cancellation_silences_the_output() {
+        let mut sink = MockSink::new(SAMPLE_RATE, 2);
+        let cancel_token = CancelToken::new();
+        let playback = start_playback(
+            preset_group(CarrierFrequency::Theta, BeatFrequency::Theta),
+            PlaybackOptions::default(),
+            None,
+            cancel_token.clone(),
+            &mut sink,
+        )
+        .unwrap();
+
+        let before = sink.capture(4_800);
+        cancel_token.cancel();
+        let after = sink.capture(4_800);
+        playback.stop().unwrap();
+
+        assert!(before.iter().any(|sample| *sample != 0.0));
+        assert!(after.iter().all(|sample| *sample == 0.0));
+    }
+
+    #[test]
+    fn cancellation_with_a_fade_ramps_the_output_down() {
+        let mut sink = MockSink::new(SAMPLE_RATE, 2);
+        let cancel_token = CancelToken::new();
+        let playback = start_playback(
+            preset_group(CarrierFrequency::Theta, BeatFrequency::Theta),
+            PlaybackOptions::default(),
+            None,
+            cancel_token.clone(),
+            &mut sink,
+        )
+        .unwrap();
+
+        sink.capture(4_800);
+        cancel_token.cancel_with_fade(StdDuration::from_millis(100));
+        let fading = sink.capture(4_800);
+        let after = sink.capture(4_800);
+        playback.stop().unwrap();
+
+        let peak = |samples: &[f32]| samples.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+        let quarter = fading.len() / 4;
+        assert!(peak(&fading[..quarter]) > CHANNEL_GAIN * 0.9);
+        assert!(peak(&fading[3 * quarter..]) < CHANNEL_GAIN * 0.2);
+        assert!(after.iter().all(|sample| *sample == 0.0));
+    }
+
+    macro_rules! test_stop_fade_cases {
+        ($($name:ident:($faded_frames:expr, $expected:expr),)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let mut samples = [1.0, -1.0];
+                    let mut faded_frames = $faded_frames;
+                    apply_stop_fade(&mut samples, 2, &mut faded_frames, 100);
+                    assert_eq!(samples, [$expected, -$expected]);
+                    assert_eq!(faded_frames, $faded_frames + 1);
+                }
+            )*
+        };
+    }
+
+    test_stop_fade_cases! {
+        stop_fade_full_at_the_start: (0, 1.0),
+        stop_fade_halfway: (50, 0.5),
+        stop_fade_silent_at_the_end: (100, 0.0),
+        stop_fade_silent_after_the_end: (150, 0.0),
+    }
+
+    /// Returns the default playback options with the tones in the given shape.
+    fn with_waveform(waveform: Waveform) -> PlaybackOptions {
+        PlaybackOptions {
+            waveform,
+            ..PlaybackOptions::default()
+        }
+    }
+
+    test_golden_output_cases! {
+        golden_sine_focus: (BinauralPresetGroup::from(Preset::Focus), PlaybackOptions::default()),
+        golden_sine_sleep: (BinauralPresetGroup::from(Preset::Sleep), PlaybackOptions::default()),
+        golden_sine_astral: (BinauralPresetGroup::from(Preset::Astral), PlaybackOptions::default()),
+        golden_sine_solfeggio_crown: (BinauralPresetGroup::from(Preset::SolfeggioCrown), PlaybackOptions::default()),
+        golden_sine_tuning_fork_root: (BinauralPresetGroup::from(Preset::TuningForkRoot), PlaybackOptions::default()),
+        golden_triangle_focus: (BinauralPresetGroup::from(Preset::Focus), with_waveform(Waveform::Triangle)),
+        golden_square_focus: (BinauralPresetGroup::from(Preset::Focus), with_waveform(Waveform::Square)),
+        golden_saw_focus: (BinauralPresetGroup::from(Preset::Focus), with_waveform(Waveform::Saw)),
+        golden_bilateral_focus: (
+            BinauralPresetGroup::from(Preset::Focus),
+            PlaybackOptions {
+                beat_mode: BeatMode::Bilateral { rate_hz: 2.0 },
+                ..PlaybackOptions::default()
+            }
+        ),
+    }
+
+    #[test]
+    fn volume_scales_the_output_level() {
+        let mut sink = MockSink::new(SAMPLE_RATE, 2);
+        let cancel_token = CancelToken::new();
+        let playback = start_playback(
+            preset_group(CarrierFrequency::Alpha, BeatFrequency::Alpha),
+            PlaybackOptions {
+                volume: 0.5,
+                ..PlaybackOptions::default()
+            },
+            None,
+            cancel_token,
+            &mut sink,
+        )
+        .unwrap();
+
+        let samples = sink.capture(4_800);
+        playback.stop().unwrap();
+
+        let peak = samples
+            .iter()
+            .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+        assert!(peak <= 0.25 + f32::EPSILON);
+        assert!(peak > 0.24);
+    }
+
+    #[test]
+    fn ease_in_starts_both_ears_on_the_carrier() {
+        let mut sink = MockSink::new(SAMPLE_RATE, 2);
+        let cancel_token = CancelToken::new();
+        let playback = start_playback(
+            preset_group(CarrierFrequency::Gamma, BeatFrequency::Gamma),
+            PlaybackOptions {
+                ease_in: StdDuration::from_secs(90),
+                ..PlaybackOptions::default()
+            },
+            None,
+            cancel_token,
+            &mut sink,
+        )
+        .unwrap();
+
+        let samples = sink.capture(SAMPLE_RATE as usize);
+        playback.stop().unwrap();
+
+        // Only a fraction of the 40 Hz Gamma beat has built up after the first second.
+        let left_hz = estimate_hz(&channel(&samples, 2, 0), SAMPLE_RATE);
+        let right_hz = estimate_hz(&channel(&samples, 2, 1), SAMPLE_RATE);
+        assert!((left_hz - 500.0).abs() <= 1.0);
+        assert!((right_hz - 500.0).abs() <= 1.0);
+    }
+
+    #[test]
+    fn tones_do_not_drift_late_in_a_long_session() {
+        // A low sample rate keeps an hour of frames quick enough to play through one by one.
+        const LONG_RUN_SAMPLE_RATE: u32 = 8_000;
+        let settings = ToneSettings::steady(432.1, 7.83);
+        let mut generator = ToneGenerator::new(settings, LONG_RUN_SAMPLE_RATE as f64);
+
+        // Play the first hour of the session, every frame moving the phase on from the one before.
+        for _ in 0..60 * 60 * LONG_RUN_SAMPLE_RATE {
+            generator.next_frame();
+        }
+        let last_second: Vec<(f32, f32)> = (0..LONG_RUN_SAMPLE_RATE)
+            .map(|_| generator.next_frame())
+            .collect();
+        let left: Vec<f32> = last_second.iter().map(|frame| frame.0).collect();
+        let right: Vec<f32> = last_second.iter().map(|frame| frame.1).collect();
+
+        let (left_hz, right_hz) = ear_frequencies(432.1, 7.83, BeatMode::Binaural);
+        assert!((measure_hz(&left, LONG_RUN_SAMPLE_RATE) - left_hz as f64).abs() < 0.01);
+        assert!((measure_hz(&right, LONG_RUN_SAMPLE_RATE) - right_hz as f64).abs() < 0.01);
+    }
+
+    /// Measures the frequency of a tone to a fraction of a hertz, from the time between its first and last upward
+    /// zero crossings, each placed between two samples by drawing a straight line through them.
+    fn measure_hz(samples: &[f32], sample_rate: u32) -> f64 {
+        let crossings: Vec<f64> = samples
+            .windows(2)
+            .enumerate()
+            .filter(|(_, pair)| pair[0] < 0.0 && pair[1] >= 0.0)
+            .map(|(index, pair)| index as f64 + (-pair[0] / (pair[1] - pair[0])) as f64)
+            .collect();
+        let (first, last) = (crossings[0], crossings[crossings.len() - 1]);
+
+        (crossings.len() - 1) as f64 * sample_rate as f64 / (last - first)
+    }
+
+    #[test]
+    fn ease_in_grows_the_beat_linearly() {
+        let settings = ToneSettings {
+            ease_in: StdDuration::from_secs(2),
+            ..ToneSettings::steady(200.0, 10.0)
+        };
+        let mut generator = ToneGenerator::new(settings, 100.0);
+
+        assert_eq!(generator.current_beat_hz(), 0.0);
+        (0..100).for_each(|_| {
+            generator.next_frame();
+        });
+        assert_eq!(generator.current_beat_hz(), 5.0);
+        (0..100).for_each(|_| {
+            generator.next_frame();
+        });
+        assert_eq!(generator.current_beat_hz(), 10.0);
+        generator.next_frame();
+        assert_eq!(generator.current_beat_hz(), 10.0);
+    }
+
+    #[test]
+    fn ease_in_follows_the_chosen_curve() {
+        let settings = ToneSettings {
+            ease_in: StdDuration::from_secs(4),
+            ease_in_curve: Easing::EaseInOut,
+            ..ToneSettings::steady(200.0, 10.0)
+        };
+        let mut generator = ToneGenerator::new(settings, 100.0);
+
+        (0..100).for_each(|_| {
+            generator.next_frame();
+        });
+        assert_eq!(generator.current_beat_hz(), 1.5625);
+        (0..100).for_each(|_| {
+            generator.next_frame();
+        });
+        assert_eq!(generator.current_beat_hz(), 5.0);
+    }
+
+    #[test]
+    fn nudged_beat_glides_to_its_new_frequency() {
+        let settings = ToneSettings::steady(200.0, 10.0);
+        let mut generator = ToneGenerator::new(settings, 100.0);
+        generator.target_beat_offset_hz = -0.5;
+
+        (0..25).for_each(|_| {
+            generator.next_frame();
+        });
+        assert!((generator.beat_offset_hz + 0.25).abs() < 1e-9);
+        (0..50).for_each(|_| {
+            generator.next_frame();
+        });
+        assert_eq!(generator.beat_offset_hz, -0.5);
+    }
+
+    #[test]
+    fn tones_start_with_the_beat_already_nudged() {
+        let settings = ToneSettings::steady(500.0, 20.0);
+        let beat_offset = SharedBeatOffset::default();
+        beat_offset.set(20.0);
+        let mut sink = MockSink::new(SAMPLE_RATE, 2);
+        let playback = start_tones_at(
+            settings,
+            StdDuration::ZERO,
+            SharedVolume::new(1.0),
+            beat_offset,
+            None,
+            CancelToken::new(),
+            &mut sink,
+        )
+        .unwrap();
+
+        let samples = sink.capture(SAMPLE_RATE as usize);
+        playback.stop().unwrap();
+
+        let left_hz = estimate_hz(&channel(&samples, 2, 0), SAMPLE_RATE);
+        let right_hz = estimate_hz(&channel(&samples, 2, 1), SAMPLE_RATE);
+        assert!((left_hz - 480.0).abs() <= 1.0);
+        assert!((right_hz - 520.0).abs() <= 1.0);
+    }
+
+    fn steady_ambient(level: f32, gain: f32) -> Ambient {
+        Ambient {
+            sound: Arc::new(AmbientSound::new(
+                SAMPLE_RATE,
+                vec![(level, -level); 10 * SAMPLE_RATE as usize],
+            )),
+            gain,
+        }
+    }
+
+    #[test]
+    fn ambient_is_mixed_under_the_tones_and_fades_with_them() {
+        let settings = ToneSettings {
+            fade_in: StdDuration::from_secs(1),
+            ..ToneSettings::steady(200.0, 10.0)
+        };
+        let mut tones = ToneGenerator::new(settings, SAMPLE_RATE as f64);
+        let mut layered = ToneGenerator::new(settings, SAMPLE_RATE as f64);
+        // Start past the blend at the start of the loop, where the level is steady.
+        layered.ambient = Some(steady_ambient(0.4, 0.5).at_rate(SAMPLE_RATE, SAMPLE_RATE as u64));
+
+        for _ in 0..2 * SAMPLE_RATE {
+            let gain = tones.fade_in_gain();
+            let (left, right) = tones.next_frame();
+            let (layered_left, layered_right) = layered.next_frame();
+
+            assert!((layered_left - left - 0.2 * gain).abs() < 1e-6);
+            assert!((layered_right - right + 0.2 * gain).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn ambient_plays_through_the_output() {
+        let settings = ToneSettings::steady(500.0, 0.0);
+        let mut sink = MockSink::new(SAMPLE_RATE, 2);
+        let playback = start_tones_at(
+            settings,
+            StdDuration::from_secs(1),
+            SharedVolume::new(1.0),
+            SharedBeatOffset::default(),
+            Some(steady_ambient(0.25, 1.0)),
+            CancelToken::new(),
+            &mut sink,
+        )
+        .unwrap();
+
+        let samples = sink.capture(SAMPLE_RATE as usize);
+        playback.stop().unwrap();
+
+        // The tone averages out to nothing over whole cycles, leaving the steady soundscape at the channel gain.
+        let left = channel(&samples, 2, 0);
+        let mean = left.iter().sum::<f32>() / left.len() as f32;
+        assert!((mean - 0.25 * CHANNEL_GAIN).abs() < 1e-3, "{}", mean);
+    }
+
+    macro_rules! test_beat_direction_cases {
+        ($($name:ident:($frame:expr, $expected:expr),)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let settings = ToneSettings {
+                        alternate_every: Some(StdDuration::from_secs(10)),
+                        ..ToneSettings::steady(200.0, 10.0)
+                    };
+                    let mut generator = ToneGenerator::new(settings, 100.0);
+                    generator.frame = $frame;
+
+                    assert_eq!(generator.beat_direction(),$expected)
+                }
+            )*
+        };
+    }
+
+    // At 100 frames per second the ears swap every 1000 frames and glide over 500 frames.
+    test_beat_direction_cases! {
+        beat_direction_starts_with_right_ear_higher: (0, 1.0),
+        beat_direction_holds_until_the_swap: (999, 1.0),
+        beat_direction_glides_from_the_swap: (1000, 1.0),
+        beat_direction_is_level_halfway_through_the_glide: (1250, 0.0),
+        beat_direction_left_ear_higher_after_the_glide: (1500, -1.0),
+        beat_direction_glides_back: (2250, 0.0),
+        beat_direction_right_ear_higher_again: (2500, 1.0),
+    }
+
+    #[test]
+    fn alternation_swaps_the_higher_ear() {
+        let settings = ToneSettings {
+            alternate_every: Some(StdDuration::from_secs(1)),
+            ..ToneSettings::steady(400.0, 20.0)
+        };
+        let mut generator = ToneGenerator::new(settings, SAMPLE_RATE as f64);
+        let samples: Vec<f32> = (0..SAMPLE_RATE * 2)
+            .flat_map(|_| {
+                let (left, right) = generator.next_frame();
+                [left, right]
+            })
+            .collect();
+
+        // The glide only covers half of the second segment, so measure its second half.
+        let second_half = &samples[(SAMPLE_RATE as usize * 3 / 2) * 2..];
+        let left_hz = estimate_hz(&channel(second_half, 2, 0), SAMPLE_RATE);
+        let right_hz = estimate_hz(&channel(second_half, 2, 1), SAMPLE_RATE);
+        assert!((left_hz - 410.0).abs() <= 2.0);
+        assert!((right_hz - 390.0).abs() <= 2.0);
+    }
+
+    macro_rules! test_ramp_beat_cases {
+        ($($name:ident:($curve:expr, $ease_in:expr, $frame:expr, $expected:expr),)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let settings = ToneSettings {
+                        ease_in: StdDuration::from_secs($ease_in),
+                        ramp: Some(BeatRamp {
+                            end_beat_hz: 2.5,
+                            length: StdDuration::from_secs(10),
+                            curve: $curve,
+                            repeat: false,
+                        }),
+                        ..ToneSettings::steady(200.0, 10.0)
+                    };
+                    let mut generator = ToneGenerator::new(settings, 100.0);
+                    generator.frame = $frame;
+
+                    assert!((generator.current_beat_hz() - $expected).abs() < 1e-9)
+                }
+            )*
+        };
+    }
+
+    #[test]
+    fn repeating_ramp_glides_back_to_the_start() {
+        let settings = ToneSettings {
+            ramp: Some(BeatRamp {
+                end_beat_hz: 2.0,
+                length: StdDuration::from_secs(20),
+                curve: Easing::Linear,
+                repeat: true,
+            }),
+            ..ToneSettings::steady(200.0, 10.0)
+        };
+        let mut generator = ToneGenerator::new(settings, 100.0);
+
+        // The ramp takes 2000 frames and the glide back takes 500.
+        generator.frame = 2000;
+        assert_eq!(generator.current_beat_hz(), 2.0);
+        generator.frame = 2500;
+        assert_eq!(generator.current_beat_hz(), 8.0);
+        generator.frame = 3000;
+        assert_eq!(generator.current_beat_hz(), 6.0);
+    }
+
+    // At 100 frames per second the ramp from 10 Hz to 2.5 Hz takes 1000 frames.
+    test_ramp_beat_cases! {
+        ramp_starts_on_the_preset_beat: (Easing::Linear, 0, 0, 10.0),
+        ramp_linear_halfway: (Easing::Linear, 0, 500, 6.25),
+        ramp_logarithmic_halfway: (Easing::Logarithmic, 0, 500, 5.0),
+        ramp_reaches_the_end_beat: (Easing::Linear, 0, 1000, 2.5),
+        ramp_holds_the_end_beat: (Easing::Linear, 0, 5000, 2.5),
+        ramp_grows_from_zero_during_the_ease_in: (Easing::Linear, 2, 100, 4.625),
+    }
+
+    #[test]
+    fn ramp_to_negative_ear_frequency_is_rejected_before_starting() {
+        let mut sink = MockSink::new(SAMPLE_RATE, 2);
+        let cancel_token = CancelToken::new();
+
+        let result = start_playback(
+            preset_group(CarrierFrequency::Custom(5.0), BeatFrequency::Custom(2.0)),
+            PlaybackOptions {
+                ramp_to_hz: Some(20.0),
+                ..PlaybackOptions::default()
+            },
+            None,
+            cancel_token,
+            &mut sink,
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn played_frames_are_counted_without_padding() {
+        let mut sink = MockSink::new(SAMPLE_RATE, 2);
+        let cancel_token = CancelToken::new();
+        let playback = start_playback(
+            preset_group(CarrierFrequency::Beta, BeatFrequency::Beta),
+            PlaybackOptions::default(),
+            None,
+            cancel_token,
+            &mut sink,
+        )
+        .unwrap();
+
+        sink.capture(4_800);
+        let frames_played = playback.frames_played.get();
+        playback.stop().unwrap();
+
+        assert_eq!(frames_played, 4_800);
+    }
+
+    #[test]
+    fn player_can_be_stopped_without_blocking() {
+        let mut player = BinauralPlayer::start_with_sink(
+            preset_group(CarrierFrequency::Beta, BeatFrequency::Beta),
+            PlaybackOptions::default(),
+            Box::new(MockSink::new(SAMPLE_RATE, 2)),
+            CancelToken::new(),
+            None,
+        )
+        .unwrap();
+
+        assert!(player.is_playing());
+        player.stop();
+        assert!(!player.is_playing());
+        assert_eq!(player.wait().unwrap(), PlaybackOutcome::Cancelled);
+    }
+
+    #[test]
+    fn player_progress_counts_the_repeats() {
+        let mut player = BinauralPlayer::start_with_sink(
+            preset_group(CarrierFrequency::Beta, BeatFrequency::Beta),
+            PlaybackOptions {
+                repeat: SessionRepeat::Times(3),
+                ..PlaybackOptions::default()
+            },
+            Box::new(MockSink::new(SAMPLE_RATE, 2)),
+            CancelToken::new(),
+            None,
+        )
+        .unwrap();
+
+        // The preset plays for five minutes, so seven minutes in is the second play.
+        player.started = Instant::now() - StdDuration::from_secs(7 * 60);
+        let progress = player.progress();
+        assert_eq!(progress.round, 2);
+        assert_eq!(progress.rounds, Some(3));
+        assert_eq!(progress.elapsed.as_secs(), 2 * 60);
+        assert!(player.is_playing());
+
+        player.started = Instant::now() - StdDuration::from_secs(15 * 60);
+        assert_eq!(player.progress().round, 3);
+        assert_eq!(player.progress().elapsed, player.progress().length);
+        assert!(!player.is_playing());
+    }
+
+    #[test]
+    fn player_played_time_stops_at_the_end_of_the_last_round() {
+        let mut player = BinauralPlayer::start_with_sink(
+            preset_group(CarrierFrequency::Beta, BeatFrequency::Beta),
+            PlaybackOptions {
+                repeat: SessionRepeat::Times(2),
+                ..PlaybackOptions::default()
+            },
+            Box::new(MockSink::new(SAMPLE_RATE, 2)),
+            CancelToken::new(),
+            None,
+        )
+        .unwrap();
+
+        player.started = Instant::now() - StdDuration::from_secs(7 * 60);
+        assert_eq!(player.played().as_secs(), 7 * 60);
+
+        player.started = Instant::now() - StdDuration::from_secs(60 * 60);
+        assert_eq!(player.played(), StdDuration::from_secs(10 * 60));
+    }
+
+    #[test]
+    fn player_repeating_forever_keeps_playing() {
+        let mut player = BinauralPlayer::start_with_sink(
+            preset_group(CarrierFrequency::Beta, BeatFrequency::Beta),
+            PlaybackOptions {
+                repeat: SessionRepeat::Forever,
+                ..PlaybackOptions::default()
+            },
+            Box::new(MockSink::new(SAMPLE_RATE, 2)),
+            CancelToken::new(),
+            None,
+        )
+        .unwrap();
+
+        player.started = Instant::now() - StdDuration::from_secs(60 * 60);
+        assert_eq!(player.progress().round, 13);
+        assert_eq!(player.progress().rounds, None);
+        assert!(player.is_playing());
+    }
+
+    #[test]
+    fn player_volume_can_change_while_playing() {
+        let player = BinauralPlayer::start_with_sink(
+            preset_group(CarrierFrequency::Beta, BeatFrequency::Beta),
+            PlaybackOptions {
+                volume: 0.5,
+                ..PlaybackOptions::default()
+            },
+            Box::new(MockSink::new(SAMPLE_RATE, 2)),
+            CancelToken::new(),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(player.volume(), 0.5);
+        player.set_volume(0.8);
+        assert_eq!(player.volume(), 0.8);
+    }
+
+    #[test]
+    fn player_beat_offset_keeps_the_beat_between_zero_and_the_carrier() {
+        let player = BinauralPlayer::start_with_sink(
+            preset_group(CarrierFrequency::Custom(100.0), BeatFrequency::Custom(4.0)),
+            PlaybackOptions::default(),
+            Box::new(MockSink::new(SAMPLE_RATE, 2)),
+            CancelToken::new(),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(player.beat_offset(), 0.0);
+        player.set_beat_offset(BEAT_NUDGE_HZ);
+        assert_eq!(player.beat_offset(), 0.5);
+        player.set_beat_offset(-10.0);
+        assert_eq!(player.beat_offset(), -4.0);
+        player.set_beat_offset(200.0);
+        assert_eq!(player.beat_offset(), 96.0);
+    }
+
+    #[test]
+    fn player_hands_over_before_the_end_of_its_last_round() {
+        let mut player = BinauralPlayer::start_with_sink(
+            preset_group(CarrierFrequency::Beta, BeatFrequency::Beta),
+            PlaybackOptions {
+                repeat: SessionRepeat::Times(2),
+                ..PlaybackOptions::default()
+            },
+            Box::new(MockSink::new(SAMPLE_RATE, 2)),
+            CancelToken::new(),
+            None,
+        )
+        .unwrap();
+
+        // The preset plays for five minutes, so 9:40 in leaves 20 seconds of the second round.
+        player.started = Instant::now() - StdDuration::from_secs(9 * 60 + 40);
+        assert!(player.is_playing_until(StdDuration::ZERO));
+        assert!(player.is_playing_until(StdDuration::from_secs(15)));
+        assert!(!player.is_playing_until(StdDuration::from_secs(30)));
+        assert!(player.is_playing());
+    }
+
+    #[test]
+    fn player_completes_once_its_time_is_up() {
+        let mut player = BinauralPlayer::start_with_sink(
+            preset_group(CarrierFrequency::Beta, BeatFrequency::Beta),
+            PlaybackOptions::default(),
+            Box::new(MockSink::new(SAMPLE_RATE, 2)),
+            CancelToken::new(),
+            None,
+        )
+        .unwrap();
+        player.cycle_length = StdDuration::ZERO;
+
+        assert!(!player.is_playing());
+        assert_eq!(player.wait().unwrap(), PlaybackOutcome::Completed);
+    }
+
+    #[test]
+    fn player_cancel_token_stops_it_from_another_thread() {
+        let mut player = BinauralPlayer::start_with_sink(
+            preset_group(CarrierFrequency::Beta, BeatFrequency::Beta),
+            PlaybackOptions::default(),
+            Box::new(MockSink::new(SAMPLE_RATE, 2)),
+            CancelToken::new(),
+            None,
+        )
+        .unwrap();
+        let cancel_token = player.cancel_token();
+
+        thread::spawn(move || cancel_token.cancel()).join().unwrap();
+
+        assert!(!player.is_playing());
+    }
+
+    #[test]
+    fn player_stopped_with_a_fade_plays_until_the_fade_is_over() {
+        let mut player = BinauralPlayer::start_with_sink(
+            preset_group(CarrierFrequency::Beta, BeatFrequency::Beta),
+            PlaybackOptions::default(),
+            Box::new(MockSink::new(SAMPLE_RATE, 2)),
+            CancelToken::new(),
+            None,
+        )
+        .unwrap();
+
+        player.stop_with_fade(StdDuration::from_millis(200));
+
+        assert!(player.is_playing());
+        thread::sleep(StdDuration::from_millis(250));
+        assert!(!player.is_playing());
+        assert_eq!(player.wait().unwrap(), PlaybackOutcome::Cancelled);
+    }
+
+    #[test]
+    fn player_sends_only_the_outcome_of_a_session_that_is_already_over() {
+        let mut player = BinauralPlayer::start_with_sink(
+            preset_group(CarrierFrequency::Beta, BeatFrequency::Beta),
+            PlaybackOptions::default(),
+            Box::new(MockSink::new(SAMPLE_RATE, 2)),
+            CancelToken::new(),
+            None,
+        )
+        .unwrap();
+        player.cycle_length = StdDuration::ZERO;
+        let (events, received) = mpsc::channel();
+
+        assert_eq!(
+            player.wait_with_events(events).unwrap(),
+            PlaybackOutcome::Completed
+        );
+        assert_eq!(
+            received.iter().collect::<Vec<_>>(),
+            vec![SessionEvent::Finished(PlaybackOutcome::Completed)]
+        );
+    }
+
+    #[test]
+    fn player_sends_progress_new_rounds_and_the_outcome() {
+        let mut player = BinauralPlayer::start_with_sink(
+            preset_group(CarrierFrequency::Beta, BeatFrequency::Beta),
+            PlaybackOptions {
+                repeat: SessionRepeat::Times(3),
+                ..PlaybackOptions::default()
+            },
+            Box::new(MockSink::new(SAMPLE_RATE, 2)),
+            CancelToken::new(),
+            None,
+        )
+        .unwrap();
+        // The first round of five minutes is almost over.
+        player.started =
+            Instant::now() - StdDuration::from_secs(5 * 60) + StdDuration::from_millis(150);
+        let cancel_token = player.cancel_token();
+        let (events, received) = mpsc::channel();
+        // The player stays on the thread that started it, the events are read on another one.
+        let reading = thread::spawn(move || {
+            let mut sent = Vec::new();
+            for event in received.iter() {
+                sent.push(event);
+                if event == SessionEvent::RoundStarted(2) {
+                    cancel_token.cancel();
+                }
+            }
+            sent
+        });
+
+        assert_eq!(
+            player.wait_with_events(events).unwrap(),
+            PlaybackOutcome::Cancelled
+        );
+        let sent = reading.join().unwrap();
+        assert!(matches!(
+            sent[0],
+            SessionEvent::Progress(SessionProgress { round: 1, .. })
+        ));
+        assert!(sent.contains(&SessionEvent::RoundStarted(2)));
+        assert!(sent.iter().any(|event| matches!(
+            event,
+            SessionEvent::Progress(SessionProgress { round: 2, .. })
+        )));
+        assert_eq!(
+            sent.last(),
+            Some(&SessionEvent::Finished(PlaybackOutcome::Cancelled))
+        );
+    }
+
+    #[test]
+    fn shared_volume_is_clamped() {
+        let volume = SharedVolume::new(1.5);
+        assert_eq!(volume.get(), 1.0);
+
+        volume.set(-0.5);
+        assert_eq!(volume.get(), 0.0);
+
+        volume.set(0.35);
+        assert_eq!(volume.get(), 0.35);
+    }
+
+    #[test]
+    fn negative_ear_frequency_is_rejected_before_starting() {
+        let mut sink = MockSink::new(SAMPLE_RATE, 2);
+        let cancel_token = CancelToken::new();
+
+        let result = start_playback(
+            preset_group(CarrierFrequency::Custom(5.0), BeatFrequency::Custom(20.0)),
+            PlaybackOptions::default(),
+            None,
+            cancel_token,
+            &mut sink,
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn tones_can_start_part_way_into_the_session() {
+        let settings = ToneSettings {
+            ease_in: StdDuration::from_secs(90),
+            ..ToneSettings::steady(500.0, 40.0)
+        };
+        let mut sink = MockSink::new(SAMPLE_RATE, 2);
+        let playback = start_tones_at(
+            settings,
+            StdDuration::from_secs(120),
+            SharedVolume::new(1.0),
+            SharedBeatOffset::default(),
+            None,
+            CancelToken::new(),
+            &mut sink,
+        )
+        .unwrap();
+
+        let samples = sink.capture(SAMPLE_RATE as usize);
+        playback.stop().unwrap();
+
+        // Two minutes in, the ease in is over and the full 40 Hz beat is playing.
+        let left_hz = estimate_hz(&channel(&samples, 2, 0), SAMPLE_RATE);
+        let right_hz = estimate_hz(&channel(&samples, 2, 1), SAMPLE_RATE);
+        assert!((left_hz - 480.0).abs() <= 1.0);
+        assert!((right_hz - 520.0).abs() <= 1.0);
+    }
+
+    #[test]
+    fn player_moves_to_a_new_device_when_the_output_is_lost() {
+        let sink = MockSink::new(SAMPLE_RATE, 2);
+        let device_errors = sink.device_errors();
+        let mut player = BinauralPlayer::start_with_sink(
+            preset_group(CarrierFrequency::Beta, BeatFrequency::Beta),
+            PlaybackOptions::default(),
+            Box::new(sink),
+            CancelToken::new(),
+            None,
+        )
+        .unwrap();
+
+        let reopened = Arc::new(AtomicU32::new(0));
+        let reopened_by_player = Arc::clone(&reopened);
+        player.reopen_sink = Some(Box::new(move || {
+            reopened_by_player.fetch_add(1, Ordering::Relaxed);
+            Ok(Box::new(MockSink::new(44_100, 2)) as Box<dyn AudioSink>)
+        }));
+
+        device_errors.send(PlaybackError::DeviceLost("gone".to_string()).into());
+
+        assert_eq!(player.take_output_health(), Some(OutputHealth::Reconnected));
+        assert!(player.is_playing());
+        assert_eq!(reopened.load(Ordering::Relaxed), 1);
+        player.stop();
+        assert_eq!(player.wait().unwrap(), PlaybackOutcome::Cancelled);
+    }
+
+    #[test]
+    fn player_keeps_checking_while_the_new_device_is_not_there_yet() {
+        let sink = MockSink::new(SAMPLE_RATE, 2);
+        let device_errors = sink.device_errors();
+        let mut player = BinauralPlayer::start_with_sink(
+            preset_group(CarrierFrequency::Beta, BeatFrequency::Beta),
+            PlaybackOptions::default(),
+            Box::new(sink),
+            CancelToken::new(),
+            None,
+        )
+        .unwrap();
+
+        // The first attempt finds no device, the one after the delay does.
+        let reopened = Arc::new(AtomicU32::new(0));
+        let reopened_by_player = Arc::clone(&reopened);
+        player.reopen_sink = Some(Box::new(move || {
+            match reopened_by_player.fetch_add(1, Ordering::Relaxed) {
+                0 => Err(PlaybackError::NoDevice("not yet".to_string()).into()),
+                _ => Ok(Box::new(MockSink::new(44_100, 2)) as Box<dyn AudioSink>),
+            }
+        }));
+        player.set_volume(0.5);
+
+        device_errors.send(PlaybackError::DeviceLost("gone".to_string()).into());
+
+        let checked = Instant::now();
+        assert_eq!(player.take_output_health(), None);
+        assert!(player.is_playing());
+        assert!(checked.elapsed() < RECONNECT_DELAY / 2);
+        assert_eq!(reopened.load(Ordering::Relaxed), 1);
+        assert_eq!(player.volume(), 0.5);
+
+        thread::sleep(RECONNECT_DELAY);
+        assert_eq!(player.take_output_health(), Some(OutputHealth::Reconnected));
+        assert_eq!(reopened.load(Ordering::Relaxed), 2);
+        assert_eq!(player.volume(), 0.5);
+        player.stop();
+        assert_eq!(player.wait().unwrap(), PlaybackOutcome::Cancelled);
+    }
+
+    #[test]
+    fn player_stopped_while_waiting_for_a_new_device_ends_straight_away() {
+        let sink = MockSink::new(SAMPLE_RATE, 2);
+        let device_errors = sink.device_errors();
+        let mut player = BinauralPlayer::start_with_sink(
+            preset_group(CarrierFrequency::Beta, BeatFrequency::Beta),
+            PlaybackOptions::default(),
+            Box::new(sink),
+            CancelToken::new(),
+            None,
+        )
+        .unwrap();
+        player.reopen_sink = Some(Box::new(|| {
+            Err(PlaybackError::NoDevice("not yet".to_string()).into())
+        }));
+
+        device_errors.send(PlaybackError::DeviceLost("gone".to_string()).into());
+        assert!(player.is_playing());
+
+        player.stop_with_fade(StdDuration::from_secs(5));
+        assert!(!player.is_playing());
+        assert_eq!(player.wait().unwrap(), PlaybackOutcome::Cancelled);
+    }
+
+    #[test]
+    fn player_without_a_device_to_move_to_ends_with_the_lost_device() {
+        let sink = MockSink::new(SAMPLE_RATE, 2);
+        let device_errors = sink.device_errors();
+        let mut player = BinauralPlayer::start_with_sink(
+            preset_group(CarrierFrequency::Beta, BeatFrequency::Beta),
+            PlaybackOptions::default(),
+            Box::new(sink),
+            CancelToken::new(),
+            None,
+        )
+        .unwrap();
+
+        device_errors.send(PlaybackError::DeviceLost("gone".to_string()).into());
+
+        assert!(!player.is_playing());
+        let err = player.wait().unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<PlaybackError>(),
+            Some(PlaybackError::DeviceLost(_))
+        ));
+    }
+
+    #[test]
+    fn player_does_not_move_devices_for_other_stream_errors() {
+        let sink = MockSink::new(SAMPLE_RATE, 2);
+        let device_errors = sink.device_errors();
+        let mut player = BinauralPlayer::start_with_sink(
+            preset_group(CarrierFrequency::Beta, BeatFrequency::Beta),
+            PlaybackOptions::default(),
+            Box::new(sink),
+            CancelToken::new(),
+            None,
+        )
+        .unwrap();
+        player.reopen_sink = Some(Box::new(|| panic!("The device wasn't lost.")));
+
+        device_errors.send(PlaybackError::Stream("broken".to_string()).into());
+
+        assert!(!player.is_playing());
+        assert!(player.wait().is_err());
+    }
+
+    #[test]
+    fn offline_render_matches_the_live_playback() {
+        let preset_options = preset_group(CarrierFrequency::Beta, BeatFrequency::Alpha);
+
+        let samples = render_samples(preset_options, SAMPLE_RATE, 4_800).unwrap();
+
+        assert_eq!(samples.len(), 9_600);
+        assert_eq!(samples, render(preset_options, 2, 4_800));
+    }
+
+    #[test]
+    fn offline_render_plays_each_ear_at_its_frequency() {
+        let preset_options =
+            preset_group(CarrierFrequency::Custom(200.0), BeatFrequency::Custom(10.0));
+
+        let samples = render_samples(preset_options, SAMPLE_RATE, SAMPLE_RATE as usize).unwrap();
+
+        let left_hz = estimate_hz(&channel(&samples, 2, 0), SAMPLE_RATE);
+        let right_hz = estimate_hz(&channel(&samples, 2, 1), SAMPLE_RATE);
+        assert!((left_hz - 195.0).abs() <= 1.0, "left ear at {} Hz", left_hz);
+        assert!(
+            (right_hz - 205.0).abs() <= 1.0,
+            "right ear at {} Hz",
+            right_hz
+        );
+    }
+
+    #[test]
+    fn offline_render_follows_the_volume() {
+        let preset_options = preset_group(CarrierFrequency::Beta, BeatFrequency::Beta);
+        let quiet = PlaybackOptions {
+            volume: 0.25,
+            ..PlaybackOptions::default()
+        };
+
+        let loud = render_samples(preset_options, SAMPLE_RATE, 4_800).unwrap();
+        let soft = render_samples_with(preset_options, quiet, SAMPLE_RATE, 4_800).unwrap();
+
+        let peak = |samples: &[f32]| samples.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+        assert!((peak(&soft) - peak(&loud) * 0.25).abs() < 1e-3);
+    }
+
+    #[test]
+    fn offline_render_rejects_settings_that_cannot_play() {
+        assert!(
+            render_samples(
+                preset_group(CarrierFrequency::Custom(5.0), BeatFrequency::Custom(20.0)),
+                SAMPLE_RATE,
+                16
+            )
+            .is_err()
+        );
+        assert!(
+            render_samples(
+                preset_group(CarrierFrequency::Beta, BeatFrequency::Beta),
+                0,
+                16
+            )
+            .is_err()
+        );
+    }
+
+    macro_rules! test_end_fade_gain_cases {
+        ($($name:ident:($frame:expr, $expected:expr),)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let settings = ToneSettings {
+                        end_fade: Some(EndFade {
+                            ends_at: StdDuration::from_secs(10),
+                            length: StdDuration::from_secs(2),
+                        }),
+                        ..ToneSettings::steady(200.0, 10.0)
+                    };
+                    let mut generator = ToneGenerator::new(settings, 100.0);
+                    generator.frame = $frame;
+
+                    assert_eq!(generator.end_fade_gain(),$expected)
+                }
+            )*
+        };
+    }
+
+    // At 100 frames per second the session ends on frame 1000 and fades over the 200 frames before it.
+    test_end_fade_gain_cases! {
+        end_fade_gain_full_at_the_start: (0, 1.0),
+        end_fade_gain_full_until_the_fade: (800, 1.0),
+        end_fade_gain_halfway_through_the_fade: (900, 0.5),
+        end_fade_gain_silent_at_the_end: (1000, 0.0),
+        end_fade_gain_silent_after_the_end: (1100, 0.0),
+    }
+
+    macro_rules! test_fade_in_gain_cases {
+        ($($name:ident:($frame:expr, $expected:expr),)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let settings = ToneSettings {
+                        fade_in: StdDuration::from_secs(2),
+                        ..ToneSettings::steady(200.0, 10.0)
+                    };
+                    let mut generator = ToneGenerator::new(settings, 100.0);
+                    generator.frame = $frame;
+
+                    assert_eq!(generator.fade_in_gain(),$expected)
+                }
+            )*
+        };
+    }
+
+    // At 100 frames per second the tones fade up over the first 200 frames.
+    test_fade_in_gain_cases! {
+        fade_in_gain_silent_at_the_start: (0, 0.0),
+        fade_in_gain_halfway_through_the_fade: (100, 0.5),
+        fade_in_gain_full_after_the_fade: (200, 1.0),
+        fade_in_gain_full_later_on: (5_000, 1.0),
+    }
+
+    #[test]
+    fn sleep_presets_fade_out_at_the_end_of_the_last_round() {
+        let preset_options = BinauralPresetGroup::from(Preset::Sleep);
+        let playback_options = PlaybackOptions {
+            repeat: SessionRepeat::Times(3),
+            ..PlaybackOptions::default()
+        };
+
+        let end_fade = session_tone_settings(preset_options, playback_options)
+            .unwrap()
+            .end_fade
+            .unwrap();
+
+        let session_minutes = preset_options.duration.to_minutes() as u64 * 3;
+        assert_eq!(
+            end_fade.ends_at,
+            StdDuration::from_secs(session_minutes * 60)
+        );
+        assert_eq!(end_fade.length, Preset::Sleep.end_fade());
+    }
+
+    #[test]
+    fn fade_out_can_be_turned_off_or_added() {
+        let no_fade = PlaybackOptions {
+            fade_out: Some(StdDuration::ZERO),
+            ..PlaybackOptions::default()
+        };
+        let fade = PlaybackOptions {
+            fade_out: Some(StdDuration::from_secs(120)),
+            ..PlaybackOptions::default()
+        };
+
+        let sleep = BinauralPresetGroup::from(Preset::Sleep);
+        let focus = BinauralPresetGroup::from(Preset::Focus);
+        assert_eq!(
+            session_tone_settings(sleep, no_fade).unwrap().end_fade,
+            None
+        );
+        assert_eq!(
+            session_tone_settings(focus, PlaybackOptions::default())
+                .unwrap()
+                .end_fade,
+            None
+        );
+        assert_eq!(
+            session_tone_settings(focus, fade)
+                .unwrap()
+                .end_fade
+                .map(|end_fade| end_fade.length),
+            Some(StdDuration::from_secs(120))
+        );
+    }
+
+    #[test]
+    fn sessions_repeating_forever_do_not_fade_out() {
+        let playback_options = PlaybackOptions {
+            repeat: SessionRepeat::Forever,
+            ..PlaybackOptions::default()
+        };
+
+        let tone_settings =
+            session_tone_settings(BinauralPresetGroup::from(Preset::Sleep), playback_options)
+                .unwrap();
+
+        assert_eq!(tone_settings.end_fade, None);
+    }
+
+    #[test]
+    fn fade_out_brings_the_tones_down_to_silence() {
+        let mut preset_options = preset_group(CarrierFrequency::Beta, BeatFrequency::Beta);
+        preset_options.duration = Duration::Custom(1);
+        let playback_options = PlaybackOptions {
+            fade_out: Some(StdDuration::from_secs(30)),
+            ..PlaybackOptions::default()
+        };
+        let sample_rate = 8_000;
+
+        let samples =
+            render_samples_with(preset_options, playback_options, sample_rate, 60 * 8_000).unwrap();
+
+        let peak = |samples: &[f32]| samples.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+        let second = sample_rate as usize * 2;
+        assert!((peak(&samples[..second]) - CHANNEL_GAIN).abs() < 1e-3);
+        assert!((peak(&samples[30 * second..31 * second]) - CHANNEL_GAIN).abs() < 1e-3);
+        assert!(peak(&samples[45 * second..46 * second]) < CHANNEL_GAIN * 0.55);
+        assert!(peak(&samples[59 * second..]) < CHANNEL_GAIN * 0.01);
+    }
+
+    #[test]
+    fn session_settings_list_the_options_in_use() {
+        let playback_options = PlaybackOptions {
+            volume: 0.5,
+            waveform: Waveform::Triangle,
+            ..PlaybackOptions::default()
+        };
+        let settings = SessionSettings::new(
+            Uuid::nil(),
+            BinauralPresetGroup::from(Preset::Sleep),
+            playback_options,
+        )
+        .unwrap();
+
+        assert_eq!(
+            settings.to_string(),
+            format!(
+                "--- Binaural Beat Settings ---\n\
+                 Session ID: {}\n\
+                 Preset Sleep\n\
+                 Carrier Frequency: 100.00 Hz\n\
+                 Beat Frequency: 2.00 Hz\n\
+                 Left Ear Frequency: 99.00 Hz\n\
+                 Right Ear Frequency: 101.00 Hz\n\
+                 Duration: 60 minutes\n\
+                 Volume: 50%\n\
+                 Waveform: triangle\n\
+                 Fade Out: over the last 10 minutes\n\
+                 ----------------------------",
+                Uuid::nil()
+            )
+        );
+    }
+
+    #[test]
+    fn session_settings_to_json() {
+        let settings = SessionSettings::new(
+            Uuid::nil(),
+            preset_group(CarrierFrequency::Beta, BeatFrequency::Alpha),
+            PlaybackOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            settings.to_json(),
+            json!({
+                "session_id": Uuid::nil().to_string(),
+                "preset": "focus",
+                "carrier_hz": 400.0,
+                "beat_hz": 10.0,
+                "left_hz": 395.0,
+                "right_hz": 405.0,
+                "duration_minutes": 5,
+                "volume": 1.0,
+                "waveform": "sine",
+                "ease_in_seconds": 0,
+                "ramp_to_hz": null,
+                "repeat": 1,
+                "alternate_every_seconds": null,
+                "fade_in_seconds": 0,
+                "fade_out_seconds": null,
+                "bilateral_rate_hz": null,
+            })
+        );
+    }
+
+    macro_rules! test_bilateral_gains_cases {
+        ($($name:ident:($seconds:expr, $expected:expr),)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (left_gain, right_gain) = bilateral_gains($seconds, 1.0);
+                    let (expected_left, expected_right) = $expected;
+                    assert!((left_gain - expected_left).abs() < 1e-6, "left gain {}", left_gain);
+                    assert!((right_gain - expected_right).abs() < 1e-6, "right gain {}", right_gain);
+                }
+            )*
+        };
+    }
+
+    test_bilateral_gains_cases! {
+        bilateral_gains_start_on_the_left: (0.0, (1.0, 0.0)),
+        bilateral_gains_in_the_middle_going_right: (0.25, (FRAC_1_SQRT_2, FRAC_1_SQRT_2)),
+        bilateral_gains_fully_right_halfway: (0.5, (0.0, 1.0)),
+        bilateral_gains_in_the_middle_going_left: (0.75, (FRAC_1_SQRT_2, FRAC_1_SQRT_2)),
+        bilateral_gains_back_on_the_left: (1.0, (1.0, 0.0)),
+    }
+
+    #[test]
+    fn bilateral_gains_keep_constant_power() {
+        for step in 0..100 {
+            let (left_gain, right_gain) = bilateral_gains(step as f64 / 100.0, 1.5);
+
+            assert!((left_gain.powi(2) + right_gain.powi(2) - 1.0).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn bilateral_mode_moves_one_tone_between_the_ears() {
+        let preset_options = preset_group(CarrierFrequency::Custom(200.0), BeatFrequency::Alpha);
+        let playback_options = PlaybackOptions {
+            beat_mode: BeatMode::Bilateral { rate_hz: 1.0 },
+            ..PlaybackOptions::default()
+        };
+
+        let samples = render_samples_with(
+            preset_options,
+            playback_options,
+            SAMPLE_RATE,
+            SAMPLE_RATE as usize,
+        )
+        .unwrap();
+
+        let peak = |samples: &[f32]| samples.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+        let left = channel(&samples, 2, 0);
+        let right = channel(&samples, 2, 1);
+        let tenth = SAMPLE_RATE as usize / 10;
+        // Fully left at the start, fully right half a second in.
+        assert!(peak(&left[..tenth / 10]) > CHANNEL_GAIN * 0.99);
+        assert!(peak(&right[..tenth / 10]) < CHANNEL_GAIN * 0.01);
+        assert!(peak(&left[5 * tenth - tenth / 20..5 * tenth + tenth / 20]) < CHANNEL_GAIN * 0.05);
+        assert!(peak(&right[5 * tenth - tenth / 20..5 * tenth + tenth / 20]) > CHANNEL_GAIN * 0.99);
+        // Both ears play the carrier, the preset's beat isn't played.
+        let left_hz = estimate_hz(&left[..tenth * 2], SAMPLE_RATE);
+        assert!((left_hz - 200.0).abs() <= 5.0, "left ear at {} Hz", left_hz);
+    }
+
+    #[test]
+    fn bilateral_rate_outside_the_range_is_rejected() {
+        for rate_hz in [0.25, 3.0] {
+            let playback_options = PlaybackOptions {
+                beat_mode: BeatMode::Bilateral { rate_hz },
+                ..PlaybackOptions::default()
+            };
+
+            assert!(
+                render_samples_with(
+                    preset_group(CarrierFrequency::Beta, BeatFrequency::Alpha),
+                    playback_options,
+                    SAMPLE_RATE,
+                    16
+                )
+                .is_err()
+            );
+        }
+    }
+
+    #[test]
+    fn bilateral_session_settings_show_the_sweep() {
+        let playback_options = PlaybackOptions {
+            beat_mode: BeatMode::Bilateral { rate_hz: 1.5 },
+            ..PlaybackOptions::default()
+        };
+        let settings = SessionSettings::new(
+            Uuid::nil(),
+            preset_group(CarrierFrequency::Beta, BeatFrequency::Alpha),
+            playback_options,
+        )
+        .unwrap();
+
+        assert!(
+            settings
+                .to_string()
+                .contains("Bilateral: the tone sweeps between the ears 1.50 times a second\n")
+        );
+        assert!(!settings.to_string().contains("Left Ear Frequency"));
+        assert_eq!(settings.to_json()["left_hz"], json!(400.0));
+        assert_eq!(settings.to_json()["right_hz"], json!(400.0));
+        assert_eq!(settings.to_json()["bilateral_rate_hz"], json!(1.5));
+    }
 }
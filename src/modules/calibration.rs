@@ -0,0 +1,324 @@
+//! A module that contains code related to the sound check that can be played before a session starts.
+
+use std::io::{self, Write};
+use std::time::{Duration as StdDuration, Instant};
+
+use anyhow::Error;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::terminal;
+
+use crate::modules::bb_generator::{CHANNEL_GAIN, SharedVolume, ToneSettings, start_tones};
+use crate::modules::cancel_token::CancelToken;
+use crate::modules::frequency::frequency_common::ToFrequency;
+use crate::modules::output::cpal_output::CpalSink;
+use crate::modules::preset::BinauralPresetGroup;
+use crate::modules::synth::waveform::Waveform;
+
+/// How long the calibration tone keeps playing after the last volume change.
+const CALIBRATION_TONE_LENGTH: StdDuration = StdDuration::from_secs(5);
+
+/// How much the volume changes on each press of `+` or `-`.
+const VOLUME_STEP: f32 = 0.05;
+
+/// The quietest output level that can be typed in, in dBFS.
+const MIN_OUTPUT_DB: f32 = -60.0;
+
+/// The ways the sound check can finish.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CalibrationOutcome {
+    /// The user is happy with the volume, the session should start at this level.
+    Accepted(f32),
+    /// The user wants to stop before the session starts.
+    Cancelled,
+}
+
+/// Returns the volume one step up or down from the current level, kept between 0.0 and 1.0.
+pub(crate) fn step_volume(level: f32, up: bool) -> f32 {
+    let stepped = if up {
+        level + VOLUME_STEP
+    } else {
+        level - VOLUME_STEP
+    };
+
+    // Round to whole percentages so repeated steps don't drift.
+    ((stepped * 100.0).round() / 100.0).clamp(0.0, 1.0)
+}
+
+/// Returns the gain of a volume level in dB, or `None` when the level is silent.
+pub(crate) fn gain_db(level: f32) -> Option<f32> {
+    (level > 0.0).then(|| 20.0 * level.log10())
+}
+
+/// Returns the peak level the tone plays at in dBFS for a volume level, or `None` when the level is silent.
+pub(crate) fn output_level_db(level: f32) -> Option<f32> {
+    gain_db(level * CHANNEL_GAIN)
+}
+
+/// Returns the loudest output level in dBFS, the one played at full volume.
+fn max_output_db() -> f32 {
+    20.0 * CHANNEL_GAIN.log10()
+}
+
+/// Returns the volume level that plays the tone at the given peak level in dBFS,
+/// or `None` when the level is quieter than `MIN_OUTPUT_DB` or louder than full volume.
+pub(crate) fn volume_for_output_db(db: f32) -> Option<f32> {
+    // Allow a little rounding, since the loudest level is shown to one decimal.
+    if !db.is_finite() || db < MIN_OUTPUT_DB || db > max_output_db() + 0.05 {
+        return None;
+    }
+
+    Some((10f32.powf(db / 20.0) / CHANNEL_GAIN).min(1.0))
+}
+
+/// Reads a typed in level such as `-18`, `-18 dB` or `-18.5dBFS`.
+pub(crate) fn parse_db(text: &str) -> Option<f32> {
+    let text = text.trim().to_lowercase();
+    let number = text
+        .strip_suffix("dbfs")
+        .or_else(|| text.strip_suffix("db"))
+        .unwrap_or(&text)
+        .trim_end();
+
+    number.parse().ok()
+}
+
+/// A helper function that formats a level in dB, showing silence as `-inf`.
+fn format_db(db: Option<f32>) -> String {
+    match db {
+        Some(db) => format!("{:.1}", db),
+        None => "-inf".to_string(),
+    }
+}
+
+/// Plays the session's carrier in both ears so the user can pick a comfortable volume before the timer starts.
+/// The tone plays for a few seconds after the last change, `+` and `-` adjust it, `d` lets an exact output level be
+/// typed in dBFS, Enter accepts and Esc or Ctrl+C cancels.
+///
+/// # Arguments
+/// - `preset_options`: Specifies the binaural beat options whose carrier is played.
+/// - `volume`: The volume level the sound check starts at.
+/// - `waveform`: The shape of the tone, the same as the session's so that it sounds as loud.
+/// - `device_name`: The name of the output device to play through, or `None` for the default device.
+///
+/// # Returns
+/// `Result<CalibrationOutcome, anyhow::Error>` with the chosen volume, or the failure.
+pub fn run_calibration(
+    preset_options: BinauralPresetGroup,
+    volume: f32,
+    waveform: Waveform,
+    device_name: Option<&str>,
+) -> Result<CalibrationOutcome, Error> {
+    let carrier_hz = preset_options.carrier.to_hz();
+    let shared_volume = SharedVolume::new(volume);
+
+    let mut sink = CpalSink::open(device_name)?;
+    let playback = start_tones(
+        ToneSettings {
+            waveform,
+            ..ToneSettings::steady(carrier_hz, 0.0)
+        },
+        shared_volume.clone(),
+        CancelToken::new(),
+        &mut sink,
+    )?;
+
+    println!("--- Sound Check ---");
+    println!(
+        "Playing the {:.2} Hz carrier. Is the volume comfortable?",
+        carrier_hz
+    );
+    println!("Press + / - to adjust, Enter to start the session, Esc to cancel.");
+    println!(
+        "Press d to type an exact output level, from {:.0} to {:.1} dBFS.",
+        MIN_OUTPUT_DB,
+        max_output_db()
+    );
+
+    terminal::enable_raw_mode()?;
+    let outcome = adjust_until_done(&shared_volume);
+    terminal::disable_raw_mode()?;
+    println!();
+
+    playback.stop()?;
+    outcome
+}
+
+/// Reads key presses while the calibration tone plays, updating the volume until the user is done.
+fn adjust_until_done(volume: &SharedVolume) -> Result<CalibrationOutcome, Error> {
+    let mut last_change = Instant::now();
+    // The output level being typed in, while `d` has been pressed and the entry isn't finished.
+    let mut db_entry: Option<String> = None;
+    print_volume(volume.get())?;
+
+    while last_change.elapsed() < CALIBRATION_TONE_LENGTH {
+        if !event::poll(StdDuration::from_millis(100))? {
+            continue;
+        }
+
+        if let Event::Key(key_event) = event::read()? {
+            if key_event.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            // Raw mode swallows Ctrl+C, so treat it like Esc.
+            if key_event.code == KeyCode::Char('c')
+                && key_event.modifiers.contains(KeyModifiers::CONTROL)
+            {
+                return Ok(CalibrationOutcome::Cancelled);
+            }
+
+            if let Some(entry) = db_entry.as_mut() {
+                match key_event.code {
+                    KeyCode::Char(c) if c.is_ascii_digit() || c == '-' || c == '.' => {
+                        entry.push(c);
+                    }
+                    KeyCode::Backspace => {
+                        entry.pop();
+                    }
+                    KeyCode::Enter => match parse_db(entry).and_then(volume_for_output_db) {
+                        Some(level) => {
+                            volume.set(level);
+                            db_entry = None;
+                        }
+                        None => entry.clear(),
+                    },
+                    // Esc only leaves the entry, the sound check carries on.
+                    KeyCode::Esc => db_entry = None,
+                    _ => continue,
+                }
+
+                last_change = Instant::now();
+                match &db_entry {
+                    Some(entry) => print_db_entry(entry)?,
+                    None => print_volume(volume.get())?,
+                }
+                continue;
+            }
+
+            match key_event.code {
+                KeyCode::Char('+') | KeyCode::Char('=') => {
+                    volume.set(step_volume(volume.get(), true));
+                }
+                KeyCode::Char('-') | KeyCode::Char('_') => {
+                    volume.set(step_volume(volume.get(), false));
+                }
+                KeyCode::Char('d') => {
+                    db_entry = Some(String::new());
+                    last_change = Instant::now();
+                    print_db_entry("")?;
+                    continue;
+                }
+                KeyCode::Enter => break,
+                KeyCode::Esc => return Ok(CalibrationOutcome::Cancelled),
+                _ => continue,
+            }
+
+            last_change = Instant::now();
+            print_volume(volume.get())?;
+        }
+    }
+
+    Ok(CalibrationOutcome::Accepted(volume.get()))
+}
+
+/// Redraws the current volume on the same line, with its gain and the level the tone plays at.
+fn print_volume(level: f32) -> Result<(), Error> {
+    print!(
+        "\r\x1b[KVolume: {:>3.0}%  Gain: {} dB  Output: {} dBFS",
+        level * 100.0,
+        format_db(gain_db(level)),
+        format_db(output_level_db(level))
+    );
+    io::stdout().flush()?;
+    Ok(())
+}
+
+/// Redraws the output level being typed in on the same line.
+fn print_db_entry(entry: &str) -> Result<(), Error> {
+    print!("\r\x1b[KOutput level (dBFS): {}", entry);
+    io::stdout().flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    macro_rules! test_step_volume_cases {
+        ($($name:ident:($level:expr, $up:expr, $expected:expr),)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    assert_eq!(step_volume($level, $up),$expected)
+                }
+            )*
+        };
+    }
+
+    test_step_volume_cases! {
+        step_volume_up: (0.5, true, 0.55),
+        step_volume_down: (0.5, false, 0.45),
+        step_volume_up_is_capped: (0.98, true, 1.0),
+        step_volume_down_is_floored: (0.02, false, 0.0),
+        step_volume_rounds_to_whole_percent: (0.333, true, 0.38),
+    }
+
+    macro_rules! test_volume_for_output_db_cases {
+        ($($name:ident:($db:expr, $expected:expr),)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let expected: Option<f32> = $expected;
+                    match (expected, volume_for_output_db($db)) {
+                        (Some(expected), Some(level)) => assert!((level - expected).abs() < 1e-4, "{} != {}", level, expected),
+                        (expected, level) => assert_eq!(level, expected),
+                    }
+                }
+            )*
+        };
+    }
+
+    test_volume_for_output_db_cases! {
+        volume_for_loudest_output: (-6.0206, Some(1.0)),
+        volume_for_rounded_loudest_output: (-6.0, Some(1.0)),
+        volume_for_half_level_output: (-12.0412, Some(0.5)),
+        volume_for_quietest_output: (-60.0, Some(0.002)),
+        volume_for_too_loud_output: (0.0, None),
+        volume_for_too_quiet_output: (-61.0, None),
+        volume_for_infinite_output: (f32::NEG_INFINITY, None),
+    }
+
+    macro_rules! test_parse_db_cases {
+        ($($name:ident:($text:expr, $expected:expr),)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    assert_eq!(parse_db($text),$expected)
+                }
+            )*
+        };
+    }
+
+    test_parse_db_cases! {
+        parse_db_plain_number: ("-18", Some(-18.0)),
+        parse_db_with_unit: ("-18 dB", Some(-18.0)),
+        parse_db_with_full_unit: ("-18.5dBFS", Some(-18.5)),
+        parse_db_empty: ("", None),
+        parse_db_text: ("loud", None),
+    }
+
+    #[test]
+    fn output_level_of_full_volume_leaves_headroom() {
+        assert!((output_level_db(1.0).unwrap() - max_output_db()).abs() < 1e-6);
+        assert_eq!(gain_db(1.0), Some(0.0));
+        assert_eq!(gain_db(0.0), None);
+        assert_eq!(output_level_db(0.0), None);
+    }
+
+    #[test]
+    fn output_level_round_trips_through_volume() {
+        let level = volume_for_output_db(-20.0).unwrap();
+
+        assert!((output_level_db(level).unwrap() + 20.0).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,107 @@
+//! A module that contains the token that stops a session, shared between the player, the audio callback
+//! and anything else that should be able to stop it, like a thread reading the keyboard.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration as StdDuration;
+
+/// How long the tones fade out for when the user stops a session, short enough to feel immediate
+/// but long enough that the sound doesn't cut off with a click.
+pub const STOP_FADE_OUT: StdDuration = StdDuration::from_millis(1_500);
+
+/// Stops a session once it is cancelled. Clones share the same token, so any of them can cancel it.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<CancelState>);
+
+#[derive(Debug, Default)]
+struct CancelState {
+    cancelled: AtomicBool,
+    /// How long the tones fade out for once cancelled, in milliseconds.
+    fade_millis: AtomicU64,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        CancelToken::default()
+    }
+
+    /// Cancels the session, silencing the output straight away.
+    pub fn cancel(&self) {
+        self.cancel_with_fade(StdDuration::ZERO);
+    }
+
+    /// Cancels the session, letting the tones fade to silence over `fade` before it ends.
+    /// Only the first cancel counts, cancelling again doesn't change the fade.
+    pub fn cancel_with_fade(&self, fade: StdDuration) {
+        if self.is_cancelled() {
+            return;
+        }
+
+        // The fade is stored first, so that whoever sees the cancel also sees its fade.
+        self.0
+            .fade_millis
+            .store(fade.as_millis() as u64, Ordering::Relaxed);
+        self.0.cancelled.store(true, Ordering::Release);
+    }
+
+    /// Returns true once the session has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::Acquire)
+    }
+
+    /// Returns how long the tones fade out for after the cancel, zero when they stop straight away.
+    pub fn fade(&self) -> StdDuration {
+        StdDuration::from_millis(self.0.fade_millis.load(Ordering::Relaxed))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn new_token_is_not_cancelled() {
+        let cancel_token = CancelToken::new();
+
+        assert!(!cancel_token.is_cancelled());
+        assert_eq!(cancel_token.fade(), StdDuration::ZERO);
+    }
+
+    #[test]
+    fn cancel_stops_without_a_fade() {
+        let cancel_token = CancelToken::new();
+        cancel_token.cancel();
+
+        assert!(cancel_token.is_cancelled());
+        assert_eq!(cancel_token.fade(), StdDuration::ZERO);
+    }
+
+    #[test]
+    fn cancel_with_fade_keeps_the_fade() {
+        let cancel_token = CancelToken::new();
+        cancel_token.cancel_with_fade(StdDuration::from_millis(250));
+
+        assert!(cancel_token.is_cancelled());
+        assert_eq!(cancel_token.fade(), StdDuration::from_millis(250));
+    }
+
+    #[test]
+    fn only_the_first_cancel_counts() {
+        let cancel_token = CancelToken::new();
+        cancel_token.cancel_with_fade(StdDuration::from_secs(2));
+        cancel_token.cancel();
+
+        assert_eq!(cancel_token.fade(), StdDuration::from_secs(2));
+    }
+
+    #[test]
+    fn clones_share_the_token() {
+        let cancel_token = CancelToken::new();
+        let clone = cancel_token.clone();
+
+        thread::spawn(move || clone.cancel()).join().unwrap();
+
+        assert!(cancel_token.is_cancelled());
+    }
+}
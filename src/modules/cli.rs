@@ -0,0 +1,345 @@
+//! A module that contains the command line arguments the program accepts.
+
+use std::path::PathBuf;
+
+use clap::builder::PossibleValuesParser;
+use clap::{Parser, Subcommand};
+
+use crate::modules::duration::duration::Duration;
+use crate::modules::frequency::easing::Easing;
+use crate::modules::manual::preset_catalog;
+use crate::modules::output::output_common::AudioOutput;
+use crate::modules::output::pipe_output::{DEFAULT_PIPE_SAMPLE_RATE, PcmFormat};
+use crate::modules::preset::{PRESET_TAGS, Preset};
+use crate::modules::start_time::StartTime;
+use crate::modules::synth::waveform::Waveform;
+
+/// Listen to binaural beat tones on your machine.
+#[derive(Debug, Parser)]
+#[command(version, about, after_long_help = preset_catalog(false))]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Play this preset, like `focus` or `deep-relaxation`, instead of choosing it from a list. It plays for its
+    /// default duration unless `--duration` is given. `--json` and `--output pipe` can't ask for a preset,
+    /// so they need this or `--queue`.
+    #[arg(long, value_name = "PRESET", group = "session")]
+    pub preset: Option<Preset>,
+
+    /// Play for this long instead of choosing the duration from a list, in minutes or hours like 45, 90m or 1h 30m.
+    #[arg(long, value_name = "MINUTES", conflicts_with = "queue")]
+    pub duration: Option<Duration>,
+
+    /// Play a short calibration tone to pick a comfortable volume before the session starts.
+    #[arg(long)]
+    pub calibrate: bool,
+
+    /// The output volume as a percentage of the loudest level.
+    #[arg(long, default_value_t = 100, value_parser = clap::value_parser!(u8).range(0..=100))]
+    pub volume: u8,
+
+    /// Start both ears on the carrier and grow the beat to its full frequency over this many seconds.
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        num_args = 0..=1,
+        default_missing_value = "90"
+    )]
+    pub ease_in: Option<u64>,
+
+    /// The shape of the beat's growth during the ease in.
+    #[arg(long, value_enum, default_value_t = Easing::Linear)]
+    pub ease_in_curve: Easing,
+
+    /// Swap which ear carries the higher frequency every this many minutes, gliding over a few seconds.
+    #[arg(
+        long,
+        value_name = "MINUTES",
+        num_args = 0..=1,
+        default_missing_value = "5",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub alternate: Option<u64>,
+
+    /// Move the beat from the preset's frequency to this one over the whole session.
+    #[arg(long, value_name = "HZ")]
+    pub ramp_to: Option<f32>,
+
+    /// The shape of the beat's change when ramping, logarithmic slows down towards the low end.
+    #[arg(long, value_enum, default_value_t = Easing::Linear)]
+    pub ramp_curve: Easing,
+
+    /// The shape of the tone played in each ear, which changes its timbre.
+    #[arg(long, value_enum, default_value_t = Waveform::Sine)]
+    pub waveform: Waveform,
+
+    /// Play through the output device with this name, leave the name out to choose from a list.
+    #[arg(long, value_name = "NAME")]
+    pub device: Option<Option<String>>,
+
+    /// Send the audio somewhere other than an output device, `pipe` writes it to stdout as raw stereo PCM
+    /// for another program, like sox or ffmpeg, instead of playing it.
+    #[arg(
+        long,
+        value_enum,
+        value_name = "KIND",
+        requires = "session",
+        conflicts_with_all = ["json", "queue", "calibrate", "start_at", "device"]
+    )]
+    pub output: Option<OutputKind>,
+
+    /// The sample format of the raw PCM written by `--output pipe`, little endian.
+    #[arg(long, value_enum, default_value_t = PcmFormat::F32)]
+    pub pcm_format: PcmFormat,
+
+    /// The sample rate of the raw PCM written by `--output pipe`.
+    #[arg(
+        long,
+        value_name = "HZ",
+        default_value_t = DEFAULT_PIPE_SAMPLE_RATE,
+        value_parser = clap::value_parser!(u32).range(8_000..=192_000)
+    )]
+    pub sample_rate: u32,
+
+    /// Play the session this many times back to back, leave the number out to repeat it until stopped.
+    #[arg(long, value_name = "TIMES", value_parser = clap::value_parser!(u32).range(1..))]
+    pub repeat: Option<Option<u32>>,
+
+    /// Play a single tone at the carrier frequency that sweeps between the ears this many times a second,
+    /// from 0.5 to 2, instead of a binaural beat.
+    #[arg(
+        long,
+        value_name = "HZ",
+        num_args = 0..=1,
+        default_missing_value = "1",
+        conflicts_with_all = ["ease_in", "alternate", "ramp_to"]
+    )]
+    pub bilateral: Option<f32>,
+
+    /// Play these presets one after the other, each for its default duration, like `focus,relaxation,sleep`.
+    /// Leave the presets out to choose them from a list. Type `n` and press Enter while playing to skip to the next one.
+    #[arg(
+        long,
+        value_name = "PRESETS",
+        num_args = 0..=1,
+        value_delimiter = ',',
+        group = "session",
+        conflicts_with_all = ["repeat", "calibrate"]
+    )]
+    pub queue: Option<Vec<Preset>>,
+
+    /// Leave this many seconds of silence between the queued presets.
+    #[arg(long, value_name = "SECONDS", requires = "queue")]
+    pub gap: Option<u64>,
+
+    /// Fade each queued preset into the next over this many seconds instead of stopping between them.
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        requires = "queue",
+        conflicts_with = "gap"
+    )]
+    pub crossfade: Option<u64>,
+
+    /// Loop a recording, like rain or the ocean, underneath the tones. WAV and FLAC files up to 10 minutes long can be played.
+    #[arg(long, value_name = "FILE")]
+    pub ambient: Option<PathBuf>,
+
+    /// The level of the looping recording as a percentage of the tones' level.
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        default_value_t = 50,
+        requires = "ambient",
+        value_parser = clap::value_parser!(u8).range(0..=100)
+    )]
+    pub ambient_volume: u8,
+
+    /// Fade the tones to silence over the last this many minutes of the session, 0 turns off the sleep presets' fade.
+    #[arg(long, value_name = "MINUTES")]
+    pub fade_out: Option<u64>,
+
+    /// Wait until this time of day, like 22:30, before starting the session, leave the time out to be asked for it.
+    #[arg(long, value_name = "HH:MM")]
+    pub start_at: Option<Option<StartTime>>,
+
+    /// Show a full-screen dashboard with the session's progress and volume while it plays, where the arrow keys nudge the beat.
+    #[cfg(feature = "tui")]
+    #[arg(long, conflicts_with_all = ["json", "queue", "output"])]
+    pub tui: bool,
+
+    /// Print the session as JSON events, one object per line, instead of the banner, settings and progress bar.
+    #[arg(long, requires = "session", conflicts_with_all = ["calibrate", "start_at"])]
+    pub json: bool,
+
+    /// Only offer presets that carry this tag, repeat it to require several tags.
+    #[arg(long, global = true, value_parser = PossibleValuesParser::new(PRESET_TAGS))]
+    pub tag: Vec<String>,
+}
+
+impl Cli {
+    /// Returns whether the full-screen dashboard should be shown, which is only built with the `tui` feature.
+    pub fn wants_dashboard(&self) -> bool {
+        #[cfg(feature = "tui")]
+        return self.tui;
+        #[cfg(not(feature = "tui"))]
+        return false;
+    }
+
+    /// Returns where the session's audio goes, through the named device unless it is written to a pipe.
+    pub fn audio_output(&self, device_name: Option<&str>) -> AudioOutput {
+        match self.output {
+            None => AudioOutput::Device(device_name.map(str::to_string)),
+            Some(OutputKind::Pipe) => AudioOutput::Pipe {
+                sample_rate: self.sample_rate,
+                format: self.pcm_format,
+            },
+        }
+    }
+
+    /// Returns whether the banner, settings and progress are printed on stdout,
+    /// which the JSON events and the piped audio keep for themselves.
+    pub fn prints_text(&self) -> bool {
+        !self.json && self.output.is_none()
+    }
+}
+
+/// Where the audio of a session goes instead of an output device.
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum OutputKind {
+    /// Write raw PCM to stdout.
+    Pipe,
+}
+
+/// The subcommands that run instead of the interactive session.
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// List the presets with their frequencies, default duration and tags.
+    #[command(after_long_help = preset_catalog(true))]
+    ListPresets,
+    /// List the output devices of every audio host with the configurations they support.
+    ListDevices {
+        /// Print the devices as JSON instead of a table.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Show the total listening time per preset and per week from the history of sessions played.
+    Stats,
+    /// Check that the presets, saved presets, rendering and output device work, without starting a session.
+    Check {
+        /// Leave out the output device, for machines without audio hardware.
+        #[arg(long)]
+        no_audio: bool,
+    },
+    /// Print the manual page, which can be read with `man -l -` or installed into a man directory.
+    Man,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use clap::CommandFactory;
+    use clap::error::ErrorKind;
+
+    #[test]
+    fn cli_definition_is_valid() {
+        Cli::command().debug_assert();
+    }
+
+    macro_rules! test_pipe_conflict_cases {
+        ($($name:ident:($option:expr),)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let err = Cli::try_parse_from(["bbgen", "--output", "pipe", "--preset", "focus", $option])
+                        .unwrap_err();
+
+                    assert_eq!(err.kind(), ErrorKind::ArgumentConflict)
+                }
+            )*
+        };
+    }
+
+    test_pipe_conflict_cases! {
+        pipe_conflicts_with_json: ("--json"),
+        pipe_conflicts_with_queue: ("--queue"),
+        pipe_conflicts_with_calibrate: ("--calibrate"),
+        pipe_conflicts_with_start_at: ("--start-at"),
+        pipe_conflicts_with_device: ("--device"),
+    }
+
+    macro_rules! test_preset_required_cases {
+        ($($name:ident:($args:expr, $expected:expr),)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let err = Cli::try_parse_from($args).err().map(|err| err.kind());
+
+                    assert_eq!(err, $expected)
+                }
+            )*
+        };
+    }
+
+    test_preset_required_cases! {
+        preset_not_needed_interactively: (vec!["bbgen"], None),
+        preset_needed_with_json: (vec!["bbgen", "--json"], Some(ErrorKind::MissingRequiredArgument)),
+        preset_needed_with_pipe: (vec!["bbgen", "--output", "pipe"], Some(ErrorKind::MissingRequiredArgument)),
+        preset_given_with_json: (vec!["bbgen", "--json", "--preset", "focus"], None),
+        preset_not_needed_with_a_json_queue: (vec!["bbgen", "--json", "--queue", "focus,sleep"], None),
+        preset_conflicts_with_queue: (vec!["bbgen", "--preset", "focus", "--queue"], Some(ErrorKind::ArgumentConflict)),
+        duration_conflicts_with_queue: (vec!["bbgen", "--duration", "45", "--queue"], Some(ErrorKind::ArgumentConflict)),
+        unknown_preset: (vec!["bbgen", "--preset", "nap"], Some(ErrorKind::ValueValidation)),
+        unreadable_duration: (vec!["bbgen", "--duration", "soon"], Some(ErrorKind::ValueValidation)),
+    }
+
+    #[test]
+    fn preset_and_duration_are_read_from_text() {
+        let cli = Cli::try_parse_from([
+            "bbgen",
+            "--preset",
+            "deep-relaxation",
+            "--duration",
+            "1h 30m",
+        ])
+        .unwrap();
+
+        assert_eq!(cli.preset, Some(Preset::DeepRelaxation));
+        assert_eq!(cli.duration, Some(Duration::Custom(90)));
+    }
+
+    #[test]
+    fn pipe_output_is_written_raw() {
+        let cli = Cli::try_parse_from([
+            "bbgen",
+            "--output",
+            "pipe",
+            "--preset",
+            "focus",
+            "--pcm-format",
+            "s16",
+        ])
+        .unwrap();
+
+        assert!(!cli.prints_text());
+        assert!(matches!(
+            cli.audio_output(None),
+            AudioOutput::Pipe {
+                sample_rate: DEFAULT_PIPE_SAMPLE_RATE,
+                format: PcmFormat::S16
+            }
+        ));
+    }
+
+    #[test]
+    fn device_output_is_the_default() {
+        let cli = Cli::try_parse_from(["bbgen", "--device", "Speakers"]).unwrap();
+
+        assert!(cli.prints_text());
+        assert!(matches!(
+            cli.audio_output(Some("Speakers")),
+            AudioOutput::Device(Some(name)) if name == "Speakers"
+        ));
+    }
+}
@@ -0,0 +1,176 @@
+//! A module that contains the optional full-screen dashboard shown while a session plays.
+
+use std::time::Duration as StdDuration;
+
+use anyhow::Error;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Gauge, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::modules::bb_generator::{BEAT_NUDGE_HZ, BeatMode, BinauralPlayer};
+use crate::modules::calibration::step_volume;
+use crate::modules::cancel_token::STOP_FADE_OUT;
+use crate::modules::frequency::frequency_common::ToFrequency;
+use crate::modules::output::output_watchdog::OutputHealth;
+use crate::modules::preset::BinauralPresetGroup;
+use crate::modules::progress::format_clock;
+
+/// How long the dashboard waits for a key press before redrawing.
+const DASHBOARD_POLL_INTERVAL: StdDuration = StdDuration::from_millis(200);
+
+/// Takes over the terminal and shows the session until it stops playing.
+/// The dashboard reads the keyboard itself: `+` and `-` change the volume, the up and down arrows nudge the beat
+/// and Enter, `q`, Esc or Ctrl+C stop the session.
+pub fn run_dashboard(
+    player: &mut BinauralPlayer,
+    preset_options: BinauralPresetGroup,
+) -> Result<(), Error> {
+    let mut terminal = ratatui::try_init()?;
+    let result = dashboard_loop(&mut terminal, player, preset_options);
+    ratatui::restore();
+    result
+}
+
+fn dashboard_loop(
+    terminal: &mut DefaultTerminal,
+    player: &mut BinauralPlayer,
+    preset_options: BinauralPresetGroup,
+) -> Result<(), Error> {
+    let mut output_health = OutputHealth::Recovered;
+
+    while player.is_playing() {
+        if let Some(change) = player.take_output_health() {
+            output_health = change;
+        }
+
+        terminal.draw(|frame| draw(frame, player, preset_options, output_health))?;
+
+        if !event::poll(DASHBOARD_POLL_INTERVAL)? {
+            continue;
+        }
+
+        if let Event::Key(key_event) = event::read()?
+            && key_event.kind == KeyEventKind::Press
+        {
+            match key_event.code {
+                KeyCode::Char('+') | KeyCode::Char('=') => {
+                    player.set_volume(step_volume(player.volume(), true));
+                }
+                KeyCode::Char('-') | KeyCode::Char('_') => {
+                    player.set_volume(step_volume(player.volume(), false));
+                }
+                KeyCode::Up => player.set_beat_offset(player.beat_offset() + BEAT_NUDGE_HZ),
+                KeyCode::Down => player.set_beat_offset(player.beat_offset() - BEAT_NUDGE_HZ),
+                KeyCode::Enter | KeyCode::Esc | KeyCode::Char('q') => {
+                    player.stop_with_fade(STOP_FADE_OUT);
+                }
+                // Raw mode swallows Ctrl+C, so treat it like Esc.
+                KeyCode::Char('c') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                    player.stop_with_fade(STOP_FADE_OUT);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Draws the session's settings, progress, volume and key hints.
+fn draw(
+    frame: &mut Frame,
+    player: &BinauralPlayer,
+    preset_options: BinauralPresetGroup,
+    output_health: OutputHealth,
+) {
+    let carrier_hz = preset_options.carrier.to_hz();
+    let beat_offset = player.beat_offset();
+    let beat_hz = preset_options.beat.to_hz() + beat_offset;
+    let progress = player.progress();
+
+    let [
+        settings_area,
+        progress_area,
+        volume_area,
+        status_area,
+        hints_area,
+    ] = Layout::vertical([
+        Constraint::Length(7),
+        Constraint::Length(3),
+        Constraint::Length(3),
+        Constraint::Length(1),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    let mut settings_lines = vec![
+        Line::from(format!("Session ID: {}", player.session_id())),
+        Line::from(format!("Carrier Frequency: {:.2} Hz", carrier_hz)),
+    ];
+    match player.settings().playback_options.beat_mode {
+        BeatMode::Binaural => settings_lines.extend([
+            Line::from(if beat_offset == 0.0 {
+                format!("Beat Frequency: {:.2} Hz", beat_hz)
+            } else {
+                format!(
+                    "Beat Frequency: {:.2} Hz (nudged {:+.2} Hz)",
+                    beat_hz, beat_offset
+                )
+            }),
+            Line::from(format!(
+                "Left Ear Frequency: {:.2} Hz",
+                carrier_hz - beat_hz / 2.0
+            )),
+            Line::from(format!(
+                "Right Ear Frequency: {:.2} Hz",
+                carrier_hz + beat_hz / 2.0
+            )),
+        ]),
+        BeatMode::Bilateral { rate_hz } => settings_lines.push(Line::from(format!(
+            "Bilateral: sweeping between the ears {:.2} times a second",
+            rate_hz
+        ))),
+    }
+
+    let settings = Paragraph::new(settings_lines)
+        .block(Block::bordered().title(format!(" {} ", preset_options.preset).bold()));
+    frame.render_widget(settings, settings_area);
+
+    let progress_gauge = Gauge::default()
+        .block(Block::bordered().title(match progress.round_text() {
+            Some(round_text) => format!(" Progress, {} ", round_text),
+            None => " Progress ".to_string(),
+        }))
+        .gauge_style(Style::new().fg(Color::Blue))
+        .ratio(progress.fraction())
+        .label(format!(
+            "{} elapsed, {} remaining",
+            format_clock(progress.elapsed),
+            format_clock(progress.remaining())
+        ));
+    frame.render_widget(progress_gauge, progress_area);
+
+    let volume_gauge = Gauge::default()
+        .block(Block::bordered().title(" Volume "))
+        .gauge_style(Style::new().fg(Color::Green))
+        .ratio(player.volume().clamp(0.0, 1.0) as f64)
+        .label(format!("{:.0}%", player.volume() * 100.0));
+    frame.render_widget(volume_gauge, volume_area);
+
+    if matches!(output_health, OutputHealth::Stalled | OutputHealth::Silent) {
+        frame.render_widget(
+            Paragraph::new(output_health.to_string()).fg(Color::Yellow),
+            status_area,
+        );
+    }
+
+    // Bilateral mode doesn't play the beat, so there is nothing to nudge.
+    let hints = match player.settings().playback_options.beat_mode {
+        BeatMode::Binaural => "+/- volume   ↑/↓ beat   Enter/q/Esc stop",
+        BeatMode::Bilateral { .. } => "+/- volume   Enter/q/Esc stop",
+    };
+    frame.render_widget(Paragraph::new(hints).dim(), hints_area);
+}
@@ -1,11 +1,19 @@
 //! A module that contains code related to the duration functionality.
 
 use std::fmt;
+use std::str::FromStr;
 
 use crate::modules::duration::duration_common::ToMinutes;
+use crate::modules::parse::ParseValueError;
 
-/// Represents common durations in minutes.
+/// The longest duration that can be typed in, a full day.
+pub const MAX_CUSTOM_MINUTES: u32 = 24 * 60;
+
+/// Represents common durations in minutes, or any other number of minutes the user typed in.
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
 pub enum Duration {
     FiveMinutes,
     TenMinutes,
@@ -16,6 +24,7 @@ pub enum Duration {
     FortyMinutes,
     FiftyMinutes,
     SixtyMinutes,
+    Custom(u32),
 }
 
 /// This formatter will return the number of minutes for the given duration enum.
@@ -31,6 +40,7 @@ impl fmt::Display for Duration {
             Duration::FortyMinutes => write!(f, "40 min"),
             Duration::FiftyMinutes => write!(f, "50 min"),
             Duration::SixtyMinutes => write!(f, "60 min"),
+            Duration::Custom(minutes) => write!(f, "{} min", minutes),
         }
     }
 }
@@ -48,13 +58,52 @@ impl ToMinutes for Duration {
             Duration::FortyMinutes => 40,
             Duration::FiftyMinutes => 50,
             Duration::SixtyMinutes => 60,
+            Duration::Custom(minutes) => *minutes,
+        }
+    }
+}
+
+impl Duration {
+    /// Returns the listed duration with this many minutes, or a custom one when none of them match.
+    pub fn from_minutes(minutes: u32) -> Self {
+        duration_list()
+            .into_iter()
+            .find(|duration| duration.to_minutes() == minutes)
+            .unwrap_or(Duration::Custom(minutes))
+    }
+}
+
+/// This implementation reads a duration such as `45m`, `90` or `1h 30m` the same way as the custom duration prompt.
+impl FromStr for Duration {
+    type Err = ParseValueError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        parse_minutes(text)
+            .map(Duration::from_minutes)
+            .ok_or_else(|| ParseValueError::new("duration", text))
+    }
+}
+
+/// Represents an entry in the duration menu, either one of the common durations or the option to type one in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DurationChoice {
+    Listed(Duration),
+    Custom,
+}
+
+/// This formatter will return the text shown for the entry in the duration menu.
+impl fmt::Display for DurationChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DurationChoice::Listed(duration) => write!(f, "{}", duration),
+            DurationChoice::Custom => write!(f, "Custom..."),
         }
     }
 }
 
 /// This function will return the a vector list of all the supported durations.
 pub fn duration_list() -> Vec<Duration> {
-    return vec![
+    vec![
         Duration::FiveMinutes,
         Duration::TenMinutes,
         Duration::FifteenMinutes,
@@ -64,7 +113,64 @@ pub fn duration_list() -> Vec<Duration> {
         Duration::FortyMinutes,
         Duration::FiftyMinutes,
         Duration::SixtyMinutes,
-    ];
+    ]
+}
+
+/// This function will return the entries of the duration menu, the supported durations followed by the custom entry.
+pub fn duration_choice_list() -> Vec<DurationChoice> {
+    duration_list()
+        .into_iter()
+        .map(DurationChoice::Listed)
+        .chain([DurationChoice::Custom])
+        .collect()
+}
+
+/// Returns where the cursor starts in the duration menu for the given duration,
+/// on the custom entry for a typed in duration so that it can be kept by pressing Enter twice.
+pub fn duration_choice_index(duration: Duration) -> usize {
+    let choice = match duration {
+        Duration::Custom(_) => DurationChoice::Custom,
+        listed => DurationChoice::Listed(listed),
+    };
+
+    duration_choice_list()
+        .iter()
+        .position(|&entry| entry == choice)
+        .unwrap_or(0)
+}
+
+/// Reads a typed in duration such as `90`, `90 min`, `8h` or `1h 30m` into minutes.
+/// A number without a unit counts as minutes, and the total has to be between 1 minute and `MAX_CUSTOM_MINUTES`.
+pub fn parse_minutes(text: &str) -> Option<u32> {
+    let text = text.trim().to_lowercase();
+    let mut rest = text.as_str();
+    let mut total: u32 = 0;
+
+    if rest.is_empty() {
+        return None;
+    }
+
+    while !rest.is_empty() {
+        let digits_end = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let amount: u32 = rest[..digits_end].parse().ok()?;
+        rest = rest[digits_end..].trim_start();
+
+        let unit_end = rest
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(rest.len());
+        let minutes = match &rest[..unit_end] {
+            "" | "m" | "min" | "mins" | "minute" | "minutes" => amount,
+            "h" | "hr" | "hrs" | "hour" | "hours" => amount.checked_mul(60)?,
+            _ => return None,
+        };
+        rest = rest[unit_end..].trim_start();
+
+        total = total.checked_add(minutes)?;
+    }
+
+    (1..=MAX_CUSTOM_MINUTES).contains(&total).then_some(total)
 }
 
 #[cfg(test)]
@@ -82,6 +188,17 @@ mod tests {
         };
     }
 
+    macro_rules! test_parse_minutes_cases {
+        ($($name:ident:($a:expr, $expected:expr),)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    assert_eq!(parse_minutes($a),$expected)
+                }
+            )*
+        };
+    }
+
     macro_rules! test_duration_enum_to_text_minutes_cases {
         ($($name:ident:($a:expr, $expected:expr),)*) => {
             $(
@@ -111,6 +228,37 @@ mod tests {
         assert_eq!(existing_list, expected_list);
     }
 
+    #[test]
+    fn duration_choice_list_ends_with_custom() {
+        let choices = duration_choice_list();
+
+        assert_eq!(choices.len(), duration_list().len() + 1);
+        assert_eq!(
+            choices.first(),
+            Some(&DurationChoice::Listed(Duration::FiveMinutes))
+        );
+        assert_eq!(choices.last(), Some(&DurationChoice::Custom));
+    }
+
+    macro_rules! test_duration_choice_index_cases {
+        ($($name:ident:($a:expr, $expected:expr),)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    assert_eq!(duration_choice_index($a),$expected)
+                }
+            )*
+        };
+    }
+
+    test_duration_choice_index_cases! {
+        choice_index_first_listed: (Duration::FiveMinutes,0),
+        choice_index_listed: (Duration::ThirtyMinutes,4),
+        choice_index_last_listed: (Duration::SixtyMinutes,8),
+        choice_index_custom: (Duration::Custom(90),9),
+        choice_index_custom_matching_a_listed_duration: (Duration::Custom(30),9),
+    }
+
     #[test]
     fn duration_list_has_expected_sequence_for_items() {
         let existing_list = duration_list();
@@ -144,6 +292,7 @@ mod tests {
         forty_minutes_integer: (&Duration::FortyMinutes,40),
         fifty_minutes_integer: (&Duration::FiftyMinutes,50),
         sixty_minutes_integer: (&Duration::SixtyMinutes,60),
+        custom_minutes_integer: (&Duration::Custom(480),480),
     }
 
     test_duration_enum_to_text_minutes_cases! {
@@ -156,5 +305,56 @@ mod tests {
         forty_minutes_text: (Duration::FortyMinutes.to_string(),"40 min"),
         fifty_minutes_text: (Duration::FiftyMinutes.to_string(),"50 min"),
         sixty_minutes_text: (Duration::SixtyMinutes.to_string(),"60 min"),
+        custom_minutes_text: (Duration::Custom(90).to_string(),"90 min"),
+        listed_choice_text: (DurationChoice::Listed(Duration::TenMinutes).to_string(),"10 min"),
+        custom_choice_text: (DurationChoice::Custom.to_string(),"Custom..."),
+    }
+
+    test_parse_minutes_cases! {
+        parse_plain_number: ("90",Some(90)),
+        parse_minutes_suffix: ("90m",Some(90)),
+        parse_minutes_word: (" 90 Minutes ",Some(90)),
+        parse_hours: ("8h",Some(480)),
+        parse_hours_word: ("8 hours",Some(480)),
+        parse_hours_and_minutes: ("1h 30m",Some(90)),
+        parse_hours_and_minutes_joined: ("1h30",Some(90)),
+        parse_full_day: ("24h",Some(1440)),
+        parse_longer_than_a_day: ("25h",None),
+        parse_zero: ("0",None),
+        parse_empty: ("",None),
+        parse_unknown_unit: ("90s",None),
+        parse_missing_number: ("h",None),
+        parse_negative: ("-5",None),
+    }
+
+    macro_rules! test_duration_from_str_cases {
+        ($($name:ident:($a:expr, $expected:expr),)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    assert_eq!($a.parse::<Duration>().ok(),$expected)
+                }
+            )*
+        };
+    }
+
+    test_duration_from_str_cases! {
+        listed_duration_from_str: ("30m",Some(Duration::ThirtyMinutes)),
+        listed_duration_from_hours: ("1h",Some(Duration::SixtyMinutes)),
+        custom_duration_from_str: ("45m",Some(Duration::Custom(45))),
+        custom_duration_from_hours_and_minutes: ("1h 30m",Some(Duration::Custom(90))),
+        duration_from_str_too_long: ("25h",None),
+        duration_from_str_unknown: ("soon",None),
+    }
+
+    #[test]
+    fn duration_from_str_error_names_the_input() {
+        assert_eq!(
+            "soon".parse::<Duration>(),
+            Err(ParseValueError {
+                kind: "duration",
+                input: "soon".to_string(),
+            })
+        );
     }
 }
@@ -1,4 +1,5 @@
 //! A module that contains references related to the duration funcitonality.
 //!
+#[allow(clippy::module_inception)]
 pub mod duration;
 pub mod duration_common;
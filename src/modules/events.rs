@@ -0,0 +1,132 @@
+//! A module that contains the JSON events printed in `--json` mode, one object per line on stdout,
+//! so that other programs can follow a session without reading the text meant for people.
+
+use std::thread;
+use std::time::Duration as StdDuration;
+
+use anyhow::Error;
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use crate::modules::bb_generator::{BinauralPlayer, EVENT_POLL_INTERVAL};
+
+/// Returns the event as a JSON object, its name under `event` together with the fields of `fields`.
+pub fn event_json(event: &str, fields: Value) -> Value {
+    let mut value = json!({ "event": event });
+
+    if let (Some(object), Value::Object(fields)) = (value.as_object_mut(), fields) {
+        object.extend(fields);
+    }
+
+    value
+}
+
+/// Returns the event of a playing session as a JSON object like `event_json`, with the session's ID under `session_id`
+/// so that the events of one session can be told apart from those of the next.
+pub fn session_event_json(event: &str, session_id: Uuid, fields: Value) -> Value {
+    let mut value = event_json(event, fields);
+    value["session_id"] = json!(session_id.to_string());
+
+    value
+}
+
+/// Prints the event on its own line of stdout.
+pub fn emit_event(event: &str, fields: Value) {
+    println!("{}", event_json(event, fields));
+}
+
+/// Prints the event of a playing session on its own line of stdout, carrying the session's ID.
+pub fn emit_session_event(event: &str, session_id: Uuid, fields: Value) {
+    println!("{}", session_event_json(event, session_id, fields));
+}
+
+/// Reports the session's progress every second and any change in the output's health until it stops playing,
+/// or until only `hand_over` of it is left. This is the `--json` counterpart of `show_progress`.
+pub fn report_progress(player: &mut BinauralPlayer, hand_over: StdDuration) -> Result<(), Error> {
+    let session_id = player.session_id();
+    let mut last_reported_second = None;
+
+    while player.is_playing_until(hand_over) {
+        if let Some(output_health) = player.take_output_health() {
+            emit_session_event(
+                "output_health",
+                session_id,
+                json!({ "message": output_health.to_string() }),
+            );
+        }
+
+        let progress = player.progress();
+        let second = progress.elapsed.as_secs();
+
+        if last_reported_second != Some(second) {
+            emit_session_event("progress", session_id, progress.to_json());
+            last_reported_second = Some(second);
+        }
+
+        thread::sleep(EVENT_POLL_INTERVAL);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::modules::progress::SessionProgress;
+
+    #[test]
+    fn event_carries_its_name_and_fields() {
+        let event = event_json("session_ended", json!({ "outcome": "completed" }));
+
+        assert_eq!(
+            event,
+            json!({ "event": "session_ended", "outcome": "completed" })
+        );
+    }
+
+    #[test]
+    fn session_event_carries_the_session_id() {
+        let session_id = Uuid::new_v4();
+        let event = session_event_json(
+            "session_ended",
+            session_id,
+            json!({ "outcome": "cancelled" }),
+        );
+
+        assert_eq!(
+            event,
+            json!({
+                "event": "session_ended",
+                "session_id": session_id.to_string(),
+                "outcome": "cancelled",
+            })
+        );
+    }
+
+    #[test]
+    fn progress_event_carries_the_session_id() {
+        let session_id = Uuid::new_v4();
+        let progress = SessionProgress {
+            elapsed: StdDuration::from_secs(30),
+            length: StdDuration::from_secs(300),
+            round: 1,
+            rounds: Some(1),
+        };
+
+        let event = session_event_json("progress", session_id, progress.to_json());
+
+        assert_eq!(event["session_id"], json!(session_id.to_string()));
+        assert_eq!(
+            event["elapsed_seconds"],
+            progress.to_json()["elapsed_seconds"]
+        );
+    }
+
+    #[test]
+    fn event_without_fields_only_has_its_name() {
+        assert_eq!(
+            event_json("progress", Value::Null),
+            json!({ "event": "progress" })
+        );
+    }
+}
@@ -0,0 +1,153 @@
+//! A module that contains the exit statuses the program finishes with and the errors that lead to them.
+
+use std::fmt;
+use std::process::ExitCode;
+
+use anyhow::Error;
+
+use crate::modules::bb_generator::PlaybackOutcome;
+
+/// The exit statuses the program finishes with so that shell scripts and schedulers can tell what happened.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExitStatus {
+    /// `0`: The session played for its full duration.
+    Completed,
+    /// `1`: A failure that doesn't fit any of the other categories.
+    Failure,
+    /// `2`: The command line arguments or the chosen settings are invalid.
+    InvalidArguments,
+    /// `3`: No usable audio output device could be found.
+    NoDevice,
+    /// `4`: The audio stream failed to start or failed while playing.
+    StreamError,
+    /// `5`: The user cancelled the session before it finished.
+    CancelledByUser,
+}
+
+impl ExitStatus {
+    /// Returns the numeric process exit code for the status.
+    pub fn code(&self) -> u8 {
+        match self {
+            ExitStatus::Completed => 0,
+            ExitStatus::Failure => 1,
+            ExitStatus::InvalidArguments => 2,
+            ExitStatus::NoDevice => 3,
+            ExitStatus::StreamError => 4,
+            ExitStatus::CancelledByUser => 5,
+        }
+    }
+}
+
+/// This implementation lets `main` return the status directly.
+impl From<ExitStatus> for ExitCode {
+    fn from(status: ExitStatus) -> Self {
+        ExitCode::from(status.code())
+    }
+}
+
+/// This implementation converts the way a session ended into its exit status.
+impl From<PlaybackOutcome> for ExitStatus {
+    fn from(outcome: PlaybackOutcome) -> Self {
+        match outcome {
+            PlaybackOutcome::Completed => ExitStatus::Completed,
+            PlaybackOutcome::Cancelled => ExitStatus::CancelledByUser,
+        }
+    }
+}
+
+/// This implementation picks the exit status for an error based on the playback error it carries.
+impl From<&Error> for ExitStatus {
+    fn from(err: &Error) -> Self {
+        match err.downcast_ref::<PlaybackError>() {
+            Some(PlaybackError::InvalidSettings(_)) => ExitStatus::InvalidArguments,
+            Some(PlaybackError::NoDevice(_)) | Some(PlaybackError::DeviceLost(_)) => {
+                ExitStatus::NoDevice
+            }
+            Some(PlaybackError::Stream(_)) => ExitStatus::StreamError,
+            None => ExitStatus::Failure,
+        }
+    }
+}
+
+/// The categories of error that can stop a session from playing.
+#[derive(Debug)]
+pub enum PlaybackError {
+    /// The chosen settings can't be played.
+    InvalidSettings(String),
+    /// No usable audio output device was found.
+    NoDevice(String),
+    /// The audio stream failed to start or failed while playing.
+    Stream(String),
+    /// The output device went away while playing, like headphones that were unplugged or ran out of battery.
+    DeviceLost(String),
+}
+
+/// This formatter returns the message describing the error.
+impl fmt::Display for PlaybackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlaybackError::InvalidSettings(message)
+            | PlaybackError::NoDevice(message)
+            | PlaybackError::Stream(message)
+            | PlaybackError::DeviceLost(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for PlaybackError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    macro_rules! test_exit_status_to_code_cases {
+        ($($name:ident:($a:expr, $expected:expr),)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    assert_eq!(ExitStatus::code($a),$expected)
+                }
+            )*
+        };
+    }
+
+    macro_rules! test_error_to_exit_status_cases {
+        ($($name:ident:($a:expr, $expected:expr),)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    assert_eq!(ExitStatus::from(&$a),$expected)
+                }
+            )*
+        };
+    }
+
+    test_exit_status_to_code_cases! {
+        exit_status_completed_code: (&ExitStatus::Completed, 0),
+        exit_status_failure_code: (&ExitStatus::Failure, 1),
+        exit_status_invalid_arguments_code: (&ExitStatus::InvalidArguments, 2),
+        exit_status_no_device_code: (&ExitStatus::NoDevice, 3),
+        exit_status_stream_error_code: (&ExitStatus::StreamError, 4),
+        exit_status_cancelled_by_user_code: (&ExitStatus::CancelledByUser, 5),
+    }
+
+    test_error_to_exit_status_cases! {
+        invalid_settings_error_exit_status: (Error::from(PlaybackError::InvalidSettings("bad".to_string())), ExitStatus::InvalidArguments),
+        no_device_error_exit_status: (Error::from(PlaybackError::NoDevice("none".to_string())), ExitStatus::NoDevice),
+        stream_error_exit_status: (Error::from(PlaybackError::Stream("broken".to_string())), ExitStatus::StreamError),
+        device_lost_error_exit_status: (Error::from(PlaybackError::DeviceLost("gone".to_string())), ExitStatus::NoDevice),
+        other_error_exit_status: (anyhow::anyhow!("unknown"), ExitStatus::Failure),
+    }
+
+    #[test]
+    fn playback_outcome_converts_to_exit_status() {
+        assert_eq!(
+            ExitStatus::from(PlaybackOutcome::Completed),
+            ExitStatus::Completed
+        );
+        assert_eq!(
+            ExitStatus::from(PlaybackOutcome::Cancelled),
+            ExitStatus::CancelledByUser
+        );
+    }
+}
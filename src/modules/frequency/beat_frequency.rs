@@ -1,9 +1,14 @@
 //! A module that contains code related to the beat functionality.
 
+use std::str::FromStr;
+
 use crate::modules::frequency::frequency_common::ToFrequency;
+use crate::modules::parse::{ParseValueError, parse_hz, to_slug};
 
 /// Represents common brainwave beat frequencies.
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
 pub enum BeatFrequency {
     /// Delta wave range (0.5 - 4 Hz), for deep relaxation, sleep.
     Delta,
@@ -34,6 +39,28 @@ impl ToFrequency for BeatFrequency {
     }
 }
 
+/// This implementation reads a band name like `theta`, or a frequency like `6.3hz` as a custom beat.
+impl FromStr for BeatFrequency {
+    type Err = ParseValueError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let beat = match to_slug(text).as_str() {
+            "delta" => BeatFrequency::Delta,
+            "theta" => BeatFrequency::Theta,
+            "alpha" => BeatFrequency::Alpha,
+            "beta" => BeatFrequency::Beta,
+            "gamma" => BeatFrequency::Gamma,
+            _ => {
+                return parse_hz(text)
+                    .map(BeatFrequency::Custom)
+                    .ok_or_else(|| ParseValueError::new("beat frequency", text));
+            }
+        };
+
+        Ok(beat)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -57,4 +84,27 @@ mod test {
         test_beat_freuency_gamma_enum_to_integer: (&BeatFrequency::Gamma, 40.0),
         test_beat_freuency_custom_enum_to_integer: (&BeatFrequency::Custom(99.9), 99.9),
     }
+
+    macro_rules! test_beat_frequency_from_str_cases {
+        ($($name:ident:($a:expr, $expected:expr),)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    assert_eq!($a.parse::<BeatFrequency>().ok(),$expected)
+                }
+            )*
+        };
+    }
+
+    test_beat_frequency_from_str_cases! {
+        test_beat_frequency_from_str_delta: ("delta", Some(BeatFrequency::Delta)),
+        test_beat_frequency_from_str_theta: ("theta", Some(BeatFrequency::Theta)),
+        test_beat_frequency_from_str_alpha_any_case: ("Alpha", Some(BeatFrequency::Alpha)),
+        test_beat_frequency_from_str_beta: ("beta", Some(BeatFrequency::Beta)),
+        test_beat_frequency_from_str_gamma: ("gamma", Some(BeatFrequency::Gamma)),
+        test_beat_frequency_from_str_custom: ("6.3hz", Some(BeatFrequency::Custom(6.3))),
+        test_beat_frequency_from_str_custom_without_unit: ("7", Some(BeatFrequency::Custom(7.0))),
+        test_beat_frequency_from_str_zero: ("0", None),
+        test_beat_frequency_from_str_unknown: ("epsilon", None),
+    }
 }
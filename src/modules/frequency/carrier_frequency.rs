@@ -1,9 +1,14 @@
 //! A module that contains code related to the carrier functionality.
 
+use std::str::FromStr;
+
 use crate::modules::frequency::frequency_common::ToFrequency;
+use crate::modules::parse::{ParseValueError, parse_hz, to_slug};
 
 /// Represents common brainwave carrier frequencies.
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
 pub enum CarrierFrequency {
     /// Delta wave range (0.5 - 4 Hz), often associated with deep sleep.
     Delta,
@@ -68,6 +73,46 @@ impl ToFrequency for CarrierFrequency {
     }
 }
 
+/// This implementation reads a name like `solfeggio-heart` or `tuning-fork-root`,
+/// or a frequency like `432hz` as a custom carrier.
+impl FromStr for CarrierFrequency {
+    type Err = ParseValueError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let carrier = match to_slug(text).as_str() {
+            "delta" => CarrierFrequency::Delta,
+            "theta" => CarrierFrequency::Theta,
+            "alpha" => CarrierFrequency::Alpha,
+            "beta" => CarrierFrequency::Beta,
+            "gamma" => CarrierFrequency::Gamma,
+
+            "solfeggio-root" => CarrierFrequency::SolfeggioRoot,
+            "solfeggio-sacral" => CarrierFrequency::SolfeggioSacral,
+            "solfeggio-solar-plexus" => CarrierFrequency::SolfeggioSolarPlexus,
+            "solfeggio-heart" => CarrierFrequency::SolfeggioHeart,
+            "solfeggio-throat" => CarrierFrequency::SolfeggioThroat,
+            "solfeggio-third-eye" => CarrierFrequency::SolfeggioThirdEye,
+            "solfeggio-crown" => CarrierFrequency::SolfeggioCrown,
+
+            "tuning-fork-root" => CarrierFrequency::TuningForkRoot,
+            "tuning-fork-sacral" => CarrierFrequency::TuningForkSacral,
+            "tuning-fork-solar-plexus" => CarrierFrequency::TuningForkSolarPlexus,
+            "tuning-fork-heart" => CarrierFrequency::TuningForkHeart,
+            "tuning-fork-throat" => CarrierFrequency::TuningForkThroat,
+            "tuning-fork-third-eye" => CarrierFrequency::TuningForkThirdEye,
+            "tuning-fork-crown" => CarrierFrequency::TuningForkCrown,
+
+            _ => {
+                return parse_hz(text)
+                    .map(CarrierFrequency::Custom)
+                    .ok_or_else(|| ParseValueError::new("carrier frequency", text));
+            }
+        };
+
+        Ok(carrier)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -108,4 +153,26 @@ mod test {
         test_carrier_frequency_custom_enum_to_integer: (&CarrierFrequency::Custom(199.99) , 199.99),
 
     }
+
+    macro_rules! test_carrier_frequency_from_str_cases {
+        ($($name:ident:($a:expr, $expected:expr),)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    assert_eq!($a.parse::<CarrierFrequency>().ok(),$expected)
+                }
+            )*
+        };
+    }
+
+    test_carrier_frequency_from_str_cases! {
+        test_carrier_frequency_from_str_theta: ("theta", Some(CarrierFrequency::Theta)),
+        test_carrier_frequency_from_str_solfeggio_heart: ("solfeggio-heart", Some(CarrierFrequency::SolfeggioHeart)),
+        test_carrier_frequency_from_str_solfeggio_third_eye: ("Solfeggio Third Eye", Some(CarrierFrequency::SolfeggioThirdEye)),
+        test_carrier_frequency_from_str_tuning_fork_root: ("tuning_fork_root", Some(CarrierFrequency::TuningForkRoot)),
+        test_carrier_frequency_from_str_tuning_fork_solar_plexus: ("tuning-fork-solar-plexus", Some(CarrierFrequency::TuningForkSolarPlexus)),
+        test_carrier_frequency_from_str_custom: ("432hz", Some(CarrierFrequency::Custom(432.0))),
+        test_carrier_frequency_from_str_negative: ("-432hz", None),
+        test_carrier_frequency_from_str_unknown: ("solfeggio-knee", None),
+    }
 }
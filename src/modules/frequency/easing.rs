@@ -0,0 +1,87 @@
+//! A module that contains code related to how frequencies move between two values over time.
+
+use std::fmt;
+
+/// The lowest frequency used when interpolating logarithmically, since a logarithmic curve can't start at 0 Hz.
+const LOGARITHMIC_FLOOR_HZ: f64 = 0.1;
+
+/// Represents the shape of a frequency change over time.
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Easing {
+    /// Changes by the same number of Hz every second.
+    Linear,
+    /// Starts and finishes gently, changing fastest in the middle.
+    EaseInOut,
+    /// Changes by the same ratio every second, so the low end moves slowest in Hz.
+    Logarithmic,
+}
+
+impl Easing {
+    /// Returns the frequency between `start_hz` and `end_hz` once `progress` (0.0 to 1.0) of the change has passed.
+    pub fn interpolate(&self, start_hz: f64, end_hz: f64, progress: f64) -> f64 {
+        let progress = progress.clamp(0.0, 1.0);
+
+        match self {
+            Easing::Linear => start_hz + (end_hz - start_hz) * progress,
+            Easing::EaseInOut => {
+                let eased = progress * progress * (3.0 - 2.0 * progress);
+                start_hz + (end_hz - start_hz) * eased
+            }
+            Easing::Logarithmic => {
+                let start_hz = start_hz.max(LOGARITHMIC_FLOOR_HZ);
+                let end_hz = end_hz.max(LOGARITHMIC_FLOOR_HZ);
+                start_hz * (end_hz / start_hz).powf(progress)
+            }
+        }
+    }
+}
+
+/// This formatter returns the name of the curve as it is typed on the command line.
+impl fmt::Display for Easing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Easing::Linear => write!(f, "linear"),
+            Easing::EaseInOut => write!(f, "ease-in-out"),
+            Easing::Logarithmic => write!(f, "logarithmic"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    macro_rules! test_easing_interpolate_cases {
+        ($($name:ident:($easing:expr, $start:expr, $end:expr, $progress:expr, $expected:expr),)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let actual = $easing.interpolate($start, $end, $progress);
+                    assert!((actual - $expected).abs() < 1e-9, "{} != {}", actual, $expected)
+                }
+            )*
+        };
+    }
+
+    test_easing_interpolate_cases! {
+        linear_start: (Easing::Linear, 10.0, 2.0, 0.0, 10.0),
+        linear_middle: (Easing::Linear, 10.0, 2.0, 0.5, 6.0),
+        linear_end: (Easing::Linear, 10.0, 2.0, 1.0, 2.0),
+        linear_clamps_progress: (Easing::Linear, 10.0, 2.0, 1.5, 2.0),
+        ease_in_out_start: (Easing::EaseInOut, 0.0, 40.0, 0.0, 0.0),
+        ease_in_out_quarter: (Easing::EaseInOut, 0.0, 40.0, 0.25, 6.25),
+        ease_in_out_middle: (Easing::EaseInOut, 0.0, 40.0, 0.5, 20.0),
+        ease_in_out_end: (Easing::EaseInOut, 0.0, 40.0, 1.0, 40.0),
+        logarithmic_start: (Easing::Logarithmic, 10.0, 2.5, 0.0, 10.0),
+        logarithmic_middle: (Easing::Logarithmic, 10.0, 2.5, 0.5, 5.0),
+        logarithmic_end: (Easing::Logarithmic, 10.0, 2.5, 1.0, 2.5),
+        logarithmic_from_zero_uses_floor: (Easing::Logarithmic, 0.0, 10.0, 0.5, 1.0),
+    }
+
+    #[test]
+    fn easing_text() {
+        assert_eq!(Easing::Linear.to_string(), "linear");
+        assert_eq!(Easing::EaseInOut.to_string(), "ease-in-out");
+        assert_eq!(Easing::Logarithmic.to_string(), "logarithmic");
+    }
+}
@@ -1,4 +1,5 @@
 //! A module that contains references related to the frequency funcitonality.
 pub mod beat_frequency;
 pub mod carrier_frequency;
+pub mod easing;
 pub mod frequency_common;
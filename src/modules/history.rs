@@ -0,0 +1,398 @@
+//! A module that contains the history of the sessions played and the listening statistics drawn from it.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration as StdDuration;
+
+use anyhow::Error;
+use jiff::Timestamp;
+use jiff::tz::TimeZone;
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use crate::modules::bb_generator::PlaybackOutcome;
+use crate::modules::preset::BinauralPresetGroup;
+use crate::modules::saved_preset::{CONFIG_DIR_NAME, SavedPreset};
+use crate::modules::state_file::{Migration, migrate};
+
+/// The file inside the program's data directory that holds one line of JSON for every session played.
+const HISTORY_FILE: &str = "history.jsonl";
+
+/// Each entry upgrades a line of the history from the schema version before it.
+/// Every line carries its own version, so lines from different versions of the program can sit side by side.
+const HISTORY_MIGRATIONS: &[Migration] = &[add_session_id];
+
+/// Version 2 added the session's ID. The sessions played before can't be told apart any more, they get the nil ID.
+fn add_session_id(value: &mut Value) {
+    value["session_id"] = json!(Uuid::nil().to_string());
+}
+
+/// A session that was played, kept in the history so that listening time can be added up later.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionRecord {
+    /// The ID the session was given when it started, the same one its JSON events carry.
+    pub session_id: Uuid,
+    /// When the session started.
+    pub started_at: Timestamp,
+    /// The name of the preset menu entry that was played, the saved name for a saved preset.
+    pub name: String,
+    /// The frequencies and duration of each round of the session.
+    pub preset_options: BinauralPresetGroup,
+    /// How long the session was meant to play for, `None` when it repeated until stopped.
+    pub requested: Option<StdDuration>,
+    /// How long the session actually played for.
+    pub played: StdDuration,
+    pub outcome: PlaybackOutcome,
+}
+
+impl SessionRecord {
+    /// Returns the record as a JSON object, the same fields as a saved preset plus how the session went.
+    pub fn to_json(&self) -> Value {
+        let mut value = SavedPreset {
+            name: self.name.clone(),
+            preset_options: self.preset_options,
+        }
+        .to_json();
+
+        value["version"] = json!(HISTORY_MIGRATIONS.len() + 1);
+        value["session_id"] = json!(self.session_id.to_string());
+        value["started_at"] = json!(self.started_at.to_string());
+        value["requested_seconds"] = json!(self.requested.map(|requested| requested.as_secs()));
+        value["played_seconds"] = json!(self.played.as_secs());
+        value["outcome"] = json!(self.outcome.name());
+
+        value
+    }
+
+    /// Reads a record written by `to_json`, or returns `None` when the object isn't one.
+    pub fn from_json(value: &Value) -> Option<Self> {
+        let saved = SavedPreset::from_json(value)?;
+        let outcome = match value["outcome"].as_str()? {
+            "completed" => PlaybackOutcome::Completed,
+            "cancelled" => PlaybackOutcome::Cancelled,
+            _ => return None,
+        };
+
+        Some(SessionRecord {
+            session_id: value["session_id"].as_str()?.parse().ok()?,
+            started_at: value["started_at"].as_str()?.parse().ok()?,
+            name: saved.name,
+            preset_options: saved.preset_options,
+            requested: value["requested_seconds"]
+                .as_u64()
+                .map(StdDuration::from_secs),
+            played: StdDuration::from_secs(value["played_seconds"].as_u64()?),
+            outcome,
+        })
+    }
+}
+
+/// Returns where the session history is kept, or `None` when the platform has no data directory.
+pub fn history_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join(CONFIG_DIR_NAME).join(HISTORY_FILE))
+}
+
+/// Adds the record to the end of the history file, creating the file and its directory when needed.
+/// A crash mid-write can at worst leave one unreadable last line. That line is ended before the record
+/// is written, so the record gets a line of its own instead of being joined onto it, and the record is
+/// synced to the disk before returning.
+pub fn append_record(path: &Path, record: &SessionRecord) -> Result<(), Error> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|err| anyhow::anyhow!("{} can't be created: {}", dir.display(), err))?;
+    }
+
+    OpenOptions::new()
+        .create(true)
+        .read(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| {
+            let mut line = String::new();
+            if !ends_with_newline(&mut file)? {
+                line.push('\n');
+            }
+            line.push_str(&format!("{}\n", record.to_json()));
+
+            file.write_all(line.as_bytes())?;
+            file.sync_data()
+        })
+        .map_err(|err| anyhow::anyhow!("{} can't be written: {}", path.display(), err))
+}
+
+/// A helper function that returns whether the file is empty or its last line is complete.
+fn ends_with_newline(file: &mut File) -> std::io::Result<bool> {
+    if file.metadata()?.len() == 0 {
+        return Ok(true);
+    }
+
+    let mut last_byte = [0; 1];
+    file.seek(SeekFrom::End(-1))?;
+    file.read_exact(&mut last_byte)?;
+    Ok(last_byte[0] == b'\n')
+}
+
+/// Reads every record in the history file, oldest first. A missing file is an empty history,
+/// and lines that can't be read are left out so that one damaged line doesn't hide the rest.
+/// A line written by a newer version of the program is an error, the history can't be summed up without it.
+pub fn read_history(path: &Path) -> Result<Vec<SessionRecord>, Error> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(anyhow::anyhow!("{} can't be read: {}", path.display(), err)),
+    };
+
+    let mut records = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let Ok(value) = serde_json::from_str(line) else {
+            continue;
+        };
+        let source = format!("Line {} of {}", index + 1, path.display());
+        let value = migrate(value, HISTORY_MIGRATIONS, &source)?;
+
+        records.extend(SessionRecord::from_json(&value));
+    }
+
+    Ok(records)
+}
+
+/// The number of sessions and the time listened, added up over part of the history.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ListeningTotal {
+    pub sessions: u32,
+    pub listened: StdDuration,
+}
+
+impl ListeningTotal {
+    fn add(&mut self, record: &SessionRecord) {
+        self.sessions += 1;
+        self.listened += record.played;
+    }
+}
+
+/// The listening time in the history, in total, for each preset and for each week.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ListeningStats {
+    pub total: ListeningTotal,
+    /// The presets by name, the one listened to longest first.
+    pub per_preset: Vec<(String, ListeningTotal)>,
+    /// The ISO weeks with any listening, like `2026-W42`, the earliest first.
+    pub per_week: Vec<(String, ListeningTotal)>,
+}
+
+/// Adds up the listening time in the records, placing each session in the week it started in the given time zone.
+pub fn listening_stats(records: &[SessionRecord], time_zone: &TimeZone) -> ListeningStats {
+    let mut stats = ListeningStats::default();
+    let mut per_preset: BTreeMap<&str, ListeningTotal> = BTreeMap::new();
+    let mut per_week: BTreeMap<String, ListeningTotal> = BTreeMap::new();
+
+    for record in records {
+        let week = record
+            .started_at
+            .to_zoned(time_zone.clone())
+            .date()
+            .iso_week_date();
+
+        stats.total.add(record);
+        per_preset.entry(&record.name).or_default().add(record);
+        per_week
+            .entry(format!("{}-W{:02}", week.year(), week.week()))
+            .or_default()
+            .add(record);
+    }
+
+    stats.per_preset = per_preset
+        .into_iter()
+        .map(|(name, total)| (name.to_string(), total))
+        .collect();
+    // Sorting is stable, so presets listened to equally long stay in name order.
+    stats
+        .per_preset
+        .sort_by_key(|(_, total)| std::cmp::Reverse(total.listened));
+    stats.per_week = per_week.into_iter().collect();
+
+    stats
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::modules::frequency::beat_frequency::BeatFrequency;
+    use crate::modules::preset::Preset;
+
+    fn record(name: &str, started_at: &str, played_minutes: u64) -> SessionRecord {
+        SessionRecord {
+            session_id: Uuid::new_v4(),
+            started_at: started_at.parse().unwrap(),
+            name: name.to_string(),
+            preset_options: BinauralPresetGroup::from(Preset::Sleep),
+            requested: Some(StdDuration::from_secs(60 * 60)),
+            played: StdDuration::from_secs(played_minutes * 60),
+            outcome: PlaybackOutcome::Completed,
+        }
+    }
+
+    fn temp_history_path() -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("bb-history-{}", uuid::Uuid::new_v4()))
+            .join(HISTORY_FILE)
+    }
+
+    fn total(sessions: u32, listened_minutes: u64) -> ListeningTotal {
+        ListeningTotal {
+            sessions,
+            listened: StdDuration::from_secs(listened_minutes * 60),
+        }
+    }
+
+    #[test]
+    fn session_record_round_trips_through_json() {
+        let mut cancelled = record("Evening", "2026-10-16T21:30:00Z", 12);
+        cancelled.preset_options.beat = BeatFrequency::Custom(3.5);
+        cancelled.requested = None;
+        cancelled.outcome = PlaybackOutcome::Cancelled;
+
+        assert_eq!(
+            SessionRecord::from_json(&cancelled.to_json()),
+            Some(cancelled)
+        );
+    }
+
+    #[test]
+    fn session_record_from_json_needs_an_outcome() {
+        let mut value = record("Sleep", "2026-10-16T21:30:00Z", 60).to_json();
+        value["outcome"] = json!("paused");
+
+        assert_eq!(SessionRecord::from_json(&value), None);
+    }
+
+    #[test]
+    fn session_record_keeps_its_session_id() {
+        let sleep = record("Sleep", "2026-10-16T21:30:00Z", 60);
+        let value = sleep.to_json();
+
+        assert_eq!(value["session_id"], json!(sleep.session_id.to_string()));
+        assert_eq!(
+            SessionRecord::from_json(&value).map(|record| record.session_id),
+            Some(sleep.session_id)
+        );
+    }
+
+    #[test]
+    fn history_from_before_session_ids_gets_the_nil_id() {
+        let path = temp_history_path();
+        let mut older = record("Sleep", "2026-10-16T21:30:00Z", 60).to_json();
+        older["version"] = json!(1);
+        older.as_object_mut().unwrap().remove("session_id");
+
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, format!("{}\n", older)).unwrap();
+        let history = read_history(&path).unwrap();
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].session_id, Uuid::nil());
+    }
+
+    #[test]
+    fn missing_history_is_empty() {
+        assert_eq!(read_history(&temp_history_path()).unwrap(), Vec::new());
+    }
+
+    #[test]
+    fn appended_records_are_read_back_in_order_skipping_damaged_lines() {
+        let path = temp_history_path();
+        let first = record("Sleep", "2026-10-16T21:30:00Z", 60);
+        let second = record("Focus", "2026-10-17T09:00:00Z", 25);
+
+        append_record(&path, &first).unwrap();
+        fs::write(
+            &path,
+            fs::read_to_string(&path).unwrap() + "{\"name\": \"half writ\n",
+        )
+        .unwrap();
+        append_record(&path, &second).unwrap();
+        let history = read_history(&path).unwrap();
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        assert_eq!(history, vec![first, second]);
+    }
+
+    #[test]
+    fn record_appended_after_a_torn_line_gets_a_line_of_its_own() {
+        let path = temp_history_path();
+        let first = record("Sleep", "2026-10-16T21:30:00Z", 60);
+        let second = record("Focus", "2026-10-17T09:00:00Z", 25);
+
+        append_record(&path, &first).unwrap();
+        // A crash cut the next record off part way through its line.
+        let torn = record("Calm", "2026-10-16T23:00:00Z", 5)
+            .to_json()
+            .to_string();
+        fs::write(
+            &path,
+            fs::read_to_string(&path).unwrap() + &torn[..torn.len() / 2],
+        )
+        .unwrap();
+        append_record(&path, &second).unwrap();
+        let history = read_history(&path).unwrap();
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        assert_eq!(history, vec![first, second]);
+    }
+
+    #[test]
+    fn history_from_a_newer_version_is_refused() {
+        let path = temp_history_path();
+        let mut newer = record("Sleep", "2026-10-16T21:30:00Z", 60).to_json();
+        newer["version"] = json!(99);
+
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, format!("{}\n", newer)).unwrap();
+        let result = read_history(&path);
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn stats_add_up_the_listening_per_preset_and_week() {
+        let records = vec![
+            record("Focus", "2026-10-12T09:00:00Z", 25),
+            record("Sleep", "2026-10-12T22:00:00Z", 60),
+            record("Focus", "2026-10-18T09:00:00Z", 50),
+            record("Focus", "2026-10-19T09:00:00Z", 25),
+        ];
+
+        let stats = listening_stats(&records, &TimeZone::UTC);
+
+        assert_eq!(stats.total, total(4, 160));
+        assert_eq!(
+            stats.per_preset,
+            vec![
+                ("Focus".to_string(), total(3, 100)),
+                ("Sleep".to_string(), total(1, 60)),
+            ]
+        );
+        assert_eq!(
+            stats.per_week,
+            vec![
+                ("2026-W42".to_string(), total(3, 135)),
+                ("2026-W43".to_string(), total(1, 25)),
+            ]
+        );
+    }
+
+    #[test]
+    fn stats_place_sessions_in_the_local_week() {
+        // Late on Sunday evening in New York is already Monday in UTC.
+        let records = vec![record("Sleep", "2026-10-19T02:00:00Z", 60)];
+        let new_york = TimeZone::posix("EST5EDT,M3.2.0,M11.1.0").unwrap();
+
+        let stats = listening_stats(&records, &new_york);
+
+        assert_eq!(stats.per_week, vec![("2026-W42".to_string(), total(1, 60))]);
+    }
+}
@@ -0,0 +1,191 @@
+//! A module that contains the preset catalog shown in the long help and the manual page, both built from
+//! the command line definitions and the preset metadata so that they always match the program.
+
+use std::io::{self, Write};
+
+use clap::CommandFactory;
+use clap_mangen::Man;
+use clap_mangen::roff::{Roff, bold, roman};
+
+use crate::modules::cli::Cli;
+use crate::modules::duration::duration_common::ToMinutes;
+use crate::modules::frequency::frequency_common::ToFrequency;
+use crate::modules::preset::{BinauralPresetGroup, Preset, preset_category_list, preset_list};
+
+/// The width the descriptions in the preset catalog are wrapped to, so they read well in an 80 column terminal.
+const CATALOG_WIDTH: usize = 76;
+
+/// Returns the line of the preset catalog for the preset, its name as typed on the command line followed by
+/// its frequencies, default duration and tags.
+fn preset_line(preset: &Preset) -> String {
+    let preset_options = BinauralPresetGroup::from(*preset);
+
+    format!(
+        "{:<28}{:>9.2} Hz{:>8.2} Hz{:>6} min  {}",
+        preset.slug(),
+        preset_options.carrier.to_hz(),
+        preset_options.beat.to_hz(),
+        preset_options.duration.to_minutes(),
+        preset.tags().join(", ")
+    )
+}
+
+/// Returns the presets listed under each category with their frequencies, and their descriptions when
+/// `with_descriptions` is set. This is the text shown after `--help` and `help list-presets`.
+pub fn preset_catalog(with_descriptions: bool) -> String {
+    let mut catalog = format!(
+        "Presets:\n  {:<28}{:>12}{:>11}{:>10}  Tags\n",
+        "Name", "Carrier", "Beat", "Duration"
+    );
+
+    for category in preset_category_list() {
+        catalog.push_str(&format!("\n  {}\n", category));
+
+        for preset in preset_list()
+            .iter()
+            .filter(|preset| preset.category() == category)
+        {
+            catalog.push_str(&format!("  {}\n", preset_line(preset)));
+
+            if with_descriptions {
+                for line in wrap(preset.description(), CATALOG_WIDTH - 6) {
+                    catalog.push_str(&format!("      {}\n", line));
+                }
+            }
+        }
+    }
+
+    catalog
+}
+
+/// A helper function that splits the text into lines no longer than `width`, breaking between words.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+
+    for word in text.split_whitespace() {
+        match lines.last_mut() {
+            Some(line) if line.len() + 1 + word.len() <= width => {
+                line.push(' ');
+                line.push_str(word);
+            }
+            _ => lines.push(word.to_string()),
+        }
+    }
+
+    lines
+}
+
+/// Writes the manual page in roff, the usual sections built from the command line definitions followed by
+/// a PRESETS section describing every preset.
+pub fn render_man_page(w: &mut dyn Write) -> io::Result<()> {
+    let man = Man::new(Cli::command());
+
+    man.render_title(w)?;
+    man.render_name_section(w)?;
+    man.render_synopsis_section(w)?;
+    man.render_description_section(w)?;
+    man.render_options_section(w)?;
+    man.render_subcommands_section(w)?;
+    presets_section().to_writer(w)?;
+    man.render_version_section(w)?;
+    man.render_authors_section(w)
+}
+
+/// Returns the PRESETS section of the manual page, one subsection per category.
+fn presets_section() -> Roff {
+    let mut roff = Roff::default();
+    roff.control("SH", ["PRESETS"]);
+    roff.text([roman(
+        "Each preset plays the carrier frequency in one ear and the carrier plus the beat in the other. \
+         Pick one on the command line by the name shown in bold.",
+    )]);
+
+    for category in preset_category_list() {
+        roff.control("SS", [category.to_string().as_str()]);
+
+        for preset in preset_list()
+            .iter()
+            .filter(|preset| preset.category() == category)
+        {
+            let preset_options = BinauralPresetGroup::from(*preset);
+
+            roff.control("TP", []);
+            roff.text([bold(preset.slug()), roman(format!(" ({})", preset))]);
+            roff.text([roman(format!(
+                "Carrier {:.2} Hz, beat {:.2} Hz, {} minutes. {}",
+                preset_options.carrier.to_hz(),
+                preset_options.beat.to_hz(),
+                preset_options.duration.to_minutes(),
+                preset.description()
+            ))]);
+        }
+    }
+
+    roff
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    macro_rules! test_wrap_cases {
+        ($($name:ident:($text:expr, $width:expr, $expected:expr),)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let expected: Vec<&str> = $expected;
+                    assert_eq!(wrap($text, $width), expected)
+                }
+            )*
+        };
+    }
+
+    test_wrap_cases! {
+        wrap_short_text: ("Deep rest.", 20, vec!["Deep rest."]),
+        wrap_between_words: ("Uses the 396 Hz tone for grounding", 16, vec!["Uses the 396 Hz", "tone for", "grounding"]),
+        wrap_exact_width: ("one two", 7, vec!["one two"]),
+        wrap_long_word: ("incomprehensibilities", 5, vec!["incomprehensibilities"]),
+        wrap_empty: ("", 10, vec![]),
+    }
+
+    #[test]
+    fn catalog_lists_every_preset_under_its_category() {
+        let catalog = preset_catalog(false);
+
+        for preset in preset_list() {
+            assert!(catalog.contains(&preset_line(&preset)), "{}", preset);
+        }
+        assert!(catalog.find("\n  Solfeggio\n") < catalog.find("solfeggio-root"));
+    }
+
+    #[test]
+    fn catalog_with_descriptions_fits_the_terminal() {
+        let catalog = preset_catalog(true);
+
+        assert!(catalog.contains("Uses the 639 Hz Solfeggio tone with an Alpha beat"));
+        assert!(
+            catalog
+                .lines()
+                .filter(|line| line.starts_with("      "))
+                .all(|line| line.len() <= CATALOG_WIDTH)
+        );
+    }
+
+    #[test]
+    fn man_page_describes_every_preset() {
+        let mut page = Vec::new();
+        render_man_page(&mut page).unwrap();
+        let page = String::from_utf8(page).unwrap();
+
+        assert!(page.contains(".SH PRESETS"));
+        assert!(page.contains(".SS \"Tuning Fork\""));
+        // Roff escapes the hyphens in the names.
+        for preset in preset_list() {
+            assert!(
+                page.contains(&format!("\\fB{}\\fR", preset.slug().replace('-', "\\-"))),
+                "{}",
+                preset
+            );
+        }
+    }
+}
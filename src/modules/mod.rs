@@ -1,6 +1,25 @@
 //! A module that contains references related to all custom modules used.
 
 pub mod bb_generator;
+pub mod calibration;
+pub mod cancel_token;
+pub mod cli;
+#[cfg(feature = "tui")]
+pub mod dashboard;
 pub mod duration;
+pub mod events;
+pub mod exit_status;
 pub mod frequency;
+pub mod history;
+pub mod manual;
+pub mod output;
+pub mod parse;
 pub mod preset;
+pub mod progress;
+pub mod queue;
+pub mod saved_preset;
+pub mod self_check;
+pub mod settings_warning;
+pub mod start_time;
+pub mod state_file;
+pub mod synth;
@@ -0,0 +1,268 @@
+//! A module that contains code related to playing audio through a cpal output device.
+
+use std::sync::mpsc::Sender;
+
+use anyhow::Error;
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use serde_json::{Value, json};
+
+use crate::modules::exit_status::PlaybackError;
+use crate::modules::output::output_common::{AudioSink, RenderCallback};
+use crate::modules::output::sample_conversion::{Dither, convert_samples};
+
+/// One range of stream configurations an output device supports.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputConfigRange {
+    pub channels: u16,
+    pub min_sample_rate: u32,
+    pub max_sample_rate: u32,
+    pub sample_format: String,
+}
+
+/// An output device together with the host it belongs to and the configurations it supports.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputDeviceInfo {
+    pub host: String,
+    pub name: String,
+    /// Whether this is the default output device of the default host, the one used without `--device`.
+    pub is_default: bool,
+    /// Empty when the device couldn't report its configurations.
+    pub configs: Vec<OutputConfigRange>,
+}
+
+impl OutputDeviceInfo {
+    /// Returns the device as a JSON object so that scripts can read the device list.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "host": self.host,
+            "name": self.name,
+            "default": self.is_default,
+            "configs": self
+                .configs
+                .iter()
+                .map(|config| {
+                    json!({
+                        "channels": config.channels,
+                        "min_sample_rate": config.min_sample_rate,
+                        "max_sample_rate": config.max_sample_rate,
+                        "sample_format": config.sample_format,
+                    })
+                })
+                .collect::<Vec<Value>>(),
+        })
+    }
+}
+
+/// Returns every output device of every host cpal can open on this machine.
+/// Hosts that aren't available, like a JACK server that isn't running, are left out.
+pub fn list_output_devices() -> Vec<OutputDeviceInfo> {
+    let default_host_id = cpal::default_host().id();
+    let default_name = default_output_device_name();
+
+    cpal::available_hosts()
+        .into_iter()
+        .filter_map(|host_id| cpal::host_from_id(host_id).ok())
+        .flat_map(|host| {
+            let host_name = host.id().name().to_string();
+            let is_default_host = host.id() == default_host_id;
+            let default_name = default_name.as_deref();
+
+            host.output_devices()
+                .into_iter()
+                .flatten()
+                .filter_map(move |device| {
+                    let name = device.name().ok()?;
+                    let configs = device
+                        .supported_output_configs()
+                        .map(|configs| {
+                            configs
+                                .map(|config| OutputConfigRange {
+                                    channels: config.channels(),
+                                    min_sample_rate: config.min_sample_rate().0,
+                                    max_sample_rate: config.max_sample_rate().0,
+                                    sample_format: config.sample_format().to_string(),
+                                })
+                                .collect()
+                        })
+                        .unwrap_or_default();
+
+                    Some(OutputDeviceInfo {
+                        host: host_name.clone(),
+                        is_default: is_default_host && default_name == Some(name.as_str()),
+                        name,
+                        configs,
+                    })
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Returns the names of the output devices of the default host, in the order the host lists them.
+pub fn output_device_names() -> Result<Vec<String>, Error> {
+    let devices = cpal::default_host().output_devices().map_err(|err| {
+        PlaybackError::NoDevice(format!("The output devices can't be listed: {}", err))
+    })?;
+
+    Ok(devices.filter_map(|device| device.name().ok()).collect())
+}
+
+/// Returns the name of the default output device of the default host, if there is one.
+pub fn default_output_device_name() -> Option<String> {
+    cpal::default_host()
+        .default_output_device()
+        .and_then(|device| device.name().ok())
+}
+
+/// An audio sink backed by a cpal output device.
+pub struct CpalSink {
+    device: cpal::Device,
+    config: cpal::SupportedStreamConfig,
+    stream: Option<cpal::Stream>,
+}
+
+impl CpalSink {
+    /// Opens an output device of the default host using its default configuration.
+    /// Without a name the default output device is used, otherwise the device with that exact name.
+    pub fn open(device_name: Option<&str>) -> Result<Self, Error> {
+        let host = cpal::default_host();
+
+        let device = match device_name {
+            None => host.default_output_device().ok_or_else(|| {
+                PlaybackError::NoDevice("No output device available.".to_string())
+            })?,
+            Some(name) => host
+                .output_devices()
+                .map_err(|err| {
+                    PlaybackError::NoDevice(format!("The output devices can't be listed: {}", err))
+                })?
+                .find(|device| device.name().is_ok_and(|device_name| device_name == name))
+                .ok_or_else(|| {
+                    PlaybackError::NoDevice(format!(
+                        "No output device named \"{}\", run list-devices to see the available devices.",
+                        name
+                    ))
+                })?,
+        };
+
+        let config = device.default_output_config().map_err(|err| {
+            PlaybackError::NoDevice(format!("The output device can't be used: {}", err))
+        })?;
+
+        Ok(CpalSink {
+            device,
+            config,
+            stream: None,
+        })
+    }
+
+    /// Builds the output stream for the device's sample type.
+    /// The samples are rendered into a scratch buffer and converted to the device's type,
+    /// with dither when `dither` is set since 16 bit samples lose precision on the way.
+    fn build_stream<T>(
+        &self,
+        mut render: RenderCallback,
+        errors: Sender<Error>,
+        dither: bool,
+    ) -> Result<cpal::Stream, Error>
+    where
+        T: cpal::SizedSample + cpal::FromSample<f32>,
+    {
+        let mut rendered: Vec<f32> = Vec::new();
+        let mut dither = dither.then(Dither::new);
+
+        let stream = self
+            .device
+            .build_output_stream(
+                &self.config.clone().into(), // Clone config for the stream builder
+                move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+                    // The buffer only grows when the device asks for more samples than before.
+                    rendered.resize(data.len(), 0.0);
+                    render(&mut rendered);
+                    convert_samples(data, &rendered, dither.as_mut());
+                },
+                move |err| {
+                    let error = match err {
+                        cpal::StreamError::DeviceNotAvailable => PlaybackError::DeviceLost(
+                            "The output device is no longer available.".to_string(),
+                        ),
+                        err => {
+                            PlaybackError::Stream(format!("An error occurred on stream: {}", err))
+                        }
+                    };
+                    let _ = errors.send(error.into());
+                },
+                None,
+            )
+            .map_err(|err| PlaybackError::Stream(format!("The stream can't be built: {}", err)))?;
+
+        Ok(stream)
+    }
+}
+
+/// This implementation plays the rendered samples through the cpal stream.
+impl AudioSink for CpalSink {
+    fn sample_rate(&self) -> u32 {
+        self.config.sample_rate().0
+    }
+
+    fn channels(&self) -> usize {
+        self.config.channels() as usize
+    }
+
+    fn start(&mut self, render: RenderCallback, errors: Sender<Error>) -> Result<(), Error> {
+        let stream = match self.config.sample_format() {
+            cpal::SampleFormat::F32 => self.build_stream::<f32>(render, errors, false),
+            cpal::SampleFormat::I16 => self.build_stream::<i16>(render, errors, true),
+            cpal::SampleFormat::U16 => self.build_stream::<u16>(render, errors, true),
+            cpal::SampleFormat::I32 => self.build_stream::<i32>(render, errors, false),
+            sample_format => Err(PlaybackError::Stream(format!(
+                "The output device plays {} samples, which aren't supported.",
+                sample_format
+            ))
+            .into()),
+        }?;
+
+        stream
+            .play()
+            .map_err(|err| PlaybackError::Stream(format!("The stream can't be played: {}", err)))?;
+        self.stream = Some(stream);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn output_device_info_to_json() {
+        let device = OutputDeviceInfo {
+            host: "ALSA".to_string(),
+            name: "USB DAC".to_string(),
+            is_default: false,
+            configs: vec![OutputConfigRange {
+                channels: 2,
+                min_sample_rate: 44_100,
+                max_sample_rate: 96_000,
+                sample_format: "f32".to_string(),
+            }],
+        };
+
+        assert_eq!(
+            device.to_json(),
+            json!({
+                "host": "ALSA",
+                "name": "USB DAC",
+                "default": false,
+                "configs": [{
+                    "channels": 2,
+                    "min_sample_rate": 44_100,
+                    "max_sample_rate": 96_000,
+                    "sample_format": "f32",
+                }],
+            })
+        );
+    }
+}
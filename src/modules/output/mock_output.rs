@@ -0,0 +1,97 @@
+//! A module that contains a test-only audio sink that captures everything played through it.
+
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration as StdDuration, Instant};
+
+use anyhow::Error;
+
+use crate::modules::output::output_common::{AudioSink, RenderCallback};
+
+/// How long a capture waits for the synthesis thread before giving up.
+const CAPTURE_TIMEOUT: StdDuration = StdDuration::from_secs(5);
+
+/// An audio sink that hands its buffers back to the test instead of a sound card.
+pub struct MockSink {
+    sample_rate: u32,
+    channels: usize,
+    render: Option<RenderCallback>,
+    errors: DeviceErrors,
+}
+
+/// Reports errors as if the mock device ran into them, it keeps working after the sink is handed over.
+#[derive(Clone, Default)]
+pub struct DeviceErrors(Arc<Mutex<Option<Sender<Error>>>>);
+
+impl DeviceErrors {
+    /// Sends the error to the playback the sink was started for.
+    pub fn send(&self, err: Error) {
+        let sender = self.0.lock().unwrap();
+        let _ = sender
+            .as_ref()
+            .expect("The sink has to be started before it can fail.")
+            .send(err);
+    }
+}
+
+impl MockSink {
+    pub fn new(sample_rate: u32, channels: usize) -> Self {
+        MockSink {
+            sample_rate,
+            channels,
+            render: None,
+            errors: DeviceErrors::default(),
+        }
+    }
+
+    /// Returns a handle that reports errors from this sink, even once it belongs to a player.
+    pub fn device_errors(&self) -> DeviceErrors {
+        self.errors.clone()
+    }
+
+    /// Pulls exactly `frames` frames of interleaved audio.
+    /// Underruns are retried rather than captured, so the result doesn't depend on thread timing.
+    pub fn capture(&mut self, frames: usize) -> Vec<f32> {
+        let render = self
+            .render
+            .as_mut()
+            .expect("The sink has to be started before capturing.");
+
+        let mut captured = vec![0.0; frames * self.channels];
+        let mut filled = 0;
+        let mut last_progress = Instant::now();
+
+        while filled < captured.len() {
+            let written = render(&mut captured[filled..]);
+            filled += written;
+
+            if written > 0 {
+                last_progress = Instant::now();
+            } else if last_progress.elapsed() > CAPTURE_TIMEOUT {
+                panic!("Timed out waiting for audio after {} samples.", filled);
+            } else {
+                thread::sleep(StdDuration::from_millis(1));
+            }
+        }
+
+        captured
+    }
+}
+
+/// This implementation keeps the render callback so that the test can drive it.
+impl AudioSink for MockSink {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn channels(&self) -> usize {
+        self.channels
+    }
+
+    fn start(&mut self, render: RenderCallback, errors: Sender<Error>) -> Result<(), Error> {
+        self.render = Some(render);
+        *self.errors.0.lock().unwrap() = Some(errors);
+        Ok(())
+    }
+}
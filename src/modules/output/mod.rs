@@ -0,0 +1,8 @@
+//! A module that contains references related to the audio output funcitonality.
+pub mod cpal_output;
+#[cfg(test)]
+pub mod mock_output;
+pub mod output_common;
+pub mod output_watchdog;
+pub mod pipe_output;
+pub mod sample_conversion;
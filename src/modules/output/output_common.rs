@@ -0,0 +1,124 @@
+//! A module that contains common code related to the audio output functionality.
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::Sender;
+
+use anyhow::Error;
+
+use crate::modules::exit_status::PlaybackError;
+use crate::modules::output::cpal_output::CpalSink;
+use crate::modules::output::pipe_output::{PcmFormat, PipeSink};
+
+/// The callback a sink calls whenever it needs more interleaved samples.
+/// It returns how many of the samples were real audio rather than silence padded in on an underrun.
+pub type RenderCallback = Box<dyn FnMut(&mut [f32]) -> usize + Send>;
+
+/// A trait for anything that generated audio can be played through.
+pub trait AudioSink {
+    /// The number of frames per second the sink plays.
+    fn sample_rate(&self) -> u32;
+
+    /// The number of interleaved channels in each frame.
+    fn channels(&self) -> usize;
+
+    /// Starts pulling audio from the render callback until the sink is dropped.
+    /// Any error the sink runs into while playing is sent through `errors`.
+    fn start(&mut self, render: RenderCallback, errors: Sender<Error>) -> Result<(), Error>;
+}
+
+/// Where the audio of a session goes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AudioOutput {
+    /// An output device of the default host, the default device when no name is given.
+    Device(Option<String>),
+    /// Raw stereo PCM written to stdout for another program to take.
+    Pipe { sample_rate: u32, format: PcmFormat },
+}
+
+impl AudioOutput {
+    /// Opens the sink the audio is played through or written to.
+    pub fn open(&self) -> Result<Box<dyn AudioSink>, Error> {
+        match self {
+            AudioOutput::Device(device_name) => {
+                Ok(Box::new(CpalSink::open(device_name.as_deref())?))
+            }
+            AudioOutput::Pipe {
+                sample_rate,
+                format,
+            } => Ok(Box::new(PipeSink::stdout(*sample_rate, *format))),
+        }
+    }
+}
+
+/// Wraps a render callback so that a panic inside it can't take down the audio thread.
+/// After a panic the error is sent once through `errors` and the callback only outputs silence from then on.
+pub fn panic_safe(mut render: RenderCallback, errors: Sender<Error>) -> RenderCallback {
+    let mut failed = false;
+
+    Box::new(move |data: &mut [f32]| {
+        if !failed {
+            match panic::catch_unwind(AssertUnwindSafe(|| render(data))) {
+                Ok(written) => return written,
+                Err(_) => {
+                    failed = true;
+                    let _ = errors.send(
+                        PlaybackError::Stream(
+                            "The audio callback failed unexpectedly, playback was silenced."
+                                .to_string(),
+                        )
+                        .into(),
+                    );
+                }
+            }
+        }
+
+        data.fill(0.0);
+        data.len()
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn panic_safe_passes_through_a_working_callback() {
+        let (sender, receiver) = mpsc::channel();
+        let mut render = panic_safe(
+            Box::new(|data: &mut [f32]| {
+                data.fill(0.25);
+                data.len()
+            }),
+            sender,
+        );
+
+        let mut buffer = [0.0; 8];
+
+        assert_eq!(render(&mut buffer), 8);
+        assert!(buffer.iter().all(|sample| *sample == 0.25));
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn panic_safe_silences_and_reports_a_panicking_callback_once() {
+        let (sender, receiver) = mpsc::channel();
+        let mut render = panic_safe(
+            Box::new(|data: &mut [f32]| {
+                data.fill(0.25);
+                panic!("synthetic failure");
+            }),
+            sender,
+        );
+
+        let mut buffer = [1.0; 8];
+        render(&mut buffer);
+        let mut second_buffer = [1.0; 8];
+        render(&mut second_buffer);
+
+        assert!(buffer.iter().all(|sample| *sample == 0.0));
+        assert!(second_buffer.iter().all(|sample| *sample == 0.0));
+        assert!(receiver.try_recv().is_ok());
+        assert!(receiver.try_recv().is_err());
+    }
+}
@@ -0,0 +1,295 @@
+//! A module that contains code that notices when the audio output stops playing what it is given,
+//! or when what it is given is silent although the tones should be heard.
+
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::time::{Duration as StdDuration, Instant};
+
+/// How much wall time each check of the output covers.
+const WATCHDOG_WINDOW: StdDuration = StdDuration::from_secs(5);
+
+/// The share of the expected frames that have to be played in a window for the output to count as working.
+/// Devices pull audio in bursts, so this leaves room for a late buffer or two.
+const MIN_PLAYED_RATIO: f64 = 0.5;
+
+/// The loudest sample a window can peak at and still count as silence, -80 dB, well below the tones at 1% volume.
+const SILENCE_PEAK: f32 = 1e-4;
+
+/// A count of the frames of real audio, not underrun padding, the output has played.
+/// It is shared between the audio callback, which adds to it, and the watchdog, which reads it.
+#[derive(Debug, Clone, Default)]
+pub struct FrameCounter(Arc<AtomicU64>);
+
+impl FrameCounter {
+    /// Adds played frames to the count.
+    pub fn add(&self, frames: u64) {
+        self.0.fetch_add(frames, Ordering::Relaxed);
+    }
+
+    /// Returns how many frames have been played so far.
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// The loudest sample the output has taken since the watchdog last looked.
+/// It is shared between the audio callback, which records the samples it hands over, and the watchdog, which reads it.
+#[derive(Debug, Clone, Default)]
+pub struct PeakMeter(Arc<AtomicU32>);
+
+impl PeakMeter {
+    /// Records the loudest of the samples, if it is louder than any recorded since the last `take`.
+    pub fn record(&self, samples: &[f32]) {
+        let peak = samples
+            .iter()
+            .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+        // The bits of positive floats sort in the same order as the floats themselves.
+        self.0.fetch_max(peak.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Returns the loudest sample recorded since the last call and starts over.
+    pub fn take(&self) -> f32 {
+        f32::from_bits(self.0.swap(0, Ordering::Relaxed))
+    }
+}
+
+/// The changes in the output's health that the user should hear about.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputHealth {
+    /// The output stopped taking audio, it might be disconnected or stuck.
+    Stalled,
+    /// The output takes audio, but only silence has reached it while the tones should be heard.
+    Silent,
+    /// The output is playing audio again after a stall.
+    Recovered,
+    /// The output device was lost and the session moved to the default output device.
+    Reconnected,
+}
+
+/// This formatter returns the message shown to the user when the output's health changes.
+impl fmt::Display for OutputHealth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputHealth::Stalled => write!(
+                f,
+                "Warning: The audio output doesn't seem to be playing. Check that the device is still connected and working."
+            ),
+            OutputHealth::Silent => write!(
+                f,
+                "Warning: Only silence is reaching the audio output although the tones should be playing."
+            ),
+            OutputHealth::Recovered => write!(f, "The audio output is playing again."),
+            OutputHealth::Reconnected => write!(
+                f,
+                "The output device was lost, playback carried on through the default output device."
+            ),
+        }
+    }
+}
+
+/// Returns whether enough frames were played over `elapsed` for a device running at `sample_rate`.
+fn played_enough(played_frames: u64, elapsed: StdDuration, sample_rate: u32) -> bool {
+    let expected_frames = elapsed.as_secs_f64() * sample_rate as f64;
+    played_frames as f64 >= expected_frames * MIN_PLAYED_RATIO
+}
+
+/// Compares the frames the output has played against the wall clock, and the loudest of them against silence,
+/// one window at a time.
+pub struct OutputWatchdog {
+    frames_played: FrameCounter,
+    peak: PeakMeter,
+    sample_rate: u32,
+    window_start: Instant,
+    window_start_frames: u64,
+    /// Whether the tones should have been heard at every check of the current window.
+    window_audible: bool,
+    /// The problem last reported, `None` while the output is working.
+    problem: Option<OutputHealth>,
+}
+
+impl OutputWatchdog {
+    pub fn new(frames_played: FrameCounter, peak: PeakMeter, sample_rate: u32) -> Self {
+        peak.take();
+
+        OutputWatchdog {
+            window_start_frames: frames_played.get(),
+            frames_played,
+            peak,
+            sample_rate,
+            window_start: Instant::now(),
+            window_audible: true,
+            problem: None,
+        }
+    }
+
+    /// Checks the output once the current window has passed, `audible` tells whether the tones should be heard
+    /// right now. Silence only counts as a problem in a window where they should have been heard throughout,
+    /// so fades and a volume of 0 are left alone.
+    /// Returns the health of the output only when it changed, so each problem is reported once.
+    pub fn check(&mut self, audible: bool) -> Option<OutputHealth> {
+        self.check_at(Instant::now(), audible)
+    }
+
+    fn check_at(&mut self, now: Instant, audible: bool) -> Option<OutputHealth> {
+        self.window_audible &= audible;
+
+        let elapsed = now.duration_since(self.window_start);
+        if elapsed < WATCHDOG_WINDOW {
+            return None;
+        }
+
+        let frames = self.frames_played.get();
+        let working = played_enough(frames - self.window_start_frames, elapsed, self.sample_rate);
+        let silent = self.peak.take() < SILENCE_PEAK;
+
+        let problem = if !working {
+            Some(OutputHealth::Stalled)
+        } else if !self.window_audible {
+            // Nothing should have been heard, so a silence reported before can't have ended yet.
+            self.problem
+                .filter(|problem| *problem == OutputHealth::Silent)
+        } else if silent {
+            Some(OutputHealth::Silent)
+        } else {
+            None
+        };
+
+        self.window_start = now;
+        self.window_start_frames = frames;
+        self.window_audible = true;
+
+        if problem == self.problem {
+            return None;
+        }
+        self.problem = problem;
+        Some(problem.unwrap_or(OutputHealth::Recovered))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    macro_rules! test_played_enough_cases {
+        ($($name:ident:($played:expr, $seconds:expr, $expected:expr),)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    assert_eq!(played_enough($played, StdDuration::from_secs($seconds), 48_000),$expected)
+                }
+            )*
+        };
+    }
+
+    test_played_enough_cases! {
+        played_enough_all_frames: (240_000, 5, true),
+        played_enough_late_buffers: (150_000, 5, true),
+        played_enough_half_the_frames: (120_000, 5, true),
+        played_enough_too_few_frames: (100_000, 5, false),
+        played_enough_no_frames: (0, 5, false),
+    }
+
+    /// Plays one window of frames at the given level, as the audio callback would.
+    fn play_window(counter: &FrameCounter, peak: &PeakMeter, level: f32) {
+        counter.add(240_000);
+        peak.record(&[level, -level]);
+    }
+
+    #[test]
+    fn watchdog_waits_for_a_full_window() {
+        let counter = FrameCounter::default();
+        let mut watchdog = OutputWatchdog::new(counter, PeakMeter::default(), 48_000);
+        let start = watchdog.window_start;
+
+        assert_eq!(
+            watchdog.check_at(start + StdDuration::from_secs(1), true),
+            None
+        );
+    }
+
+    #[test]
+    fn watchdog_reports_a_stall_once_and_the_recovery() {
+        let counter = FrameCounter::default();
+        let peak = PeakMeter::default();
+        let mut watchdog = OutputWatchdog::new(counter.clone(), peak.clone(), 48_000);
+        let start = watchdog.window_start;
+
+        play_window(&counter, &peak, 0.5);
+        assert_eq!(
+            watchdog.check_at(start + StdDuration::from_secs(5), true),
+            None
+        );
+
+        assert_eq!(
+            watchdog.check_at(start + StdDuration::from_secs(10), true),
+            Some(OutputHealth::Stalled)
+        );
+        assert_eq!(
+            watchdog.check_at(start + StdDuration::from_secs(15), true),
+            None
+        );
+
+        play_window(&counter, &peak, 0.5);
+        assert_eq!(
+            watchdog.check_at(start + StdDuration::from_secs(20), true),
+            Some(OutputHealth::Recovered)
+        );
+    }
+
+    #[test]
+    fn watchdog_reports_silence_while_the_tones_should_be_heard() {
+        let counter = FrameCounter::default();
+        let peak = PeakMeter::default();
+        let mut watchdog = OutputWatchdog::new(counter.clone(), peak.clone(), 48_000);
+        let start = watchdog.window_start;
+
+        play_window(&counter, &peak, 0.0);
+        assert_eq!(
+            watchdog.check_at(start + StdDuration::from_secs(5), true),
+            Some(OutputHealth::Silent)
+        );
+
+        play_window(&counter, &peak, 0.0);
+        assert_eq!(
+            watchdog.check_at(start + StdDuration::from_secs(10), true),
+            None
+        );
+
+        play_window(&counter, &peak, 0.2);
+        assert_eq!(
+            watchdog.check_at(start + StdDuration::from_secs(15), true),
+            Some(OutputHealth::Recovered)
+        );
+    }
+
+    #[test]
+    fn watchdog_leaves_silence_alone_while_fading() {
+        let counter = FrameCounter::default();
+        let peak = PeakMeter::default();
+        let mut watchdog = OutputWatchdog::new(counter.clone(), peak.clone(), 48_000);
+        let start = watchdog.window_start;
+
+        // The fade ended part way through the window, so it doesn't count as silent.
+        play_window(&counter, &peak, 0.0);
+        assert_eq!(
+            watchdog.check_at(start + StdDuration::from_secs(2), false),
+            None
+        );
+        assert_eq!(
+            watchdog.check_at(start + StdDuration::from_secs(5), true),
+            None
+        );
+    }
+
+    #[test]
+    fn peak_meter_keeps_the_loudest_sample_until_taken() {
+        let peak = PeakMeter::default();
+
+        peak.record(&[0.1, -0.4]);
+        peak.record(&[0.2]);
+
+        assert_eq!(peak.take(), 0.4);
+        assert_eq!(peak.take(), 0.0);
+    }
+}
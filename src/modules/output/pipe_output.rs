@@ -0,0 +1,320 @@
+//! A module that contains code related to writing the rendered samples to stdout as raw PCM instead of playing them,
+//! so that another program like sox, ffmpeg or an icecast source client can take the signal from there.
+
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::{Duration as StdDuration, Instant};
+
+use anyhow::Error;
+
+use crate::modules::exit_status::PlaybackError;
+use crate::modules::output::output_common::{AudioSink, RenderCallback};
+use crate::modules::output::sample_conversion::{Dither, convert_samples};
+
+/// The sample rate the pipe is written at unless another one is chosen.
+pub const DEFAULT_PIPE_SAMPLE_RATE: u32 = 48_000;
+
+/// Every frame written to the pipe holds a left and a right sample.
+const PIPE_CHANNELS: usize = 2;
+
+/// How much audio is rendered and written at a time.
+const PIPE_BLOCK: StdDuration = StdDuration::from_millis(20);
+
+/// How far the pipe may run ahead of the wall clock. The session is timed by the clock,
+/// so writing any faster would only fill up the buffers of the program reading it.
+const PIPE_LEAD: StdDuration = StdDuration::from_millis(100);
+
+/// How long the writer waits when the synthesis thread hasn't rendered anything new yet.
+const PIPE_IDLE_SLEEP: StdDuration = StdDuration::from_millis(1);
+
+/// The sample formats raw PCM can be written in, both little endian with the left and right samples interleaved.
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum PcmFormat {
+    /// 32 bit floating point samples between -1.0 and 1.0.
+    F32,
+    /// 16 bit signed integer samples, dithered on the way down from floating point.
+    S16,
+}
+
+/// This formatter returns the name sox and ffmpeg know the format by, to be shown to the user.
+impl fmt::Display for PcmFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PcmFormat::F32 => write!(f, "f32le"),
+            PcmFormat::S16 => write!(f, "s16le"),
+        }
+    }
+}
+
+/// An audio sink that writes raw stereo PCM to a pipe, usually stdout, at the pace it would play at.
+pub struct PipeSink {
+    sample_rate: u32,
+    format: PcmFormat,
+    writer: Option<Box<dyn Write + Send>>,
+    running: Arc<AtomicBool>,
+}
+
+impl PipeSink {
+    pub fn new(writer: Box<dyn Write + Send>, sample_rate: u32, format: PcmFormat) -> Self {
+        PipeSink {
+            sample_rate,
+            format,
+            writer: Some(writer),
+            running: Arc::new(AtomicBool::new(true)),
+        }
+    }
+
+    /// Returns a sink that writes to the program's stdout.
+    pub fn stdout(sample_rate: u32, format: PcmFormat) -> Self {
+        PipeSink::new(Box::new(io::stdout()), sample_rate, format)
+    }
+}
+
+/// This implementation writes the rendered samples from a thread of its own, the stand in for a sound card's clock.
+impl AudioSink for PipeSink {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn channels(&self) -> usize {
+        PIPE_CHANNELS
+    }
+
+    fn start(&mut self, render: RenderCallback, errors: Sender<Error>) -> Result<(), Error> {
+        let writer = self.writer.take().ok_or_else(|| {
+            PlaybackError::Stream("The pipe has already been written to.".to_string())
+        })?;
+        let (sample_rate, format) = (self.sample_rate, self.format);
+        let running = Arc::clone(&self.running);
+
+        thread::spawn(move || write_pipe(writer, render, errors, sample_rate, format, running));
+        Ok(())
+    }
+}
+
+/// Stops the writer thread once the sink is dropped. It isn't waited for, since a reader that stopped
+/// reading would leave it blocked on a full pipe.
+impl Drop for PipeSink {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Renders and writes one block at a time until told to stop, keeping at most `PIPE_LEAD` ahead of the wall clock.
+/// Only real audio is written, so a synthesis thread falling behind delays the stream instead of leaving gaps in it.
+/// A failed write ends the stream and is sent through `errors`.
+fn write_pipe(
+    mut writer: Box<dyn Write + Send>,
+    mut render: RenderCallback,
+    errors: Sender<Error>,
+    sample_rate: u32,
+    format: PcmFormat,
+    running: Arc<AtomicBool>,
+) {
+    let block_frames = ((PIPE_BLOCK.as_secs_f64() * sample_rate as f64) as usize).max(1);
+    let mut rendered = vec![0.0; block_frames * PIPE_CHANNELS];
+    let mut bytes = Vec::new();
+    let mut dither = Dither::new();
+    let started = Instant::now();
+    let mut frames_written: u64 = 0;
+
+    while running.load(Ordering::Relaxed) {
+        let written_length = StdDuration::from_secs_f64(frames_written as f64 / sample_rate as f64);
+        let ahead = written_length.saturating_sub(started.elapsed() + PIPE_LEAD);
+        if !ahead.is_zero() {
+            thread::sleep(ahead);
+            continue;
+        }
+
+        let written = render(&mut rendered);
+        if written == 0 {
+            thread::sleep(PIPE_IDLE_SLEEP);
+            continue;
+        }
+
+        bytes.clear();
+        encode_samples(&rendered[..written], format, &mut dither, &mut bytes);
+        if let Err(err) = writer.write_all(&bytes).and_then(|()| writer.flush()) {
+            let _ = errors.send(pipe_error(err));
+            return;
+        }
+        frames_written += (written / PIPE_CHANNELS) as u64;
+    }
+}
+
+/// Appends the samples to `bytes` in the format, dithering them first when they are rounded to 16 bits.
+fn encode_samples(samples: &[f32], format: PcmFormat, dither: &mut Dither, bytes: &mut Vec<u8>) {
+    match format {
+        PcmFormat::F32 => {
+            for sample in samples {
+                bytes.extend_from_slice(&sample.to_le_bytes());
+            }
+        }
+        PcmFormat::S16 => {
+            let mut converted = vec![0i16; samples.len()];
+            convert_samples(&mut converted, samples, Some(dither));
+
+            for sample in converted {
+                bytes.extend_from_slice(&sample.to_le_bytes());
+            }
+        }
+    }
+}
+
+/// Returns the playback error for a failed write, a reader that went away counts as a lost output.
+fn pipe_error(err: io::Error) -> Error {
+    match err.kind() {
+        io::ErrorKind::BrokenPipe => {
+            PlaybackError::DeviceLost("The program reading the audio closed the pipe.".to_string())
+        }
+        _ => PlaybackError::Stream(format!("The audio can't be written to the pipe: {}", err)),
+    }
+    .into()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::Mutex;
+    use std::sync::mpsc;
+
+    /// How long a test waits for the writer thread before giving up.
+    const TEST_TIMEOUT: StdDuration = StdDuration::from_secs(5);
+
+    /// A pipe that keeps everything written to it where the test can read it.
+    #[derive(Clone, Default)]
+    struct SharedPipe(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedPipe {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// A pipe whose reader has gone away.
+    struct ClosedPipe;
+
+    impl Write for ClosedPipe {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn steady_render(level: f32) -> RenderCallback {
+        Box::new(move |data: &mut [f32]| {
+            data.fill(level);
+            data.len()
+        })
+    }
+
+    #[test]
+    fn f32_samples_are_written_little_endian() {
+        let mut bytes = Vec::new();
+        encode_samples(
+            &[0.5, -0.25],
+            PcmFormat::F32,
+            &mut Dither::new(),
+            &mut bytes,
+        );
+
+        assert_eq!(
+            bytes,
+            [0.5f32.to_le_bytes(), (-0.25f32).to_le_bytes()].concat()
+        );
+    }
+
+    #[test]
+    fn s16_samples_are_dithered_to_within_a_step() {
+        let mut bytes = Vec::new();
+        encode_samples(
+            &[-1.0, 0.0, 1.0],
+            PcmFormat::S16,
+            &mut Dither::new(),
+            &mut bytes,
+        );
+
+        let samples: Vec<i16> = bytes
+            .chunks(2)
+            .map(|sample| i16::from_le_bytes([sample[0], sample[1]]))
+            .collect();
+
+        assert_eq!(samples.len(), 3);
+        assert!(samples[0] <= i16::MIN + 1);
+        assert!(samples[1].abs() <= 1);
+        assert!(samples[2] >= i16::MAX - 1);
+    }
+
+    #[test]
+    fn pipe_sink_writes_whole_frames_of_the_rendered_audio() {
+        let pipe = SharedPipe::default();
+        let (sender, _receiver) = mpsc::channel();
+        let mut sink = PipeSink::new(Box::new(pipe.clone()), 1_000, PcmFormat::F32);
+        sink.start(steady_render(0.25), sender).unwrap();
+
+        let started = Instant::now();
+        while pipe.0.lock().unwrap().len() < 80 {
+            assert!(started.elapsed() < TEST_TIMEOUT, "Nothing was written.");
+            thread::sleep(StdDuration::from_millis(1));
+        }
+        drop(sink);
+
+        let bytes = pipe.0.lock().unwrap().clone();
+        assert_eq!(bytes.len() % (PIPE_CHANNELS * 4), 0);
+        assert_eq!(bytes[..4], 0.25f32.to_le_bytes());
+    }
+
+    #[test]
+    fn pipe_sink_does_not_run_far_ahead_of_the_clock() {
+        let pipe = SharedPipe::default();
+        let (sender, _receiver) = mpsc::channel();
+        let mut sink = PipeSink::new(Box::new(pipe.clone()), 1_000, PcmFormat::F32);
+        sink.start(steady_render(0.0), sender).unwrap();
+
+        thread::sleep(StdDuration::from_millis(50));
+        drop(sink);
+
+        // At 1,000 frames a second, the lead and one block more are all that can be written this early.
+        let frames = pipe.0.lock().unwrap().len() / (PIPE_CHANNELS * 4);
+        assert!(
+            frames > 0 && frames <= 200,
+            "{} frames were written.",
+            frames
+        );
+    }
+
+    #[test]
+    fn closed_pipe_is_reported_as_a_lost_output() {
+        let (sender, receiver) = mpsc::channel();
+        let mut sink = PipeSink::new(Box::new(ClosedPipe), 1_000, PcmFormat::S16);
+        sink.start(steady_render(0.25), sender).unwrap();
+
+        let err = receiver.recv_timeout(TEST_TIMEOUT).unwrap();
+
+        assert!(matches!(
+            err.downcast_ref::<PlaybackError>(),
+            Some(PlaybackError::DeviceLost(_))
+        ));
+    }
+
+    #[test]
+    fn pipe_sink_can_only_be_started_once() {
+        let (sender, _receiver) = mpsc::channel();
+        let mut sink = PipeSink::new(Box::new(SharedPipe::default()), 1_000, PcmFormat::F32);
+        sink.start(steady_render(0.0), sender.clone()).unwrap();
+
+        assert!(sink.start(steady_render(0.0), sender).is_err());
+    }
+}
@@ -0,0 +1,120 @@
+//! A module that contains code related to converting the rendered samples to the format an output device plays.
+
+use cpal::{FromSample, Sample};
+
+/// Adds triangular dither of one 16 bit step to samples before they are rounded to 16 bits,
+/// which turns the rounding error of quiet passages into a faint hiss instead of distortion.
+#[derive(Debug, Clone)]
+pub struct Dither {
+    state: u32,
+}
+
+impl Dither {
+    pub fn new() -> Self {
+        Dither { state: 0x9E37_79B9 }
+    }
+
+    /// Returns a random number between 0.0 and 1.0 from a xorshift generator, which is cheap enough for the audio thread.
+    fn next_random(&mut self) -> f32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+
+        (self.state >> 8) as f32 / (1 << 24) as f32
+    }
+
+    /// Returns the sample with the dither noise added, kept between -1.0 and 1.0.
+    pub fn apply(&mut self, sample: f32) -> f32 {
+        // The difference of two uniform values has a triangular distribution between -1 and 1 steps.
+        let noise = (self.next_random() - self.next_random()) / i16::MAX as f32;
+
+        (sample + noise).clamp(-1.0, 1.0)
+    }
+}
+
+/// This implementation starts the generator from a fixed seed, dither doesn't need to be unpredictable.
+impl Default for Dither {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Writes the rendered samples into the device's buffer, converting each one to the device's sample type.
+/// The dither is applied first when there is one.
+pub fn convert_samples<T>(output: &mut [T], samples: &[f32], mut dither: Option<&mut Dither>)
+where
+    T: Sample + FromSample<f32>,
+{
+    for (out, sample) in output.iter_mut().zip(samples) {
+        let sample = match dither.as_deref_mut() {
+            Some(dither) => dither.apply(*sample),
+            None => *sample,
+        };
+
+        *out = T::from_sample(sample);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn convert_samples_to_i16() {
+        let mut output = [0i16; 3];
+        convert_samples(&mut output, &[-1.0, 0.0, 1.0], None);
+
+        assert_eq!(output, [i16::MIN, 0, i16::MAX]);
+    }
+
+    #[test]
+    fn convert_samples_to_u16_centres_silence() {
+        let mut output = [0u16; 3];
+        convert_samples(&mut output, &[-1.0, 0.0, 1.0], None);
+
+        assert_eq!(output, [0, 32_768, u16::MAX]);
+    }
+
+    #[test]
+    fn convert_samples_to_i32() {
+        let mut output = [0i32; 2];
+        convert_samples(&mut output, &[0.0, 0.5], None);
+
+        assert_eq!(output, [0, 1 << 30]);
+    }
+
+    #[test]
+    fn dither_stays_within_one_step() {
+        let mut dither = Dither::new();
+        let step = 1.0 / i16::MAX as f32;
+        let mut sum = 0.0;
+
+        for _ in 0..10_000 {
+            let noise = dither.apply(0.0);
+            assert!(noise.abs() <= step);
+            sum += noise;
+        }
+
+        // The noise averages out, so it doesn't shift the signal.
+        assert!((sum / 10_000.0).abs() < step / 10.0);
+    }
+
+    #[test]
+    fn dither_keeps_full_scale_in_range() {
+        let mut dither = Dither::new();
+
+        for _ in 0..1_000 {
+            assert!(dither.apply(1.0) <= 1.0);
+            assert!(dither.apply(-1.0) >= -1.0);
+        }
+    }
+
+    #[test]
+    fn dithered_silence_rounds_to_the_nearest_steps() {
+        let mut dither = Dither::new();
+        let mut output = [0i16; 1_000];
+        convert_samples(&mut output, &[0.0; 1_000], Some(&mut dither));
+
+        assert!(output.iter().all(|sample| (-1..=1).contains(sample)));
+    }
+}
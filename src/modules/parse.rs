@@ -0,0 +1,100 @@
+//! A module that contains code shared by the parsers that read the program's values from text.
+
+use std::fmt;
+
+/// The error returned when text can't be read as one of the program's values, like a preset or a duration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseValueError {
+    /// What the text was meant to be, like `preset` or `beat frequency`.
+    pub kind: &'static str,
+    /// The text that couldn't be read.
+    pub input: String,
+}
+
+impl ParseValueError {
+    pub(crate) fn new(kind: &'static str, input: &str) -> Self {
+        ParseValueError {
+            kind,
+            input: input.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ParseValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "\"{}\" is not a valid {}", self.input, self.kind)
+    }
+}
+
+impl std::error::Error for ParseValueError {}
+
+/// Turns a name like `Solfeggio Heart` or `solfeggio_heart` into `solfeggio-heart` so that names can be compared.
+pub(crate) fn to_slug(text: &str) -> String {
+    text.split(|c: char| c.is_whitespace() || c == '-' || c == '_')
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Reads a frequency such as `6.3`, `6.3hz` or `6.3 Hz`, which has to be a positive number.
+pub(crate) fn parse_hz(text: &str) -> Option<f32> {
+    let text = text.trim().to_lowercase();
+    let number = text.strip_suffix("hz").unwrap_or(&text).trim_end();
+    let hz: f32 = number.parse().ok()?;
+
+    (hz.is_finite() && hz > 0.0).then_some(hz)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    macro_rules! test_to_slug_cases {
+        ($($name:ident:($a:expr, $expected:expr),)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    assert_eq!(to_slug($a), $expected)
+                }
+            )*
+        };
+    }
+
+    test_to_slug_cases! {
+        to_slug_keeps_a_slug: ("solfeggio-heart", "solfeggio-heart"),
+        to_slug_from_display_name: ("Solfeggio Heart Chakra", "solfeggio-heart-chakra"),
+        to_slug_from_snake_case: ("tuning_fork_root", "tuning-fork-root"),
+        to_slug_collapses_separators: ("  High -- Focus ", "high-focus"),
+    }
+
+    macro_rules! test_parse_hz_cases {
+        ($($name:ident:($a:expr, $expected:expr),)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    assert_eq!(parse_hz($a), $expected)
+                }
+            )*
+        };
+    }
+
+    test_parse_hz_cases! {
+        parse_hz_plain_number: ("6.3", Some(6.3)),
+        parse_hz_with_suffix: ("6.3hz", Some(6.3)),
+        parse_hz_with_spaced_suffix: (" 432 Hz ", Some(432.0)),
+        parse_hz_rejects_zero: ("0hz", None),
+        parse_hz_rejects_negative: ("-4", None),
+        parse_hz_rejects_infinity: ("inf", None),
+        parse_hz_rejects_text: ("theta", None),
+        parse_hz_rejects_bare_suffix: ("hz", None),
+    }
+
+    #[test]
+    fn parse_value_error_message() {
+        assert_eq!(
+            ParseValueError::new("preset", "nap").to_string(),
+            "\"nap\" is not a valid preset"
+        );
+    }
+}
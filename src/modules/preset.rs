@@ -1,14 +1,73 @@
 //! A module that contains code that allows for presets so that all settings can be easily used and passed around.
 //!
 use std::fmt;
+use std::str::FromStr;
+use std::time::Duration as StdDuration;
 
 use crate::modules::{
-    duration::duration::Duration,
+    duration::{duration::Duration, duration_common::ToMinutes},
     frequency::{beat_frequency::BeatFrequency, carrier_frequency::CarrierFrequency},
+    parse::{ParseValueError, to_slug},
 };
 
+/// Presets whose default duration is at most this many minutes are tagged as `short`.
+const SHORT_PRESET_MINUTES: u32 = 15;
+
+/// How many minutes the sleep presets take to fade out at the end, so the silence doesn't wake the listener.
+const SLEEP_END_FADE_MINUTES: u64 = 10;
+
+/// Every tag that a preset can carry.
+pub const PRESET_TAGS: [&str; 14] = [
+    "work",
+    "focus",
+    "relaxation",
+    "sleep",
+    "meditation",
+    "healing",
+    "mood",
+    "chakra",
+    "crown",
+    "solfeggio",
+    "tuning-fork",
+    "grounding",
+    "communication",
+    "short",
+];
+
+/// The groups that the built in presets are listed under in the preset menu.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PresetCategory {
+    General,
+    CrownChakra,
+    Solfeggio,
+    TuningFork,
+}
+
+/// This implementation returns the name the category is listed under.
+impl fmt::Display for PresetCategory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PresetCategory::General => write!(f, "General"),
+            PresetCategory::CrownChakra => write!(f, "Crown Chakra"),
+            PresetCategory::Solfeggio => write!(f, "Solfeggio"),
+            PresetCategory::TuningFork => write!(f, "Tuning Fork"),
+        }
+    }
+}
+
+/// This function returns all of the preset categories in the order they are listed in.
+pub fn preset_category_list() -> Vec<PresetCategory> {
+    vec![
+        PresetCategory::General,
+        PresetCategory::CrownChakra,
+        PresetCategory::Solfeggio,
+        PresetCategory::TuningFork,
+    ]
+}
+
 /// This structure groups the basic values needed to run the binaural beat program.
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BinauralPresetGroup {
     pub preset: Preset,
     pub carrier: CarrierFrequency,
@@ -18,6 +77,8 @@ pub struct BinauralPresetGroup {
 
 /// The preset enum allows the user to be able to select a preset to use on the command line.
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
 pub enum Preset {
     /// **Focus:**
     /// A preset for heightened concentration and alertness, typically used
@@ -194,79 +255,298 @@ pub enum Preset {
     TuningForkCrown,
 }
 
+impl Preset {
+    /// Returns the tags that describe what the preset is for, used to filter the list of presets.
+    pub fn tags(&self) -> Vec<&'static str> {
+        let mut tags = match self {
+            Preset::Focus => vec!["work", "focus"],
+            Preset::HighFocus => vec!["work", "focus"],
+            Preset::Relaxation => vec!["relaxation"],
+            Preset::DeepRelaxation => vec!["relaxation", "meditation"],
+            Preset::Sleep => vec!["sleep"],
+            Preset::Chanting => vec!["meditation"],
+            Preset::Intuition => vec!["meditation"],
+            Preset::Astral => vec!["meditation", "sleep"],
+            Preset::Healing => vec!["healing", "sleep"],
+            Preset::Alpha => vec!["relaxation"],
+            Preset::Intelligence => vec!["work", "focus"],
+            Preset::Euphoria => vec!["mood"],
+            Preset::CrownFocus => vec!["chakra", "crown", "focus"],
+            Preset::CrownRelaxation => vec!["chakra", "crown", "relaxation"],
+            Preset::CrownSleep => vec!["chakra", "crown", "sleep"],
+            Preset::CrownChanting => vec!["chakra", "crown", "meditation"],
+            Preset::CrownIntuition => vec!["chakra", "crown", "meditation"],
+            Preset::CrownAstral => vec!["chakra", "crown", "sleep"],
+            Preset::SolfeggioRoot => vec!["chakra", "solfeggio", "grounding"],
+            Preset::SolfeggioSacral => vec!["chakra", "solfeggio", "mood"],
+            Preset::SolfeggioSolarPlexus => vec!["chakra", "solfeggio", "focus"],
+            Preset::SolfeggioHeart => vec!["chakra", "solfeggio", "relaxation"],
+            Preset::SolfeggioThroat => vec!["chakra", "solfeggio", "communication"],
+            Preset::SolfeggioThirdEye => vec!["chakra", "solfeggio", "meditation"],
+            Preset::SolfeggioCrown => vec!["chakra", "solfeggio", "crown", "meditation"],
+            Preset::TuningForkRoot => vec!["chakra", "tuning-fork", "grounding"],
+            Preset::TuningForkSacral => vec!["chakra", "tuning-fork", "mood"],
+            Preset::TuningForkSolarPlexus => vec!["chakra", "tuning-fork", "focus"],
+            Preset::TuningForkHeart => vec!["chakra", "tuning-fork", "relaxation"],
+            Preset::TuningForkThroat => vec!["chakra", "tuning-fork", "communication"],
+            Preset::TuningForkThirdEye => vec!["chakra", "tuning-fork", "meditation"],
+            Preset::TuningForkCrown => vec!["chakra", "tuning-fork", "crown", "meditation"],
+        };
+
+        if BinauralPresetGroup::from(*self).duration.to_minutes() <= SHORT_PRESET_MINUTES {
+            tags.push("short");
+        }
+
+        tags
+    }
+
+    /// Returns what the preset is for and the brainwaves it works with, shown when the preset is picked.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Preset::Focus => {
+                "A preset for heightened concentration and alertness, typically used for studying or complex problem-solving. It utilizes Beta brainwaves (12-30 Hz) which are associated with active thinking."
+            }
+            Preset::HighFocus => {
+                "A more intense version of the Focus preset, pushing the mind to higher levels of cognitive processing. It leverages Gamma brainwaves (30-100 Hz), linked to peak concentration and intelligence."
+            }
+            Preset::Relaxation => {
+                "Promotes a state of calm alertness, ideal for unwinding after a stressful day or for light meditation. This preset uses Alpha brainwaves (8-12 Hz)."
+            }
+            Preset::DeepRelaxation => {
+                "A deeper state of calm, bridging the gap between wakefulness and sleep. It's often used for deep meditation or to prepare for rest. This preset uses Theta brainwaves (4-8 Hz)."
+            }
+            Preset::Sleep => {
+                "Designed to induce a state of deep, restorative sleep. It utilizes Delta brainwaves (0.5-4 Hz), which are associated with deep, dreamless sleep."
+            }
+            Preset::Chanting => {
+                "A preset that mimics the meditative state achieved during chanting. It helps to calm the mind and body using Theta brainwaves (4-8 Hz)."
+            }
+            Preset::Intuition => {
+                "This preset is designed to enhance intuition and insight by fostering a Theta state, which is linked to creativity and subconscious processing."
+            }
+            Preset::Astral => {
+                "An advanced preset aimed at assisting with out-of-body or astral projection experiences. It combines a deep Theta beat with a Delta carrier to induce a highly altered state of consciousness."
+            }
+            Preset::Healing => {
+                "This preset is thought to promote physical and mental healing by inducing a deep Delta state, which is associated with the body's natural restorative processes during sleep."
+            }
+            Preset::Alpha => {
+                "A preset that specifically targets the Alpha brainwave state (8-12 Hz) to encourage a feeling of relaxed awareness and stress reduction."
+            }
+            Preset::Intelligence => {
+                "This preset stimulates the brain for enhanced learning and cognitive function. It primarily uses Gamma brainwaves (30-100 Hz), which are linked to high-level information processing."
+            }
+            Preset::Euphoria => {
+                "A preset designed to promote feelings of happiness and well-being. It utilizes a Gamma beat, which is often associated with endorphin release and positive emotional states."
+            }
+            Preset::CrownFocus => {
+                "Combines the Crown Chakra's tuning fork frequency with a Beta beat for focused meditation on spiritual connection."
+            }
+            Preset::CrownRelaxation => {
+                "Combines the Crown Chakra's tuning fork frequency with an Alpha beat to promote a relaxed spiritual state."
+            }
+            Preset::CrownSleep => {
+                "Combines the Crown Chakra's tuning fork frequency with a Delta beat for deep rest and spiritual renewal."
+            }
+            Preset::CrownChanting => {
+                "Combines the Crown Chakra's tuning fork frequency with a Theta beat for a deeply meditative state during spiritual practices."
+            }
+            Preset::CrownIntuition => {
+                "Combines the Crown Chakra's tuning fork frequency with a Theta beat to enhance intuition and cosmic awareness."
+            }
+            Preset::CrownAstral => {
+                "Combines the Crown Chakra's tuning fork frequency with a Delta beat for advanced meditation and astral exploration."
+            }
+            Preset::SolfeggioRoot => {
+                "Uses the 396 Hz Solfeggio tone with a Delta beat for grounding and stability."
+            }
+            Preset::SolfeggioSacral => {
+                "Uses the 417 Hz Solfeggio tone with a Theta beat for creativity and emotional release."
+            }
+            Preset::SolfeggioSolarPlexus => {
+                "Uses the 528 Hz Solfeggio tone with an Alpha beat for transformation and motivation."
+            }
+            Preset::SolfeggioHeart => {
+                "Uses the 639 Hz Solfeggio tone with an Alpha beat for love and connection."
+            }
+            Preset::SolfeggioThroat => {
+                "Uses the 741 Hz Solfeggio tone with a Beta beat for communication and expression."
+            }
+            Preset::SolfeggioThirdEye => {
+                "Uses the 852 Hz Solfeggio tone with a Beta beat for clarity and intuition."
+            }
+            Preset::SolfeggioCrown => {
+                "Uses the 963 Hz Solfeggio tone with a Gamma beat for spiritual connection and unity."
+            }
+            Preset::TuningForkRoot => {
+                "Uses the 194.18 Hz Tuning Fork tone with a Delta beat for grounding."
+            }
+            Preset::TuningForkSacral => {
+                "Uses the 210.42 Hz Tuning Fork tone with a Theta beat for emotional flow."
+            }
+            Preset::TuningForkSolarPlexus => {
+                "Uses the 126.22 Hz Tuning Fork tone with an Alpha beat for confidence."
+            }
+            Preset::TuningForkHeart => {
+                "Uses the 136.10 Hz Tuning Fork tone with an Alpha beat for love and compassion."
+            }
+            Preset::TuningForkThroat => {
+                "Uses the 141.27 Hz Tuning Fork tone with a Beta beat for communication."
+            }
+            Preset::TuningForkThirdEye => {
+                "Uses the 221.23 Hz Tuning Fork tone with a Beta beat for insight and wisdom."
+            }
+            Preset::TuningForkCrown => {
+                "Uses the 172.06 Hz Tuning Fork tone with a Gamma beat for spiritual transcendence."
+            }
+        }
+    }
+
+    /// Returns the category the preset is listed under in the preset menu.
+    pub fn category(&self) -> PresetCategory {
+        match self {
+            Preset::Focus
+            | Preset::HighFocus
+            | Preset::Relaxation
+            | Preset::DeepRelaxation
+            | Preset::Sleep
+            | Preset::Chanting
+            | Preset::Intuition
+            | Preset::Astral
+            | Preset::Healing
+            | Preset::Alpha
+            | Preset::Intelligence
+            | Preset::Euphoria => PresetCategory::General,
+            Preset::CrownFocus
+            | Preset::CrownRelaxation
+            | Preset::CrownSleep
+            | Preset::CrownChanting
+            | Preset::CrownIntuition
+            | Preset::CrownAstral => PresetCategory::CrownChakra,
+            Preset::SolfeggioRoot
+            | Preset::SolfeggioSacral
+            | Preset::SolfeggioSolarPlexus
+            | Preset::SolfeggioHeart
+            | Preset::SolfeggioThroat
+            | Preset::SolfeggioThirdEye
+            | Preset::SolfeggioCrown => PresetCategory::Solfeggio,
+            Preset::TuningForkRoot
+            | Preset::TuningForkSacral
+            | Preset::TuningForkSolarPlexus
+            | Preset::TuningForkHeart
+            | Preset::TuningForkThroat
+            | Preset::TuningForkThirdEye
+            | Preset::TuningForkCrown => PresetCategory::TuningFork,
+        }
+    }
+
+    /// Returns true when the preset carries every one of the given tags, ignoring case.
+    pub fn has_tags(&self, tags: &[String]) -> bool {
+        let own_tags = self.tags();
+
+        tags.iter().all(|tag| {
+            own_tags
+                .iter()
+                .any(|own_tag| own_tag.eq_ignore_ascii_case(tag))
+        })
+    }
+
+    /// Returns how long the tones fade to silence at the end of the session, which only the sleep presets do.
+    pub fn end_fade(&self) -> StdDuration {
+        if self.tags().contains(&"sleep") {
+            StdDuration::from_secs(SLEEP_END_FADE_MINUTES * 60)
+        } else {
+            StdDuration::ZERO
+        }
+    }
+
+    /// Returns the name used to pick the preset from text, like `solfeggio-heart` or `crown-focus`.
+    pub fn slug(&self) -> String {
+        preset_slug(&self.to_string())
+    }
+
+    /// Returns true when the text typed into the preset menu is part of the name or the start of one of the tags.
+    pub fn matches_filter(&self, filter: &str) -> bool {
+        let filter = filter.trim().to_lowercase();
+
+        self.to_string().to_lowercase().contains(&filter)
+            || self.tags().iter().any(|tag| tag.starts_with(&filter))
+    }
+}
+
 /// The this implementation converts a preset to a preset group of values based on predetermined settings.
 impl From<Preset> for BinauralPresetGroup {
     fn from(preset: Preset) -> Self {
         match preset {
             // General Presets
             Preset::Focus => BinauralPresetGroup {
-                preset: preset,
+                preset,
                 carrier: CarrierFrequency::Beta,
                 beat: BeatFrequency::Beta,
                 duration: Duration::ThirtyMinutes,
             },
             Preset::HighFocus => BinauralPresetGroup {
-                preset: preset,
+                preset,
                 carrier: CarrierFrequency::Gamma,
                 beat: BeatFrequency::Gamma,
                 duration: Duration::ThirtyMinutes,
             },
             Preset::Relaxation => BinauralPresetGroup {
-                preset: preset,
+                preset,
                 carrier: CarrierFrequency::Alpha,
                 beat: BeatFrequency::Alpha,
                 duration: Duration::FifteenMinutes,
             },
             Preset::DeepRelaxation => BinauralPresetGroup {
-                preset: preset,
+                preset,
                 carrier: CarrierFrequency::Theta,
                 beat: BeatFrequency::Theta,
                 duration: Duration::FifteenMinutes,
             },
             Preset::Sleep => BinauralPresetGroup {
-                preset: preset,
+                preset,
                 carrier: CarrierFrequency::Delta,
                 beat: BeatFrequency::Delta,
                 duration: Duration::SixtyMinutes,
             },
             Preset::Chanting => BinauralPresetGroup {
-                preset: preset,
+                preset,
                 carrier: CarrierFrequency::Theta,
                 beat: BeatFrequency::Theta,
                 duration: Duration::ThirtyMinutes,
             },
             Preset::Intuition => BinauralPresetGroup {
-                preset: preset,
+                preset,
                 carrier: CarrierFrequency::Theta,
                 beat: BeatFrequency::Theta,
                 duration: Duration::FifteenMinutes,
             },
             Preset::Astral => BinauralPresetGroup {
-                preset: preset,
+                preset,
                 carrier: CarrierFrequency::Custom(140.0),
                 beat: BeatFrequency::Custom(6.3),
                 duration: Duration::SixtyMinutes,
             },
             Preset::Healing => BinauralPresetGroup {
-                preset: preset,
+                preset,
                 carrier: CarrierFrequency::Delta,
                 beat: BeatFrequency::Theta,
                 duration: Duration::SixtyMinutes,
             },
             Preset::Alpha => BinauralPresetGroup {
-                preset: preset,
+                preset,
                 carrier: CarrierFrequency::Alpha,
                 beat: BeatFrequency::Alpha,
                 duration: Duration::ThirtyMinutes,
             },
             Preset::Intelligence => BinauralPresetGroup {
-                preset: preset,
+                preset,
                 carrier: CarrierFrequency::Gamma,
                 beat: BeatFrequency::Gamma,
                 duration: Duration::TenMinutes,
             },
             Preset::Euphoria => BinauralPresetGroup {
-                preset: preset,
+                preset,
                 carrier: CarrierFrequency::Custom(210.42),
                 beat: BeatFrequency::Custom(20.0),
                 duration: Duration::TenMinutes,
@@ -274,37 +554,37 @@ impl From<Preset> for BinauralPresetGroup {
 
             // Crown Chakra Presets
             Preset::CrownFocus => BinauralPresetGroup {
-                preset: preset,
+                preset,
                 carrier: CarrierFrequency::TuningForkCrown,
                 beat: BeatFrequency::Beta,
                 duration: Duration::ThirtyMinutes,
             },
             Preset::CrownRelaxation => BinauralPresetGroup {
-                preset: preset,
+                preset,
                 carrier: CarrierFrequency::TuningForkCrown,
                 beat: BeatFrequency::Alpha,
                 duration: Duration::FifteenMinutes,
             },
             Preset::CrownSleep => BinauralPresetGroup {
-                preset: preset,
+                preset,
                 carrier: CarrierFrequency::TuningForkCrown,
                 beat: BeatFrequency::Delta,
                 duration: Duration::SixtyMinutes,
             },
             Preset::CrownChanting => BinauralPresetGroup {
-                preset: preset,
+                preset,
                 carrier: CarrierFrequency::TuningForkCrown,
                 beat: BeatFrequency::Theta,
                 duration: Duration::ThirtyMinutes,
             },
             Preset::CrownIntuition => BinauralPresetGroup {
-                preset: preset,
+                preset,
                 carrier: CarrierFrequency::TuningForkCrown,
                 beat: BeatFrequency::Theta,
                 duration: Duration::FifteenMinutes,
             },
             Preset::CrownAstral => BinauralPresetGroup {
-                preset: preset,
+                preset,
                 carrier: CarrierFrequency::TuningForkCrown,
                 beat: BeatFrequency::Delta,
                 duration: Duration::SixtyMinutes,
@@ -312,43 +592,43 @@ impl From<Preset> for BinauralPresetGroup {
 
             // Solfeggio Chakra Presets
             Preset::SolfeggioRoot => BinauralPresetGroup {
-                preset: preset,
+                preset,
                 carrier: CarrierFrequency::SolfeggioRoot,
                 beat: BeatFrequency::Delta,
                 duration: Duration::ThirtyMinutes,
             },
             Preset::SolfeggioSacral => BinauralPresetGroup {
-                preset: preset,
+                preset,
                 carrier: CarrierFrequency::SolfeggioSacral,
                 beat: BeatFrequency::Theta,
                 duration: Duration::ThirtyMinutes,
             },
             Preset::SolfeggioSolarPlexus => BinauralPresetGroup {
-                preset: preset,
+                preset,
                 carrier: CarrierFrequency::SolfeggioSolarPlexus,
                 beat: BeatFrequency::Alpha,
                 duration: Duration::ThirtyMinutes,
             },
             Preset::SolfeggioHeart => BinauralPresetGroup {
-                preset: preset,
+                preset,
                 carrier: CarrierFrequency::SolfeggioHeart,
                 beat: BeatFrequency::Alpha,
                 duration: Duration::FifteenMinutes,
             },
             Preset::SolfeggioThroat => BinauralPresetGroup {
-                preset: preset,
+                preset,
                 carrier: CarrierFrequency::SolfeggioThroat,
                 beat: BeatFrequency::Beta,
                 duration: Duration::TenMinutes,
             },
             Preset::SolfeggioThirdEye => BinauralPresetGroup {
-                preset: preset,
+                preset,
                 carrier: CarrierFrequency::SolfeggioThirdEye,
                 beat: BeatFrequency::Beta,
                 duration: Duration::TenMinutes,
             },
             Preset::SolfeggioCrown => BinauralPresetGroup {
-                preset: preset,
+                preset,
                 carrier: CarrierFrequency::SolfeggioCrown,
                 beat: BeatFrequency::Gamma,
                 duration: Duration::TenMinutes,
@@ -356,43 +636,43 @@ impl From<Preset> for BinauralPresetGroup {
 
             // Tuning Fork Chakra Presets
             Preset::TuningForkRoot => BinauralPresetGroup {
-                preset: preset,
+                preset,
                 carrier: CarrierFrequency::TuningForkRoot,
                 beat: BeatFrequency::Delta,
                 duration: Duration::ThirtyMinutes,
             },
             Preset::TuningForkSacral => BinauralPresetGroup {
-                preset: preset,
+                preset,
                 carrier: CarrierFrequency::TuningForkSacral,
                 beat: BeatFrequency::Theta,
                 duration: Duration::ThirtyMinutes,
             },
             Preset::TuningForkSolarPlexus => BinauralPresetGroup {
-                preset: preset,
+                preset,
                 carrier: CarrierFrequency::TuningForkSolarPlexus,
                 beat: BeatFrequency::Alpha,
                 duration: Duration::ThirtyMinutes,
             },
             Preset::TuningForkHeart => BinauralPresetGroup {
-                preset: preset,
+                preset,
                 carrier: CarrierFrequency::TuningForkHeart,
                 beat: BeatFrequency::Alpha,
                 duration: Duration::FifteenMinutes,
             },
             Preset::TuningForkThroat => BinauralPresetGroup {
-                preset: preset,
+                preset,
                 carrier: CarrierFrequency::TuningForkThroat,
                 beat: BeatFrequency::Beta,
                 duration: Duration::TenMinutes,
             },
             Preset::TuningForkThirdEye => BinauralPresetGroup {
-                preset: preset,
+                preset,
                 carrier: CarrierFrequency::TuningForkThirdEye,
                 beat: BeatFrequency::Beta,
                 duration: Duration::TenMinutes,
             },
             Preset::TuningForkCrown => BinauralPresetGroup {
-                preset: preset,
+                preset,
                 carrier: CarrierFrequency::TuningForkCrown,
                 beat: BeatFrequency::Gamma,
                 duration: Duration::TenMinutes,
@@ -441,9 +721,33 @@ impl fmt::Display for Preset {
     }
 }
 
+/// A helper function that turns a preset name into its slug, leaving out the word chakra
+/// so that `Solfeggio Heart Chakra` and `solfeggio-heart` name the same preset.
+fn preset_slug(name: &str) -> String {
+    to_slug(name)
+        .split('-')
+        .filter(|word| *word != "chakra")
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// This implementation reads a preset by its slug or its full name, in any case.
+impl FromStr for Preset {
+    type Err = ParseValueError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let slug = preset_slug(text);
+
+        preset_list()
+            .into_iter()
+            .find(|preset| preset.slug() == slug)
+            .ok_or_else(|| ParseValueError::new("preset", text))
+    }
+}
+
 /// This function returns all of the presets used in a vector.
 pub fn preset_list() -> Vec<Preset> {
-    return vec![
+    vec![
         Preset::Focus,
         Preset::HighFocus,
         Preset::Relaxation,
@@ -476,7 +780,15 @@ pub fn preset_list() -> Vec<Preset> {
         Preset::TuningForkThroat,
         Preset::TuningForkThirdEye,
         Preset::TuningForkCrown,
-    ];
+    ]
+}
+
+/// This function returns the presets that carry every one of the given tags, in the usual order.
+pub fn presets_with_tags(tags: &[String]) -> Vec<Preset> {
+    preset_list()
+        .into_iter()
+        .filter(|preset| preset.has_tags(tags))
+        .collect()
 }
 
 #[cfg(test)]
@@ -494,6 +806,17 @@ mod test {
         };
     }
 
+    macro_rules! test_preset_enum_to_tags_cases {
+        ($($name:ident:($a:expr, $expected:expr),)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    assert_eq!($a.tags(),$expected)
+                }
+            )*
+        };
+    }
+
     macro_rules! test_preset_enum_to_text_description_cases {
         ($($name:ident:($a:expr, $expected:expr),)*) => {
             $(
@@ -591,6 +914,62 @@ mod test {
         }
     }
 
+    test_preset_enum_to_tags_cases! {
+        preset_tags_focus: (Preset::Focus, vec!["work", "focus"]),
+        preset_tags_relaxation: (Preset::Relaxation, vec!["relaxation", "short"]),
+        preset_tags_sleep: (Preset::Sleep, vec!["sleep"]),
+        preset_tags_intelligence: (Preset::Intelligence, vec!["work", "focus", "short"]),
+        preset_tags_crown_sleep: (Preset::CrownSleep, vec!["chakra", "crown", "sleep"]),
+        preset_tags_solfeggio_throat: (Preset::SolfeggioThroat, vec!["chakra", "solfeggio", "communication", "short"]),
+        preset_tags_tuning_fork_root: (Preset::TuningForkRoot, vec!["chakra", "tuning-fork", "grounding"]),
+    }
+
+    #[test]
+    fn every_preset_tag_is_a_known_tag() {
+        for preset in preset_list() {
+            for tag in preset.tags() {
+                assert!(
+                    PRESET_TAGS.contains(&tag),
+                    "{} has unknown tag {}",
+                    preset,
+                    tag
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn every_known_tag_is_used() {
+        for tag in PRESET_TAGS {
+            assert!(
+                !presets_with_tags(&[tag.to_string()]).is_empty(),
+                "{} is never used",
+                tag
+            );
+        }
+    }
+
+    #[test]
+    fn presets_with_tags_requires_every_tag() {
+        let existing_list = presets_with_tags(&["sleep".to_string(), "Chakra".to_string()]);
+        let expected_list = vec![Preset::CrownSleep, Preset::CrownAstral];
+
+        assert_eq!(existing_list, expected_list);
+    }
+
+    #[test]
+    fn matches_filter_checks_name_and_tags() {
+        assert!(Preset::DeepRelaxation.matches_filter("relax"));
+        assert!(Preset::DeepRelaxation.matches_filter("Meditat"));
+        assert!(Preset::Sleep.matches_filter(""));
+        assert!(!Preset::Sleep.matches_filter("work"));
+    }
+
+    #[test]
+    fn presets_with_no_tags_returns_every_preset() {
+        assert_eq!(presets_with_tags(&[]), preset_list());
+    }
+
     test_preset_enum_to_text_description_cases! {
         preset_text_focus: (Preset::Focus.to_string(), "Focus"),
         preset_text_high_focus: (Preset::HighFocus.to_string(), "High Focus"),
@@ -823,4 +1202,139 @@ mod test {
         duration: Duration::TenMinutes,
     }),
         }
+
+    macro_rules! test_preset_from_str_cases {
+        ($($name:ident:($a:expr, $expected:expr),)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    assert_eq!($a.parse::<Preset>().ok(),$expected)
+                }
+            )*
+        };
+    }
+
+    macro_rules! test_preset_end_fade_cases {
+        ($($name:ident:($a:expr, $minutes:expr),)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    assert_eq!($a.end_fade(),StdDuration::from_secs($minutes * 60))
+                }
+            )*
+        };
+    }
+
+    macro_rules! test_preset_category_cases {
+        ($($name:ident:($a:expr, $expected:expr),)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    assert_eq!($a.category(),$expected)
+                }
+            )*
+        };
+    }
+
+    test_preset_category_cases! {
+        preset_category_focus: (Preset::Focus, PresetCategory::General),
+        preset_category_euphoria: (Preset::Euphoria, PresetCategory::General),
+        preset_category_crown_focus: (Preset::CrownFocus, PresetCategory::CrownChakra),
+        preset_category_crown_astral: (Preset::CrownAstral, PresetCategory::CrownChakra),
+        preset_category_solfeggio_root: (Preset::SolfeggioRoot, PresetCategory::Solfeggio),
+        preset_category_solfeggio_crown: (Preset::SolfeggioCrown, PresetCategory::Solfeggio),
+        preset_category_tuning_fork_root: (Preset::TuningForkRoot, PresetCategory::TuningFork),
+        preset_category_tuning_fork_crown: (Preset::TuningForkCrown, PresetCategory::TuningFork),
+    }
+
+    #[test]
+    fn every_category_has_presets_in_list_order() {
+        let categories: Vec<PresetCategory> = preset_list()
+            .into_iter()
+            .map(|preset| preset.category())
+            .fold(Vec::new(), |mut categories, category| {
+                if categories.last() != Some(&category) {
+                    categories.push(category);
+                }
+                categories
+            });
+
+        assert_eq!(categories, preset_category_list());
+    }
+
+    #[test]
+    fn every_preset_has_a_description() {
+        for preset in preset_list() {
+            assert!(preset.description().ends_with('.'), "{}", preset);
+        }
+    }
+
+    #[test]
+    fn preset_description_comes_from_its_documentation() {
+        assert_eq!(
+            Preset::SolfeggioHeart.description(),
+            "Uses the 639 Hz Solfeggio tone with an Alpha beat for love and connection."
+        );
+    }
+
+    test_preset_end_fade_cases! {
+        preset_end_fade_sleep: (Preset::Sleep, 10),
+        preset_end_fade_healing: (Preset::Healing, 10),
+        preset_end_fade_crown_sleep: (Preset::CrownSleep, 10),
+        preset_end_fade_focus: (Preset::Focus, 0),
+        preset_end_fade_relaxation: (Preset::Relaxation, 0),
+    }
+
+    test_preset_from_str_cases! {
+        preset_from_str_slug: ("high-focus", Some(Preset::HighFocus)),
+        preset_from_str_solfeggio_heart: ("solfeggio-heart", Some(Preset::SolfeggioHeart)),
+        preset_from_str_full_name: ("Solfeggio Heart Chakra", Some(Preset::SolfeggioHeart)),
+        preset_from_str_crown: ("crown-focus", Some(Preset::CrownFocus)),
+        preset_from_str_crown_full_name: ("crown chakra focus", Some(Preset::CrownFocus)),
+        preset_from_str_tuning_fork: ("tuning-fork-third-eye", Some(Preset::TuningForkThirdEye)),
+        preset_from_str_unknown: ("nap", None),
+        preset_from_str_empty: ("", None),
+    }
+
+    #[test]
+    fn preset_slugs_are_unique_and_round_trip() {
+        for preset in preset_list() {
+            assert_eq!(preset.slug().parse::<Preset>(), Ok(preset));
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn preset_group_to_json_tags_custom_values() {
+        let preset_options = BinauralPresetGroup {
+            preset: Preset::SolfeggioHeart,
+            carrier: CarrierFrequency::Custom(432.0),
+            beat: BeatFrequency::Theta,
+            duration: Duration::Custom(45),
+        };
+
+        assert_eq!(
+            serde_json::to_value(preset_options).unwrap(),
+            serde_json::json!({
+                "preset": "solfeggio-heart",
+                "carrier": { "custom": 432.0 },
+                "beat": "theta",
+                "duration": { "custom": 45 },
+            })
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn preset_groups_round_trip_through_json() {
+        for preset in preset_list() {
+            let preset_options = BinauralPresetGroup::from(preset);
+            let json = serde_json::to_string(&preset_options).unwrap();
+
+            assert_eq!(
+                serde_json::from_str::<BinauralPresetGroup>(&json).unwrap(),
+                preset_options
+            );
+        }
+    }
 }
@@ -0,0 +1,217 @@
+//! A module that contains code related to showing how far along a playing session is.
+
+use std::fmt;
+use std::io::{self, Write};
+use std::thread;
+use std::time::Duration as StdDuration;
+
+use anyhow::Error;
+use serde_json::{Value, json};
+
+use crate::modules::bb_generator::BinauralPlayer;
+
+/// How many characters wide the progress bar is.
+const PROGRESS_BAR_WIDTH: usize = 30;
+
+/// How often the player is checked while the progress is shown, the line itself is only redrawn once a second.
+const PROGRESS_POLL_INTERVAL: StdDuration = StdDuration::from_millis(100);
+
+/// How far a session has played.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SessionProgress {
+    /// How long the current play of the session has been playing.
+    pub elapsed: StdDuration,
+    /// How long one play of the session takes.
+    pub length: StdDuration,
+    /// Which play of the session this is, starting at 1.
+    pub round: u32,
+    /// How many times the session plays, `None` when it repeats until stopped.
+    pub rounds: Option<u32>,
+}
+
+impl SessionProgress {
+    /// Returns how much of the session is left to play.
+    pub fn remaining(&self) -> StdDuration {
+        self.length.saturating_sub(self.elapsed)
+    }
+
+    /// Returns which play of the session this is, or `None` when the session only plays once.
+    pub fn round_text(&self) -> Option<String> {
+        match self.rounds {
+            Some(1) => None,
+            Some(rounds) => Some(format!("round {} of {}", self.round, rounds)),
+            None => Some(format!("round {}", self.round)),
+        }
+    }
+
+    /// Returns the progress as a JSON object, with the times in whole seconds.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "elapsed_seconds": self.elapsed.as_secs(),
+            "length_seconds": self.length.as_secs(),
+            "round": self.round,
+            "rounds": self.rounds,
+        })
+    }
+
+    /// Returns the share of the session that has played, between 0.0 and 1.0.
+    pub fn fraction(&self) -> f64 {
+        if self.length.is_zero() {
+            1.0
+        } else {
+            (self.elapsed.as_secs_f64() / self.length.as_secs_f64()).min(1.0)
+        }
+    }
+}
+
+/// This formatter draws a progress bar followed by the elapsed and remaining time.
+impl fmt::Display for SessionProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let filled = (self.fraction() * PROGRESS_BAR_WIDTH as f64).round() as usize;
+
+        write!(
+            f,
+            "[{}{}] {} elapsed, {} remaining",
+            "#".repeat(filled),
+            "-".repeat(PROGRESS_BAR_WIDTH - filled),
+            format_clock(self.elapsed),
+            format_clock(self.remaining())
+        )?;
+
+        match self.round_text() {
+            Some(round_text) => write!(f, " ({})", round_text),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Returns the duration as a clock, `mm:ss` below an hour and `h:mm:ss` from an hour on.
+pub fn format_clock(duration: StdDuration) -> String {
+    let seconds = duration.as_secs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{:02}:{:02}", minutes, seconds)
+    }
+}
+
+/// Redraws the session's progress on one line every second until the session stops playing,
+/// or until only `hand_over` of it is left when the next session of a queue starts over its end.
+/// Stopping the player from another thread still ends the session, the progress only watches it.
+pub fn show_progress(player: &mut BinauralPlayer, hand_over: StdDuration) -> Result<(), Error> {
+    show_progress_on(player, hand_over, &mut io::stdout())
+}
+
+/// Redraws the session's progress like `show_progress` on `out`, so that it can go to stderr when stdout carries the audio.
+pub fn show_progress_on(
+    player: &mut BinauralPlayer,
+    hand_over: StdDuration,
+    out: &mut dyn Write,
+) -> Result<(), Error> {
+    let mut last_drawn_second = None;
+
+    while player.is_playing_until(hand_over) {
+        if let Some(output_health) = player.take_output_health() {
+            // Finish the progress line first so the message gets its own line.
+            writeln!(out, "\n{}", output_health)?;
+            last_drawn_second = None;
+        }
+
+        let progress = player.progress();
+        let second = progress.elapsed.as_secs();
+
+        if last_drawn_second != Some(second) {
+            write!(out, "\r{}", progress)?;
+            out.flush()?;
+            last_drawn_second = Some(second);
+        }
+
+        thread::sleep(PROGRESS_POLL_INTERVAL);
+    }
+
+    writeln!(out)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    macro_rules! test_format_clock_cases {
+        ($($name:ident:($seconds:expr, $expected:expr),)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    assert_eq!(format_clock(StdDuration::from_secs($seconds)),$expected)
+                }
+            )*
+        };
+    }
+
+    macro_rules! test_progress_text_cases {
+        ($($name:ident:($elapsed:expr, $length:expr, $expected:expr),)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let progress = SessionProgress {
+                        elapsed: StdDuration::from_secs($elapsed),
+                        length: StdDuration::from_secs($length),
+                        round: 1,
+                        rounds: Some(1),
+                    };
+                    assert_eq!(progress.to_string(),$expected)
+                }
+            )*
+        };
+    }
+
+    test_format_clock_cases! {
+        format_clock_zero: (0, "00:00"),
+        format_clock_seconds: (59, "00:59"),
+        format_clock_minutes: (754, "12:34"),
+        format_clock_hours: (3600, "1:00:00"),
+        format_clock_overnight: (8 * 3600 + 65, "8:01:05"),
+    }
+
+    test_progress_text_cases! {
+        progress_text_start: (0, 600, "[------------------------------] 00:00 elapsed, 10:00 remaining"),
+        progress_text_middle: (300, 600, "[###############---------------] 05:00 elapsed, 05:00 remaining"),
+        progress_text_end: (600, 600, "[##############################] 10:00 elapsed, 00:00 remaining"),
+        progress_text_past_the_end: (700, 600, "[##############################] 11:40 elapsed, 00:00 remaining"),
+    }
+
+    #[test]
+    fn progress_text_shows_the_round() {
+        let progress = SessionProgress {
+            elapsed: StdDuration::from_secs(60),
+            length: StdDuration::from_secs(600),
+            round: 2,
+            rounds: Some(3),
+        };
+        assert!(
+            progress
+                .to_string()
+                .ends_with("09:00 remaining (round 2 of 3)")
+        );
+
+        let progress = SessionProgress {
+            rounds: None,
+            ..progress
+        };
+        assert!(progress.to_string().ends_with("09:00 remaining (round 2)"));
+    }
+
+    #[test]
+    fn progress_fraction_of_an_empty_session_is_complete() {
+        let progress = SessionProgress {
+            elapsed: StdDuration::ZERO,
+            length: StdDuration::ZERO,
+            round: 1,
+            rounds: Some(1),
+        };
+
+        assert_eq!(progress.fraction(), 1.0);
+    }
+}
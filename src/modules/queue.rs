@@ -0,0 +1,221 @@
+//! A module that contains the queue of presets played one after the other in a single run,
+//! and how each one hands over to the next.
+
+use std::fmt;
+use std::thread;
+use std::time::Duration as StdDuration;
+
+use anyhow::Error;
+
+use crate::modules::bb_generator::PlaybackOptions;
+use crate::modules::cancel_token::CancelToken;
+use crate::modules::duration::duration_common::ToMinutes;
+use crate::modules::exit_status::PlaybackError;
+use crate::modules::preset::BinauralPresetGroup;
+
+/// How often a gap between queued presets checks whether the queue was stopped.
+const GAP_POLL_INTERVAL: StdDuration = StdDuration::from_millis(100);
+
+/// How one preset of a queue hands over to the next.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QueueTransition {
+    /// The next preset starts once the one before has ended and this much silence has passed.
+    Gap(StdDuration),
+    /// The next preset fades in over the last part of the one before, which fades out over the same time.
+    Crossfade(StdDuration),
+}
+
+impl QueueTransition {
+    /// Returns the transition chosen on the command line, presets follow each other straight away without either.
+    pub fn from_seconds(gap: Option<u64>, crossfade: Option<u64>) -> Self {
+        match (gap, crossfade) {
+            (_, Some(seconds)) => QueueTransition::Crossfade(StdDuration::from_secs(seconds)),
+            (Some(seconds), None) => QueueTransition::Gap(StdDuration::from_secs(seconds)),
+            (None, None) => QueueTransition::Gap(StdDuration::ZERO),
+        }
+    }
+
+    /// Returns how long before the end of a preset the next one starts, which is zero unless they crossfade.
+    pub fn hand_over(&self) -> StdDuration {
+        match self {
+            QueueTransition::Gap(_) => StdDuration::ZERO,
+            QueueTransition::Crossfade(length) => *length,
+        }
+    }
+}
+
+/// This formatter returns how the presets follow each other, to be shown before the queue starts.
+impl fmt::Display for QueueTransition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueueTransition::Gap(gap) if gap.is_zero() => write!(f, "back to back"),
+            QueueTransition::Gap(gap) => {
+                write!(f, "with {} seconds of silence between", gap.as_secs())
+            }
+            QueueTransition::Crossfade(length) => {
+                write!(f, "crossfading over {} seconds", length.as_secs())
+            }
+        }
+    }
+}
+
+/// Returns the playback options for the preset at `index` of a queue `length` presets long.
+/// When crossfading, every preset but the first fades in and every preset but the last fades out over the crossfade.
+pub fn queue_playback_options(
+    playback_options: PlaybackOptions,
+    transition: QueueTransition,
+    index: usize,
+    length: usize,
+) -> PlaybackOptions {
+    let QueueTransition::Crossfade(crossfade) = transition else {
+        return playback_options;
+    };
+
+    PlaybackOptions {
+        fade_in: if index > 0 {
+            crossfade
+        } else {
+            playback_options.fade_in
+        },
+        fade_out: if index + 1 < length {
+            Some(crossfade)
+        } else {
+            playback_options.fade_out
+        },
+        ..playback_options
+    }
+}
+
+/// Checks that a crossfade fits into every preset of the queue, it can take up at most half of each.
+pub fn check_transition(
+    queue: &[BinauralPresetGroup],
+    transition: QueueTransition,
+) -> Result<(), Error> {
+    let crossfade = transition.hand_over();
+
+    if let Some(too_short) = queue
+        .iter()
+        .find(|preset_options| crossfade * 2 > preset_length(preset_options))
+    {
+        return Err(PlaybackError::InvalidSettings(format!(
+            "The crossfade of {} seconds is longer than half of {}.",
+            crossfade.as_secs(),
+            too_short.preset
+        ))
+        .into());
+    }
+
+    Ok(())
+}
+
+fn preset_length(preset_options: &BinauralPresetGroup) -> StdDuration {
+    StdDuration::from_secs(preset_options.duration.to_minutes() as u64 * 60)
+}
+
+/// Waits out the gap before the next preset of the queue, returning false when the queue was stopped in the meantime.
+pub fn wait_for_next(gap: StdDuration, queue_stop: &CancelToken) -> bool {
+    let mut waited = StdDuration::ZERO;
+
+    while waited < gap && !queue_stop.is_cancelled() {
+        let step = GAP_POLL_INTERVAL.min(gap - waited);
+        thread::sleep(step);
+        waited += step;
+    }
+
+    !queue_stop.is_cancelled()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::modules::duration::duration::Duration;
+    use crate::modules::preset::Preset;
+
+    macro_rules! test_transition_cases {
+        ($($name:ident:($gap:expr, $crossfade:expr, $expected:expr, $text:expr),)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let transition = QueueTransition::from_seconds($gap, $crossfade);
+                    assert_eq!(transition, $expected);
+                    assert_eq!(transition.to_string(), $text);
+                }
+            )*
+        };
+    }
+
+    test_transition_cases! {
+        transition_by_default: (None, None, QueueTransition::Gap(StdDuration::ZERO), "back to back"),
+        transition_gap: (Some(10), None, QueueTransition::Gap(StdDuration::from_secs(10)), "with 10 seconds of silence between"),
+        transition_crossfade: (None, Some(20), QueueTransition::Crossfade(StdDuration::from_secs(20)), "crossfading over 20 seconds"),
+    }
+
+    #[test]
+    fn crossfaded_presets_fade_into_each_other() {
+        let transition = QueueTransition::Crossfade(StdDuration::from_secs(20));
+        let playback_options = PlaybackOptions::default();
+
+        let first = queue_playback_options(playback_options, transition, 0, 3);
+        let middle = queue_playback_options(playback_options, transition, 1, 3);
+        let last = queue_playback_options(playback_options, transition, 2, 3);
+
+        assert_eq!(first.fade_in, StdDuration::ZERO);
+        assert_eq!(first.fade_out, Some(StdDuration::from_secs(20)));
+        assert_eq!(middle.fade_in, StdDuration::from_secs(20));
+        assert_eq!(middle.fade_out, Some(StdDuration::from_secs(20)));
+        assert_eq!(last.fade_in, StdDuration::from_secs(20));
+        assert_eq!(last.fade_out, None);
+    }
+
+    #[test]
+    fn presets_with_a_gap_keep_their_options() {
+        let playback_options = PlaybackOptions {
+            volume: 0.5,
+            ..PlaybackOptions::default()
+        };
+
+        assert_eq!(
+            queue_playback_options(
+                playback_options,
+                QueueTransition::Gap(StdDuration::from_secs(5)),
+                1,
+                2
+            ),
+            playback_options
+        );
+    }
+
+    #[test]
+    fn crossfade_must_fit_into_every_preset() {
+        let mut short = BinauralPresetGroup::from(Preset::Focus);
+        short.duration = Duration::Custom(1);
+        let queue = [BinauralPresetGroup::from(Preset::Sleep), short];
+
+        assert!(
+            check_transition(
+                &queue,
+                QueueTransition::Crossfade(StdDuration::from_secs(30))
+            )
+            .is_ok()
+        );
+        assert!(
+            check_transition(
+                &queue,
+                QueueTransition::Crossfade(StdDuration::from_secs(31))
+            )
+            .is_err()
+        );
+        assert!(
+            check_transition(&queue, QueueTransition::Gap(StdDuration::from_secs(600))).is_ok()
+        );
+    }
+
+    #[test]
+    fn stopped_queue_doesnt_wait_for_the_next_preset() {
+        let queue_stop = CancelToken::new();
+        queue_stop.cancel();
+
+        assert!(!wait_for_next(StdDuration::from_secs(60), &queue_stop));
+        assert!(wait_for_next(StdDuration::ZERO, &CancelToken::new()));
+    }
+}
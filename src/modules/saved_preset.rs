@@ -0,0 +1,608 @@
+//! A module that contains code related to the presets the user saved under their own name.
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Error;
+use serde_json::{Value, json};
+
+use crate::modules::duration::{duration::Duration, duration_common::ToMinutes};
+use crate::modules::frequency::{
+    beat_frequency::BeatFrequency, carrier_frequency::CarrierFrequency,
+    frequency_common::ToFrequency,
+};
+use crate::modules::parse::to_slug;
+use crate::modules::preset::{BinauralPresetGroup, Preset, PresetCategory, preset_category_list};
+use crate::modules::state_file::{Migration, migrate, write_atomically};
+
+/// The directory inside the user's config directory that the program keeps its files in.
+pub const CONFIG_DIR_NAME: &str = "binaural-beat-generator-cli";
+
+/// The file inside the program's config directory that holds the saved presets.
+const SAVED_PRESETS_FILE: &str = "presets.json";
+
+/// Each entry upgrades the saved presets file from the schema version before it,
+/// files written before versions were added are version 1.
+const SAVED_PRESETS_MIGRATIONS: &[Migration] = &[];
+
+/// A combination of carrier, beat and duration that the user saved under a name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SavedPreset {
+    pub name: String,
+    pub preset_options: BinauralPresetGroup,
+}
+
+impl SavedPreset {
+    /// Returns the saved preset as a JSON object, with the preset it started from and its settings.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "name": self.name,
+            "preset": self.preset_options.preset.slug(),
+            "carrier_hz": self.preset_options.carrier.to_hz(),
+            "beat_hz": self.preset_options.beat.to_hz(),
+            "minutes": self.preset_options.duration.to_minutes(),
+        })
+    }
+
+    /// Reads a saved preset written by `to_json`, or returns `None` when the object isn't one.
+    /// Frequencies that still match the preset it started from keep their names, the others become custom.
+    pub fn from_json(value: &Value) -> Option<Self> {
+        let name = value["name"].as_str()?.trim();
+        let preset: Preset = value["preset"].as_str()?.parse().ok()?;
+        let carrier_hz = value["carrier_hz"].as_f64()? as f32;
+        let beat_hz = value["beat_hz"].as_f64()? as f32;
+        let minutes = u32::try_from(value["minutes"].as_u64()?).ok()?;
+
+        if name.is_empty() || carrier_hz <= 0.0 || beat_hz < 0.0 || minutes == 0 {
+            return None;
+        }
+
+        let base = BinauralPresetGroup::from(preset);
+
+        Some(SavedPreset {
+            name: name.to_string(),
+            preset_options: BinauralPresetGroup {
+                preset,
+                carrier: if base.carrier.to_hz() == carrier_hz {
+                    base.carrier
+                } else {
+                    CarrierFrequency::Custom(carrier_hz)
+                },
+                beat: if base.beat.to_hz() == beat_hz {
+                    base.beat
+                } else {
+                    BeatFrequency::Custom(beat_hz)
+                },
+                duration: Duration::from_minutes(minutes),
+            },
+        })
+    }
+}
+
+/// This formatter will return the name the preset was saved under.
+impl fmt::Display for SavedPreset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+/// Why a name can't simply be used for a new saved preset.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NameConflict {
+    /// The name is empty once the surrounding spaces are removed.
+    Empty,
+    /// One of the built in presets already has this name, so it can't be used.
+    BuiltIn,
+    /// A saved preset already has this name, saving again replaces it.
+    Saved,
+}
+
+/// The presets the user saved, together with the file they are kept in.
+#[derive(Debug)]
+pub struct SavedPresetStore {
+    path: PathBuf,
+    presets: Vec<SavedPreset>,
+}
+
+impl SavedPresetStore {
+    /// Loads the saved presets from the user's config directory.
+    pub fn open_default() -> Result<Self, Error> {
+        let path = saved_presets_path()
+            .ok_or_else(|| anyhow::anyhow!("The config directory can't be found."))?;
+
+        Self::open(path)
+    }
+
+    /// Loads the saved presets from the given file, which doesn't exist until the first preset is saved.
+    /// Entries that can't be read are left out, but a file that isn't JSON at all or was written by a newer
+    /// version of the program is an error so it isn't overwritten.
+    pub fn open(path: PathBuf) -> Result<Self, Error> {
+        let presets = match fs::read_to_string(&path) {
+            Ok(text) => {
+                let value: Value = serde_json::from_str(&text)
+                    .map_err(|err| anyhow::anyhow!("{} can't be read: {}", path.display(), err))?;
+                let value = migrate(value, SAVED_PRESETS_MIGRATIONS, &path.display().to_string())?;
+
+                value["presets"]
+                    .as_array()
+                    .map(|presets| presets.iter().filter_map(SavedPreset::from_json).collect())
+                    .unwrap_or_default()
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => {
+                return Err(anyhow::anyhow!("{} can't be read: {}", path.display(), err));
+            }
+        };
+
+        Ok(SavedPresetStore { path, presets })
+    }
+
+    /// Returns the file the presets are kept in.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the saved presets, the most recently saved first.
+    pub fn presets(&self) -> &[SavedPreset] {
+        &self.presets
+    }
+
+    /// Returns why the name can't simply be used for a new preset, or `None` when it is free.
+    /// Names are compared the same way presets are parsed, ignoring case, spaces and dashes.
+    pub fn name_conflict(&self, name: &str) -> Option<NameConflict> {
+        let slug = to_slug(name);
+
+        if slug.is_empty() {
+            Some(NameConflict::Empty)
+        } else if name.parse::<Preset>().is_ok() {
+            Some(NameConflict::BuiltIn)
+        } else if self
+            .presets
+            .iter()
+            .any(|saved| to_slug(&saved.name) == slug)
+        {
+            Some(NameConflict::Saved)
+        } else {
+            None
+        }
+    }
+
+    /// Saves the preset at the top of the list and writes the file, replacing a saved preset with the same name.
+    pub fn save(&mut self, preset: SavedPreset) -> Result<(), Error> {
+        let slug = to_slug(&preset.name);
+        self.presets.retain(|saved| to_slug(&saved.name) != slug);
+        self.presets.insert(0, preset);
+
+        self.write()
+    }
+
+    /// A helper function that writes every saved preset to the file, creating its directory when needed.
+    /// The file is replaced in one step, so a crash while saving can't lose the presets saved before.
+    fn write(&self) -> Result<(), Error> {
+        let value = json!({
+            "version": SAVED_PRESETS_MIGRATIONS.len() + 1,
+            "presets": self.presets.iter().map(SavedPreset::to_json).collect::<Vec<Value>>(),
+        });
+
+        write_atomically(&self.path, &format!("{:#}\n", value))
+    }
+}
+
+/// Returns where the saved presets are kept, or `None` when the platform has no config directory.
+pub fn saved_presets_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(CONFIG_DIR_NAME).join(SAVED_PRESETS_FILE))
+}
+
+/// Represents an entry in the preset menu, either a preset the user saved or one of the built in presets.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PresetChoice {
+    Saved(SavedPreset),
+    BuiltIn(Preset),
+}
+
+impl PresetChoice {
+    /// Returns the name the entry goes by, the name it was saved under or the preset's own.
+    pub fn name(&self) -> String {
+        match self {
+            PresetChoice::Saved(saved) => saved.name.clone(),
+            PresetChoice::BuiltIn(preset) => preset.to_string(),
+        }
+    }
+
+    /// Returns the settings the entry plays with.
+    pub fn preset_options(&self) -> BinauralPresetGroup {
+        match self {
+            PresetChoice::Saved(saved) => saved.preset_options,
+            PresetChoice::BuiltIn(preset) => BinauralPresetGroup::from(*preset),
+        }
+    }
+
+    /// Returns the description of the entry's preset, for a saved preset the one it started from.
+    pub fn description(&self) -> &'static str {
+        match self {
+            PresetChoice::Saved(saved) => saved.preset_options.preset.description(),
+            PresetChoice::BuiltIn(preset) => preset.description(),
+        }
+    }
+
+    /// Returns the group of the preset menu the entry is listed under.
+    pub fn menu_group(&self) -> PresetMenuGroup {
+        match self {
+            PresetChoice::Saved(_) => PresetMenuGroup::User,
+            PresetChoice::BuiltIn(preset) => PresetMenuGroup::BuiltIn(preset.category()),
+        }
+    }
+
+    /// Returns true when the text typed into the preset menu is part of the entry's name,
+    /// saved presets also match on the preset they started from.
+    pub fn matches_filter(&self, filter: &str) -> bool {
+        match self {
+            PresetChoice::Saved(saved) => {
+                saved
+                    .name
+                    .to_lowercase()
+                    .contains(&filter.trim().to_lowercase())
+                    || saved.preset_options.preset.matches_filter(filter)
+            }
+            PresetChoice::BuiltIn(preset) => preset.matches_filter(filter),
+        }
+    }
+}
+
+/// This formatter will return the text shown for the entry in the preset menu.
+impl fmt::Display for PresetChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PresetChoice::Saved(saved) => write!(
+                f,
+                "{} (saved, {:.2} Hz / {:.2} Hz, {})",
+                saved.name,
+                saved.preset_options.carrier.to_hz(),
+                saved.preset_options.beat.to_hz(),
+                saved.preset_options.duration
+            ),
+            PresetChoice::BuiltIn(preset) => write!(f, "{}", preset),
+        }
+    }
+}
+
+/// The groups the entries of the preset menu are listed under, the user's saved presets or a category of built in presets.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PresetMenuGroup {
+    User,
+    BuiltIn(PresetCategory),
+}
+
+/// This formatter will return the name of the group.
+impl fmt::Display for PresetMenuGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PresetMenuGroup::User => write!(f, "User"),
+            PresetMenuGroup::BuiltIn(category) => write!(f, "{}", category),
+        }
+    }
+}
+
+/// Represents an entry in the first level of the preset menu, a group and how many entries it has,
+/// or every entry in one list so that they can all be searched at once.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PresetGroupChoice {
+    Group(PresetMenuGroup, usize),
+    All(usize),
+}
+
+impl PresetGroupChoice {
+    /// Returns true when the preset menu entry is listed under this entry.
+    pub fn contains(&self, choice: &PresetChoice) -> bool {
+        match self {
+            PresetGroupChoice::Group(group, _) => choice.menu_group() == *group,
+            PresetGroupChoice::All(_) => true,
+        }
+    }
+}
+
+/// This formatter will return the text shown for the entry in the group menu.
+impl fmt::Display for PresetGroupChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PresetGroupChoice::Group(group, count) => write!(f, "{} ({})", group, count),
+            PresetGroupChoice::All(count) => write!(f, "All presets ({})", count),
+        }
+    }
+}
+
+/// Returns the entries of the group menu for the preset menu entries, the groups that have any entries
+/// in the usual order followed by the entry listing them all. When every entry is in the same group
+/// there is nothing to choose between, so only that group is returned.
+pub fn preset_group_choice_list(choices: &[PresetChoice]) -> Vec<PresetGroupChoice> {
+    let mut group_choices: Vec<PresetGroupChoice> = std::iter::once(PresetMenuGroup::User)
+        .chain(
+            preset_category_list()
+                .into_iter()
+                .map(PresetMenuGroup::BuiltIn),
+        )
+        .filter_map(|group| {
+            let count = choices
+                .iter()
+                .filter(|choice| choice.menu_group() == group)
+                .count();
+            (count > 0).then_some(PresetGroupChoice::Group(group, count))
+        })
+        .collect();
+
+    if group_choices.len() > 1 {
+        group_choices.push(PresetGroupChoice::All(choices.len()));
+    }
+
+    group_choices
+}
+
+/// Returns the entries of the preset menu, the saved presets whose starting preset carries every tag first,
+/// followed by the built in presets.
+pub fn preset_choice_list(saved: &[SavedPreset], presets: &[Preset]) -> Vec<PresetChoice> {
+    saved
+        .iter()
+        .filter(|saved| presets.contains(&saved.preset_options.preset))
+        .cloned()
+        .map(PresetChoice::Saved)
+        .chain(presets.iter().copied().map(PresetChoice::BuiltIn))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn saved_preset(name: &str, minutes: u32) -> SavedPreset {
+        SavedPreset {
+            name: name.to_string(),
+            preset_options: BinauralPresetGroup {
+                duration: Duration::from_minutes(minutes),
+                ..BinauralPresetGroup::from(Preset::SolfeggioHeart)
+            },
+        }
+    }
+
+    fn temp_store_path() -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("bb-saved-presets-{}", uuid::Uuid::new_v4()))
+            .join(SAVED_PRESETS_FILE)
+    }
+
+    #[test]
+    fn saved_preset_to_json() {
+        assert_eq!(
+            saved_preset("Evening", 45).to_json(),
+            json!({
+                "name": "Evening",
+                "preset": "solfeggio-heart",
+                "carrier_hz": 639.0,
+                "beat_hz": 10.0,
+                "minutes": 45,
+            })
+        );
+    }
+
+    #[test]
+    fn saved_preset_round_trips_through_json() {
+        let saved = saved_preset("Evening", 45);
+
+        assert_eq!(SavedPreset::from_json(&saved.to_json()), Some(saved));
+    }
+
+    #[test]
+    fn saved_preset_from_json_keeps_changed_frequencies_as_custom() {
+        let saved = SavedPreset::from_json(&json!({
+            "name": "Low heart",
+            "preset": "solfeggio-heart",
+            "carrier_hz": 432.0,
+            "beat_hz": 10.0,
+            "minutes": 30,
+        }))
+        .unwrap();
+
+        assert_eq!(
+            saved.preset_options.carrier,
+            CarrierFrequency::Custom(432.0)
+        );
+        assert_eq!(saved.preset_options.beat, BeatFrequency::Alpha);
+        assert_eq!(saved.preset_options.duration, Duration::ThirtyMinutes);
+    }
+
+    macro_rules! test_saved_preset_from_json_rejects_cases {
+        ($($name:ident:($value:expr),)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    assert_eq!(SavedPreset::from_json(&$value), None)
+                }
+            )*
+        };
+    }
+
+    test_saved_preset_from_json_rejects_cases! {
+        saved_preset_without_name: (json!({"name": " ", "preset": "focus", "carrier_hz": 400.0, "beat_hz": 20.0, "minutes": 30})),
+        saved_preset_with_unknown_preset: (json!({"name": "Nap", "preset": "nap", "carrier_hz": 400.0, "beat_hz": 20.0, "minutes": 30})),
+        saved_preset_with_zero_minutes: (json!({"name": "Nap", "preset": "focus", "carrier_hz": 400.0, "beat_hz": 20.0, "minutes": 0})),
+        saved_preset_with_missing_carrier: (json!({"name": "Nap", "preset": "focus", "beat_hz": 20.0, "minutes": 30})),
+    }
+
+    macro_rules! test_name_conflict_cases {
+        ($($name:ident:($text:expr, $expected:expr),)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let store = SavedPresetStore {
+                        path: temp_store_path(),
+                        presets: vec![saved_preset("Evening Wind Down", 45)],
+                    };
+
+                    assert_eq!(store.name_conflict($text), $expected)
+                }
+            )*
+        };
+    }
+
+    test_name_conflict_cases! {
+        name_conflict_free: ("Morning", None),
+        name_conflict_empty: ("  ", Some(NameConflict::Empty)),
+        name_conflict_built_in: ("deep relaxation", Some(NameConflict::BuiltIn)),
+        name_conflict_built_in_full_name: ("Solfeggio Heart Chakra", Some(NameConflict::BuiltIn)),
+        name_conflict_saved: ("evening-wind-down", Some(NameConflict::Saved)),
+    }
+
+    #[test]
+    fn store_saves_and_reloads_presets() {
+        let path = temp_store_path();
+        let mut store = SavedPresetStore::open(path.clone()).unwrap();
+        assert!(store.presets().is_empty());
+
+        store.save(saved_preset("Evening", 45)).unwrap();
+        store.save(saved_preset("Morning", 20)).unwrap();
+        // Saving under a taken name replaces the old preset and moves it to the top.
+        store.save(saved_preset("evening", 50)).unwrap();
+
+        let reloaded = SavedPresetStore::open(path.clone()).unwrap();
+        assert_eq!(
+            reloaded.presets(),
+            &[saved_preset("evening", 50), saved_preset("Morning", 20)]
+        );
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn store_refuses_a_file_that_is_not_json() {
+        let path = temp_store_path();
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "not json").unwrap();
+
+        assert!(SavedPresetStore::open(path.clone()).is_err());
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn store_reads_files_written_before_versions() {
+        let path = temp_store_path();
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(
+            &path,
+            json!({ "presets": [saved_preset("Evening", 45).to_json()] }).to_string(),
+        )
+        .unwrap();
+
+        let store = SavedPresetStore::open(path.clone()).unwrap();
+        store.write().unwrap();
+        let written: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+        assert_eq!(store.presets(), &[saved_preset("Evening", 45)]);
+        assert_eq!(written["version"], 1);
+    }
+
+    #[test]
+    fn store_refuses_a_file_from_a_newer_version() {
+        let path = temp_store_path();
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, json!({ "version": 99, "presets": [] }).to_string()).unwrap();
+
+        let err = SavedPresetStore::open(path.clone()).unwrap_err();
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+        assert!(err.to_string().contains("newer version"));
+    }
+
+    #[test]
+    fn preset_choice_list_puts_saved_presets_first() {
+        let saved = vec![saved_preset("Evening", 45)];
+        let choices = preset_choice_list(&saved, &[Preset::Focus, Preset::SolfeggioHeart]);
+
+        assert_eq!(
+            choices,
+            vec![
+                PresetChoice::Saved(saved_preset("Evening", 45)),
+                PresetChoice::BuiltIn(Preset::Focus),
+                PresetChoice::BuiltIn(Preset::SolfeggioHeart),
+            ]
+        );
+    }
+
+    #[test]
+    fn preset_choice_list_leaves_out_saved_presets_filtered_by_tag() {
+        let saved = vec![saved_preset("Evening", 45)];
+        let choices = preset_choice_list(&saved, &[Preset::Focus]);
+
+        assert_eq!(choices, vec![PresetChoice::BuiltIn(Preset::Focus)]);
+    }
+
+    #[test]
+    fn saved_preset_choice_text() {
+        assert_eq!(
+            PresetChoice::Saved(saved_preset("Evening", 45)).to_string(),
+            "Evening (saved, 639.00 Hz / 10.00 Hz, 45 min)"
+        );
+    }
+
+    #[test]
+    fn group_choice_list_counts_the_entries_of_each_group() {
+        let saved = vec![saved_preset("Evening", 45)];
+        let choices = preset_choice_list(
+            &saved,
+            &[Preset::Focus, Preset::Sleep, Preset::SolfeggioHeart],
+        );
+
+        assert_eq!(
+            preset_group_choice_list(&choices),
+            vec![
+                PresetGroupChoice::Group(PresetMenuGroup::User, 1),
+                PresetGroupChoice::Group(PresetMenuGroup::BuiltIn(PresetCategory::General), 2),
+                PresetGroupChoice::Group(PresetMenuGroup::BuiltIn(PresetCategory::Solfeggio), 1),
+                PresetGroupChoice::All(4),
+            ]
+        );
+    }
+
+    #[test]
+    fn group_choice_list_with_one_group_has_nothing_to_choose() {
+        let choices = preset_choice_list(&[], &[Preset::SolfeggioRoot, Preset::SolfeggioHeart]);
+
+        assert_eq!(
+            preset_group_choice_list(&choices),
+            vec![PresetGroupChoice::Group(
+                PresetMenuGroup::BuiltIn(PresetCategory::Solfeggio),
+                2
+            )]
+        );
+    }
+
+    #[test]
+    fn group_choice_contains_the_entries_of_its_group() {
+        let saved = PresetChoice::Saved(saved_preset("Evening", 45));
+        let crown = PresetChoice::BuiltIn(Preset::CrownSleep);
+        let user = PresetGroupChoice::Group(PresetMenuGroup::User, 1);
+
+        assert!(user.contains(&saved));
+        assert!(!user.contains(&crown));
+        assert!(PresetGroupChoice::All(2).contains(&crown));
+    }
+
+    #[test]
+    fn group_choice_text() {
+        assert_eq!(
+            PresetGroupChoice::Group(PresetMenuGroup::BuiltIn(PresetCategory::TuningFork), 7)
+                .to_string(),
+            "Tuning Fork (7)"
+        );
+        assert_eq!(PresetGroupChoice::All(33).to_string(), "All presets (33)");
+    }
+
+    #[test]
+    fn saved_preset_choice_is_described_by_its_starting_preset() {
+        assert_eq!(
+            PresetChoice::Saved(saved_preset("Evening", 45)).description(),
+            Preset::SolfeggioHeart.description()
+        );
+    }
+}
@@ -0,0 +1,233 @@
+//! A module that contains the checks behind the `check` command, which verify an install without starting a session.
+
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::modules::bb_generator::render_samples;
+use crate::modules::output::cpal_output::{default_output_device_name, output_device_names};
+use crate::modules::preset::{BinauralPresetGroup, Preset, preset_list};
+use crate::modules::saved_preset::{SavedPresetStore, saved_presets_path};
+
+/// The sample rate the offline rendering check renders at.
+const CHECK_SAMPLE_RATE: u32 = 48_000;
+
+/// The outcome of one check, with a line describing what was found either way.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckReport {
+    /// What was checked, like `Presets`.
+    pub name: &'static str,
+    /// What the check found when it passed, or why it failed.
+    pub result: Result<String, String>,
+}
+
+impl CheckReport {
+    /// Returns true when the check passed.
+    pub fn passed(&self) -> bool {
+        self.result.is_ok()
+    }
+}
+
+impl fmt::Display for CheckReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.result {
+            Ok(details) => write!(f, "[ ok ] {}: {}", self.name, details),
+            Err(details) => write!(f, "[fail] {}: {}", self.name, details),
+        }
+    }
+}
+
+/// Runs every check in turn, leaving out the output device unless `include_audio` is set,
+/// so that an install can be verified on a machine without audio hardware.
+pub fn run_checks(include_audio: bool) -> Vec<CheckReport> {
+    let mut reports = vec![
+        CheckReport {
+            name: "Presets",
+            result: check_presets(),
+        },
+        CheckReport {
+            name: "Saved presets",
+            result: check_saved_presets(saved_presets_path()),
+        },
+        CheckReport {
+            name: "Offline rendering",
+            result: check_rendering(),
+        },
+    ];
+
+    if include_audio {
+        reports.push(CheckReport {
+            name: "Output device",
+            result: check_output_device(),
+        });
+    }
+
+    reports
+}
+
+/// Checks that every preset can be picked by its name and has settings that can be played.
+fn check_presets() -> Result<String, String> {
+    let presets = preset_list();
+
+    for preset in &presets {
+        if preset.slug().parse::<Preset>() != Ok(*preset) {
+            return Err(format!(
+                "{} can't be picked by its name {}.",
+                preset,
+                preset.slug()
+            ));
+        }
+
+        render_samples(BinauralPresetGroup::from(*preset), CHECK_SAMPLE_RATE, 1)
+            .map_err(|err| format!("{} can't be played: {}", preset, err))?;
+    }
+
+    Ok(format!(
+        "{} presets resolve and can be played",
+        presets.len()
+    ))
+}
+
+/// Checks that the saved presets file, if there is one yet, can be read.
+fn check_saved_presets(path: Option<PathBuf>) -> Result<String, String> {
+    let path = path.ok_or_else(|| "The config directory can't be found.".to_string())?;
+
+    if !path.exists() {
+        return Ok(format!("none saved yet, they go in {}", path.display()));
+    }
+
+    let store = SavedPresetStore::open(path).map_err(|err| err.to_string())?;
+
+    Ok(format!(
+        "{} read from {}",
+        store.presets().len(),
+        store.path().display()
+    ))
+}
+
+/// Checks that one second of a session renders to the expected number of samples at a sensible level.
+fn check_rendering() -> Result<String, String> {
+    let preset = Preset::Focus;
+    let samples = render_samples(
+        BinauralPresetGroup::from(preset),
+        CHECK_SAMPLE_RATE,
+        CHECK_SAMPLE_RATE as usize,
+    )
+    .map_err(|err| err.to_string())?;
+
+    if samples.len() != CHECK_SAMPLE_RATE as usize * 2 {
+        return Err(format!(
+            "Rendered {} samples instead of {}.",
+            samples.len(),
+            CHECK_SAMPLE_RATE * 2
+        ));
+    }
+
+    if let Some(sample) = samples.iter().find(|sample| !sample.is_finite()) {
+        return Err(format!(
+            "Rendered a sample that isn't a number: {}.",
+            sample
+        ));
+    }
+
+    let peak = samples
+        .iter()
+        .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+    if peak == 0.0 || peak > 1.0 {
+        return Err(format!(
+            "The rendered peak level is {:.3}, outside of the playable range.",
+            peak
+        ));
+    }
+
+    Ok(format!(
+        "one second of {} at {} Hz, peak level {:.3}",
+        preset, CHECK_SAMPLE_RATE, peak
+    ))
+}
+
+/// Checks that the default host has a default output device to play through.
+fn check_output_device() -> Result<String, String> {
+    let device_count = output_device_names().map_err(|err| err.to_string())?.len();
+    let default_name =
+        default_output_device_name().ok_or_else(|| "No default output device.".to_string())?;
+
+    Ok(format!(
+        "{} is the default of {} output devices",
+        default_name, device_count
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs;
+
+    fn temp_store_path() -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("bb-self-check-{}", uuid::Uuid::new_v4()))
+            .join("presets.json")
+    }
+
+    #[test]
+    fn presets_pass_the_check() {
+        assert_eq!(
+            check_presets(),
+            Ok(format!(
+                "{} presets resolve and can be played",
+                preset_list().len()
+            ))
+        );
+    }
+
+    #[test]
+    fn rendering_passes_the_check() {
+        assert_eq!(
+            check_rendering(),
+            Ok("one second of Focus at 48000 Hz, peak level 0.500".to_string())
+        );
+    }
+
+    #[test]
+    fn missing_saved_presets_file_passes_the_check() {
+        assert!(check_saved_presets(Some(temp_store_path())).is_ok());
+    }
+
+    #[test]
+    fn unreadable_saved_presets_file_fails_the_check() {
+        let path = temp_store_path();
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "not json").unwrap();
+
+        let result = check_saved_presets(Some(path.clone()));
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn missing_config_directory_fails_the_check() {
+        assert!(check_saved_presets(None).is_err());
+    }
+
+    #[test]
+    fn checks_without_audio_leave_out_the_device() {
+        let reports = run_checks(false);
+
+        assert!(reports.iter().all(|report| report.name != "Output device"));
+    }
+
+    #[test]
+    fn report_shows_whether_the_check_passed() {
+        let passed = CheckReport {
+            name: "Presets",
+            result: Ok("32 presets".to_string()),
+        };
+        let failed = CheckReport {
+            name: "Presets",
+            result: Err("broken".to_string()),
+        };
+
+        assert_eq!(passed.to_string(), "[ ok ] Presets: 32 presets");
+        assert_eq!(failed.to_string(), "[fail] Presets: broken");
+    }
+}
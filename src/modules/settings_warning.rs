@@ -0,0 +1,185 @@
+//! A module that contains the warnings about settings that can be played but are unlikely to work as intended,
+//! like the custom frequencies of a hand edited saved preset.
+
+use std::fmt;
+
+use crate::modules::bb_generator::{BeatMode, PlaybackOptions};
+use crate::modules::frequency::frequency_common::ToFrequency;
+use crate::modules::preset::BinauralPresetGroup;
+
+/// The highest beat frequency with reasonable evidence of entrainment, the top of the Gamma presets.
+pub const MAX_ENTRAINING_BEAT_HZ: f32 = 40.0;
+
+/// The lowest carrier frequency that most headphones can reproduce.
+pub const MIN_REPRODUCIBLE_CARRIER_HZ: f32 = 30.0;
+
+/// The highest carrier frequency at which the ears still perceive a binaural beat.
+pub const MAX_PERCEIVED_CARRIER_HZ: f32 = 1_500.0;
+
+/// A setting outside of the range where binaural beats are known to work, with the value that was found.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SettingsWarning {
+    /// The beat frequency is above the range where entrainment has been shown.
+    HighBeat(f32),
+    /// The beat frequency the session ramps to is above the range where entrainment has been shown.
+    HighRampBeat(f32),
+    /// The carrier frequency is too low for most headphones to reproduce.
+    LowCarrier(f32),
+    /// The carrier frequency is too high for a binaural beat to be perceived.
+    HighCarrier(f32),
+}
+
+/// This formatter returns the warning as a sentence to show the user.
+impl fmt::Display for SettingsWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsWarning::HighBeat(hz) => write!(
+                f,
+                "The beat frequency of {:.2} Hz is above {} Hz, where the evidence for entrainment is weak.",
+                hz, MAX_ENTRAINING_BEAT_HZ
+            ),
+            SettingsWarning::HighRampBeat(hz) => write!(
+                f,
+                "The ramp goes to a beat frequency of {:.2} Hz, above {} Hz where the evidence for entrainment is weak.",
+                hz, MAX_ENTRAINING_BEAT_HZ
+            ),
+            SettingsWarning::LowCarrier(hz) => write!(
+                f,
+                "The carrier frequency of {:.2} Hz is below {} Hz, which most headphones can't reproduce.",
+                hz, MIN_REPRODUCIBLE_CARRIER_HZ
+            ),
+            SettingsWarning::HighCarrier(hz) => write!(
+                f,
+                "The carrier frequency of {:.2} Hz is above {} Hz, where binaural beats aren't perceived.",
+                hz, MAX_PERCEIVED_CARRIER_HZ
+            ),
+        }
+    }
+}
+
+/// Returns a warning for every setting of the session that is unusual, in the order they are listed above.
+/// Settings that can't be played at all, like a zero frequency, are errors when the session starts instead.
+/// The beat isn't played in bilateral mode, so it isn't checked there.
+pub fn settings_warnings(
+    preset_options: BinauralPresetGroup,
+    playback_options: &PlaybackOptions,
+) -> Vec<SettingsWarning> {
+    let carrier_hz = preset_options.carrier.to_hz();
+    let beat_hz = preset_options.beat.to_hz();
+    let mut warnings = Vec::new();
+
+    if playback_options.beat_mode == BeatMode::Binaural {
+        if beat_hz > MAX_ENTRAINING_BEAT_HZ {
+            warnings.push(SettingsWarning::HighBeat(beat_hz));
+        }
+        if let Some(ramp_to_hz) = playback_options.ramp_to_hz
+            && ramp_to_hz > MAX_ENTRAINING_BEAT_HZ
+        {
+            warnings.push(SettingsWarning::HighRampBeat(ramp_to_hz));
+        }
+    }
+
+    if carrier_hz < MIN_REPRODUCIBLE_CARRIER_HZ {
+        warnings.push(SettingsWarning::LowCarrier(carrier_hz));
+    }
+    if carrier_hz > MAX_PERCEIVED_CARRIER_HZ {
+        warnings.push(SettingsWarning::HighCarrier(carrier_hz));
+    }
+
+    warnings
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::modules::duration::duration::Duration;
+    use crate::modules::frequency::beat_frequency::BeatFrequency;
+    use crate::modules::frequency::carrier_frequency::CarrierFrequency;
+    use crate::modules::preset::{Preset, preset_list};
+
+    fn preset_group(carrier_hz: f32, beat_hz: f32) -> BinauralPresetGroup {
+        BinauralPresetGroup {
+            preset: Preset::Focus,
+            carrier: CarrierFrequency::Custom(carrier_hz),
+            beat: BeatFrequency::Custom(beat_hz),
+            duration: Duration::FiveMinutes,
+        }
+    }
+
+    macro_rules! test_settings_warnings_cases {
+        ($($name:ident:($carrier_hz:expr, $beat_hz:expr, $expected:expr),)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    assert_eq!(
+                        settings_warnings(preset_group($carrier_hz, $beat_hz), &PlaybackOptions::default()),
+                        $expected
+                    )
+                }
+            )*
+        };
+    }
+
+    test_settings_warnings_cases! {
+        settings_warnings_usual_values: (400.0, 10.0, vec![]),
+        settings_warnings_beat_at_the_limit: (400.0, 40.0, vec![]),
+        settings_warnings_high_beat: (400.0, 60.0, vec![SettingsWarning::HighBeat(60.0)]),
+        settings_warnings_low_carrier: (20.0, 4.0, vec![SettingsWarning::LowCarrier(20.0)]),
+        settings_warnings_carrier_at_the_low_limit: (30.0, 4.0, vec![]),
+        settings_warnings_high_carrier: (2_000.0, 10.0, vec![SettingsWarning::HighCarrier(2_000.0)]),
+        settings_warnings_high_beat_and_carrier: (
+            1_600.0,
+            50.0,
+            vec![SettingsWarning::HighBeat(50.0), SettingsWarning::HighCarrier(1_600.0)]
+        ),
+    }
+
+    #[test]
+    fn built_in_presets_have_no_warnings() {
+        for preset in preset_list() {
+            assert_eq!(
+                settings_warnings(
+                    BinauralPresetGroup::from(preset),
+                    &PlaybackOptions::default()
+                ),
+                vec![],
+                "{}",
+                preset
+            );
+        }
+    }
+
+    #[test]
+    fn high_ramp_beat_is_warned_about() {
+        let playback_options = PlaybackOptions {
+            ramp_to_hz: Some(80.0),
+            ..PlaybackOptions::default()
+        };
+
+        assert_eq!(
+            settings_warnings(preset_group(400.0, 10.0), &playback_options),
+            vec![SettingsWarning::HighRampBeat(80.0)]
+        );
+    }
+
+    #[test]
+    fn bilateral_mode_leaves_out_the_beat() {
+        let playback_options = PlaybackOptions {
+            beat_mode: BeatMode::Bilateral { rate_hz: 1.0 },
+            ..PlaybackOptions::default()
+        };
+
+        assert_eq!(
+            settings_warnings(preset_group(20.0, 60.0), &playback_options),
+            vec![SettingsWarning::LowCarrier(20.0)]
+        );
+    }
+
+    #[test]
+    fn warning_text() {
+        assert_eq!(
+            SettingsWarning::HighCarrier(2_000.0).to_string(),
+            "The carrier frequency of 2000.00 Hz is above 1500 Hz, where binaural beats aren't perceived."
+        );
+    }
+}
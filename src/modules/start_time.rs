@@ -0,0 +1,229 @@
+//! A module that contains the wait before a session that starts at a set time of day.
+
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+use std::time::Duration as StdDuration;
+
+use anyhow::Error;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::terminal;
+use jiff::Zoned;
+use jiff::civil::Time;
+
+use crate::modules::parse::ParseValueError;
+use crate::modules::progress::format_clock;
+
+/// How often the countdown is redrawn, which is also how quickly a key press cancels the wait.
+const COUNTDOWN_POLL_INTERVAL: StdDuration = StdDuration::from_millis(200);
+
+/// A time of day on the 24 hour clock to start the session at, like 22:30.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StartTime {
+    hour: i8,
+    minute: i8,
+}
+
+impl StartTime {
+    /// Returns the time of day, or `None` when the hour or minute is out of range.
+    pub fn new(hour: i8, minute: i8) -> Option<Self> {
+        ((0..24).contains(&hour) && (0..60).contains(&minute)).then_some(StartTime { hour, minute })
+    }
+
+    /// Returns the next moment the clock shows this time in the time zone of `now`,
+    /// which is tomorrow when the time has already passed today.
+    /// A time skipped by a daylight saving change starts as soon as the clock has jumped past it.
+    pub fn next_after(&self, now: &Zoned) -> Result<Zoned, Error> {
+        let time = Time::constant(self.hour, self.minute, 0, 0);
+
+        let today = now
+            .date()
+            .to_datetime(time)
+            .to_zoned(now.time_zone().clone())?;
+        if today >= *now {
+            return Ok(today);
+        }
+
+        Ok(now
+            .date()
+            .tomorrow()?
+            .to_datetime(time)
+            .to_zoned(now.time_zone().clone())?)
+    }
+}
+
+impl fmt::Display for StartTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}:{:02}", self.hour, self.minute)
+    }
+}
+
+impl FromStr for StartTime {
+    type Err = ParseValueError;
+
+    /// Reads a time such as `22:30` or `7:05`.
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let error = || ParseValueError::new("start time", text);
+
+        let (hour, minute) = text.trim().split_once(':').ok_or_else(error)?;
+        if hour.is_empty() || hour.len() > 2 || minute.len() != 2 {
+            return Err(error());
+        }
+
+        StartTime::new(
+            hour.parse().map_err(|_| error())?,
+            minute.parse().map_err(|_| error())?,
+        )
+        .ok_or_else(error)
+    }
+}
+
+/// How the wait for the start time ended.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WaitOutcome {
+    /// The start time was reached and the session should start.
+    Reached,
+    /// The user cancelled the wait, the session shouldn't start.
+    Cancelled,
+}
+
+/// Returns how long is left from `now` until `start_at`, which is zero once the time has come.
+pub fn time_until(start_at: &Zoned, now: &Zoned) -> StdDuration {
+    StdDuration::try_from(start_at.timestamp().duration_since(now.timestamp())).unwrap_or_default()
+}
+
+/// Waits until the clock next shows the start time, counting down on one line.
+/// Enter, Esc or Ctrl+C cancel the wait.
+pub fn wait_for_start(start_time: StartTime) -> Result<WaitOutcome, Error> {
+    let start_at = start_time.next_after(&Zoned::now())?;
+
+    println!(
+        "The session starts at {} on {}. Press Enter or Esc to cancel.",
+        start_time,
+        start_at.date()
+    );
+
+    terminal::enable_raw_mode()?;
+    let outcome = count_down(&start_at);
+    terminal::disable_raw_mode()?;
+    println!();
+
+    outcome
+}
+
+/// Redraws the time left until the start, reading the clock each time so that a machine that slept
+/// through part of the wait still starts on time.
+fn count_down(start_at: &Zoned) -> Result<WaitOutcome, Error> {
+    loop {
+        let remaining = time_until(start_at, &Zoned::now());
+        if remaining.is_zero() {
+            return Ok(WaitOutcome::Reached);
+        }
+
+        // Raw mode leaves the cursor where it is, so go back to the start of the line before redrawing.
+        print!("\r\x1b[KStarting in {}", format_clock(remaining));
+        io::stdout().flush()?;
+
+        if !event::poll(remaining.min(COUNTDOWN_POLL_INTERVAL))? {
+            continue;
+        }
+
+        if let Event::Key(key_event) = event::read()?
+            && key_event.kind == KeyEventKind::Press
+        {
+            match key_event.code {
+                KeyCode::Enter | KeyCode::Esc => return Ok(WaitOutcome::Cancelled),
+                // Raw mode swallows Ctrl+C, so treat it like Esc.
+                KeyCode::Char('c') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                    return Ok(WaitOutcome::Cancelled);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use jiff::tz::TimeZone;
+
+    /// A time zone with the United States daylight saving rules, which don't need the time zone database.
+    fn eastern() -> TimeZone {
+        TimeZone::posix("EST5EDT,M3.2.0,M11.1.0").unwrap()
+    }
+
+    /// Returns the moment the clock shows the given date and time in the eastern time zone.
+    fn at(date_time: &str) -> Zoned {
+        date_time
+            .parse::<jiff::civil::DateTime>()
+            .unwrap()
+            .to_zoned(eastern())
+            .unwrap()
+    }
+
+    macro_rules! test_parse_start_time_cases {
+        ($($name:ident:($text:expr, $expected:expr),)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let expected: Option<StartTime> = $expected;
+                    assert_eq!($text.parse::<StartTime>().ok(), expected)
+                }
+            )*
+        };
+    }
+
+    macro_rules! test_next_start_cases {
+        ($($name:ident:($start_time:expr, $now:expr, $expected:expr, $minutes_left:expr),)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let now = at($now);
+                    let start_at = $start_time.next_after(&now).unwrap();
+                    assert_eq!(start_at, at($expected));
+                    assert_eq!(
+                        time_until(&start_at, &now),
+                        StdDuration::from_secs($minutes_left * 60)
+                    );
+                }
+            )*
+        };
+    }
+
+    test_parse_start_time_cases! {
+        parse_start_time_evening: ("22:30", StartTime::new(22, 30)),
+        parse_start_time_single_digit_hour: ("7:05", StartTime::new(7, 5)),
+        parse_start_time_midnight: ("00:00", StartTime::new(0, 0)),
+        parse_start_time_padded: (" 23:59 ", StartTime::new(23, 59)),
+        parse_start_time_hour_out_of_range: ("24:00", None),
+        parse_start_time_minute_out_of_range: ("22:60", None),
+        parse_start_time_short_minute: ("22:5", None),
+        parse_start_time_without_minutes: ("22", None),
+        parse_start_time_negative: ("-1:30", None),
+        parse_start_time_text: ("bedtime", None),
+    }
+
+    test_next_start_cases! {
+        next_start_later_today: (StartTime::new(22, 30).unwrap(), "2026-06-01T20:00", "2026-06-01T22:30", 150),
+        next_start_now: (StartTime::new(22, 30).unwrap(), "2026-06-01T22:30", "2026-06-01T22:30", 0),
+        next_start_tomorrow_once_passed: (StartTime::new(22, 30).unwrap(), "2026-06-01T23:00", "2026-06-02T22:30", 23 * 60 + 30),
+        next_start_after_midnight: (StartTime::new(0, 15).unwrap(), "2026-06-01T23:45", "2026-06-02T00:15", 30),
+        next_start_across_spring_forward: (StartTime::new(22, 30).unwrap(), "2026-03-07T23:00", "2026-03-08T22:30", 22 * 60 + 30),
+        next_start_across_fall_back: (StartTime::new(22, 30).unwrap(), "2026-10-31T23:00", "2026-11-01T22:30", 24 * 60 + 30),
+        next_start_in_the_skipped_hour: (StartTime::new(2, 30).unwrap(), "2026-03-08T01:00", "2026-03-08T03:30", 90),
+    }
+
+    #[test]
+    fn start_time_is_shown_on_the_24_hour_clock() {
+        assert_eq!(StartTime::new(7, 5).unwrap().to_string(), "07:05");
+    }
+
+    #[test]
+    fn time_until_a_passed_start_is_zero() {
+        assert_eq!(
+            time_until(&at("2026-06-01T22:30"), &at("2026-06-01T22:31")),
+            StdDuration::ZERO
+        );
+    }
+}
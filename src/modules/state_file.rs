@@ -0,0 +1,218 @@
+//! A module that contains the code shared by the files the program keeps between runs, like the saved presets.
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::Error;
+use serde_json::{Value, json};
+
+/// Upgrades the JSON of a file, or of one entry in it, from the schema version before to the next one.
+pub(crate) type Migration = fn(&mut Value);
+
+/// Brings JSON written by any earlier version of the program up to the current schema version,
+/// which is one more than the number of migrations, and stamps it with that version.
+/// JSON without a version was written before versions were added and counts as version 1.
+/// JSON from a newer version of the program is an error, since reading it could lose what that version added.
+pub(crate) fn migrate(
+    mut value: Value,
+    migrations: &[Migration],
+    source: &str,
+) -> Result<Value, Error> {
+    let current_version = migrations.len() as u64 + 1;
+    let version = match &value["version"] {
+        Value::Null => 1,
+        version => version
+            .as_u64()
+            .filter(|version| *version >= 1)
+            .ok_or_else(|| {
+                anyhow::anyhow!("{} has an invalid schema version: {}", source, version)
+            })?,
+    };
+
+    if version > current_version {
+        return Err(anyhow::anyhow!(
+            "{} was written by a newer version of the program (schema version {}, this version reads up to {}). Update the program to use it.",
+            source,
+            version,
+            current_version
+        ));
+    }
+
+    for migration in &migrations[(version - 1) as usize..] {
+        migration(&mut value);
+    }
+    value["version"] = json!(current_version);
+
+    Ok(value)
+}
+
+/// Writes the contents to the file without ever leaving it half written.
+/// The contents go to a temporary file next to it first, which then replaces the file in one step,
+/// so a crash or a full disk mid-write leaves the previous version in place.
+/// The file's directory is created when needed.
+pub(crate) fn write_atomically(path: &Path, contents: &str) -> Result<(), Error> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    fs::create_dir_all(dir)
+        .map_err(|err| anyhow::anyhow!("{} can't be created: {}", dir.display(), err))?;
+
+    let temp_path = temp_path_for(path);
+    let result = write_and_sync(&temp_path, contents).and_then(|()| fs::rename(&temp_path, path));
+
+    if let Err(err) = result {
+        // The temporary file is only clutter once the write has failed.
+        let _ = fs::remove_file(&temp_path);
+        return Err(anyhow::anyhow!(
+            "{} can't be written: {}",
+            path.display(),
+            err
+        ));
+    }
+
+    sync_dir(dir);
+    Ok(())
+}
+
+/// Returns a hidden file name in the same directory, so that the rename stays on one file system.
+fn temp_path_for(path: &Path) -> PathBuf {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    path.with_file_name(format!(".{}.{}.tmp", file_name, uuid::Uuid::new_v4()))
+}
+
+/// Writes the contents and waits until they reach the disk, so the rename can't get ahead of them.
+fn write_and_sync(path: &Path, contents: &str) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()
+}
+
+/// Makes the rename itself survive a crash on platforms where directories can be synced.
+/// Failing here doesn't undo the write, so it's only tried.
+fn sync_dir(dir: &Path) {
+    #[cfg(unix)]
+    if let Ok(dir) = File::open(dir) {
+        let _ = dir.sync_all();
+    }
+    #[cfg(not(unix))]
+    let _ = dir;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Version 2 renamed `minutes` to `duration_minutes`, version 3 added `volume`.
+    const TEST_MIGRATIONS: &[Migration] = &[
+        |value| {
+            let minutes = value
+                .as_object_mut()
+                .and_then(|object| object.remove("minutes"));
+            value["duration_minutes"] = minutes.unwrap_or_default();
+        },
+        |value| value["volume"] = json!(1.0),
+    ];
+
+    macro_rules! test_migrate_cases {
+        ($($name:ident:($value:expr, $expected:expr),)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    assert_eq!(migrate($value, TEST_MIGRATIONS, "state.json").ok(), $expected)
+                }
+            )*
+        };
+    }
+
+    test_migrate_cases! {
+        migrate_unversioned_as_version_1: (
+            json!({"minutes": 30}),
+            Some(json!({"version": 3, "duration_minutes": 30, "volume": 1.0}))
+        ),
+        migrate_from_version_2: (
+            json!({"version": 2, "duration_minutes": 30}),
+            Some(json!({"version": 3, "duration_minutes": 30, "volume": 1.0}))
+        ),
+        migrate_current_version_unchanged: (
+            json!({"version": 3, "duration_minutes": 30, "volume": 0.5}),
+            Some(json!({"version": 3, "duration_minutes": 30, "volume": 0.5}))
+        ),
+        migrate_newer_version: (json!({"version": 4, "duration_minutes": 30}), None),
+        migrate_version_0: (json!({"version": 0}), None),
+        migrate_version_as_text: (json!({"version": "2"}), None),
+    }
+
+    #[test]
+    fn migrate_without_migrations_stamps_version_1() {
+        assert_eq!(
+            migrate(json!({"presets": []}), &[], "presets.json").unwrap(),
+            json!({"version": 1, "presets": []})
+        );
+    }
+
+    #[test]
+    fn newer_version_error_names_both_versions() {
+        let err = migrate(json!({"version": 4}), TEST_MIGRATIONS, "state.json").unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "state.json was written by a newer version of the program (schema version 4, this version reads up to 3). Update the program to use it."
+        );
+    }
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("bb-state-file-{}", uuid::Uuid::new_v4()))
+    }
+
+    /// Returns the names of the files in the directory.
+    fn file_names(dir: &Path) -> Vec<String> {
+        fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn atomic_write_creates_the_file_and_its_directory() {
+        let dir = temp_dir();
+        let path = dir.join("nested").join("state.json");
+
+        write_atomically(&path, "{}\n").unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{}\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn atomic_write_replaces_the_file_without_leaving_temporary_files() {
+        let dir = temp_dir();
+        let path = dir.join("state.json");
+
+        write_atomically(&path, "first").unwrap();
+        write_atomically(&path, "second").unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+        assert_eq!(file_names(&dir), vec!["state.json".to_string()]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn failed_atomic_write_cleans_up_after_itself() {
+        let dir = temp_dir();
+        // A directory in the file's place makes the final rename fail.
+        let path = dir.join("state.json");
+        fs::create_dir_all(path.join("in-the-way")).unwrap();
+
+        assert!(write_atomically(&path, "lost").is_err());
+
+        assert_eq!(file_names(&dir), vec!["state.json".to_string()]);
+        assert!(path.join("in-the-way").is_dir());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,361 @@
+//! A module that contains code related to looping a recorded soundscape, like rain or the ocean, underneath the tones.
+
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+
+use anyhow::Error;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::DecoderOptions;
+use symphonia::core::errors::Error as DecodeError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+/// How long the end of the recording is blended into its start, so that the loop has no seam to click at.
+const LOOP_CROSSFADE: StdDuration = StdDuration::from_millis(500);
+
+/// The longest recording that can be looped. Every frame is kept in memory, about 200 MB of it at this length.
+const MAX_AMBIENT_LENGTH: StdDuration = StdDuration::from_secs(10 * 60);
+
+/// A recording decoded into stereo frames and blended into a loop, a mono recording plays the same in both ears.
+/// It is prepared once when it is loaded, every session that plays it shares the same frames.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AmbientSound {
+    sample_rate: u32,
+    frames: Vec<(f32, f32)>,
+}
+
+impl AmbientSound {
+    /// Returns the recording ready to loop, with its end blended into its start.
+    pub fn new(sample_rate: u32, frames: Vec<(f32, f32)>) -> Self {
+        let crossfade_frames = (LOOP_CROSSFADE.as_secs_f64() * sample_rate as f64) as usize;
+
+        AmbientSound {
+            sample_rate,
+            frames: looped(frames, crossfade_frames),
+        }
+    }
+
+    /// Decodes a WAV or FLAC file. Only the first two channels of a recording with more are played.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        decode(path).map_err(|err| anyhow::anyhow!("{} can't be played: {}", path.display(), err))
+    }
+
+    /// Returns how long the recording plays for before it loops.
+    pub fn length(&self) -> StdDuration {
+        StdDuration::from_secs_f64(self.frames.len() as f64 / self.sample_rate.max(1) as f64)
+    }
+}
+
+/// Returns the frames to play over and over, with the last `crossfade_frames` blended into the start at equal power.
+/// The loop ends on the frame just before the blended ones, so wrapping around continues the recording without a seam.
+fn looped(mut frames: Vec<(f32, f32)>, crossfade_frames: usize) -> Vec<(f32, f32)> {
+    let crossfade_frames = crossfade_frames.min(frames.len() / 2);
+    let loop_length = frames.len() - crossfade_frames;
+    let end = frames.split_off(loop_length);
+
+    for (index, (frame, (end_left, end_right))) in frames.iter_mut().zip(end).enumerate() {
+        let angle = (index as f32 + 0.5) / crossfade_frames as f32 * std::f32::consts::FRAC_PI_2;
+        let (fade_in, fade_out) = (angle.sin(), angle.cos());
+
+        frame.0 = frame.0 * fade_in + end_left * fade_out;
+        frame.1 = frame.1 * fade_in + end_right * fade_out;
+    }
+
+    frames
+}
+
+/// A soundscape to play underneath the tones, and how loud it plays next to them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ambient {
+    pub sound: Arc<AmbientSound>,
+    /// The level of the recording next to the tones, 1.0 plays its loudest part as loud as the tones.
+    pub gain: f32,
+}
+
+impl Ambient {
+    /// Returns the soundscape looping at the sample rate of an output, played from `start_frame` of that rate on.
+    /// The recording itself is shared, not copied, so this is as cheap for a long recording as for a short one.
+    pub(crate) fn at_rate(&self, sample_rate: u32, start_frame: u64) -> AmbientLoop {
+        let step = self.sound.sample_rate as f64 / sample_rate.max(1) as f64;
+        let position = if self.sound.frames.is_empty() {
+            0.0
+        } else {
+            (start_frame as f64 * step) % self.sound.frames.len() as f64
+        };
+
+        AmbientLoop {
+            sound: Arc::clone(&self.sound),
+            position,
+            step,
+            gain: self.gain,
+        }
+    }
+}
+
+/// Plays a soundscape over and over, one frame at a time. When the output runs at another sample rate than the recording,
+/// each frame is drawn in a straight line between the two recorded frames around it.
+#[derive(Debug, Clone)]
+pub(crate) struct AmbientLoop {
+    sound: Arc<AmbientSound>,
+    /// Where in the recording the next frame is drawn from, in frames of the recording.
+    position: f64,
+    /// How many frames of the recording one frame of the output moves on.
+    step: f64,
+    gain: f32,
+}
+
+impl AmbientLoop {
+    /// Returns the next left and right samples at the soundscape's gain, going back to the start after the last frame.
+    pub(crate) fn next_frame(&mut self) -> (f32, f32) {
+        let frames = &self.sound.frames;
+        if frames.is_empty() {
+            return (0.0, 0.0);
+        }
+
+        let index = (self.position as usize).min(frames.len() - 1);
+        let fraction = (self.position - index as f64) as f32;
+        let (left, right) = frames[index];
+        // The loop has no seam, so the frame after the last one is the first.
+        let (next_left, next_right) = frames[(index + 1) % frames.len()];
+
+        self.position += self.step;
+        if self.position >= frames.len() as f64 {
+            self.position -= frames.len() as f64;
+        }
+
+        (
+            (left + (next_left - left) * fraction) * self.gain,
+            (right + (next_right - right) * fraction) * self.gain,
+        )
+    }
+}
+
+/// Decodes every frame of the file's first audio track.
+fn decode(path: &Path) -> Result<AmbientSound, Error> {
+    let source = MediaSourceStream::new(Box::new(File::open(path)?), Default::default());
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(|extension| extension.to_str()) {
+        hint.with_extension(extension);
+    }
+
+    let mut format = symphonia::default::get_probe()
+        .format(
+            &hint,
+            source,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )?
+        .format;
+    let track = format
+        .default_track()
+        .ok_or_else(|| anyhow::anyhow!("the file has no audio track"))?;
+    let track_id = track.id;
+    let sample_rate = track
+        .codec_params
+        .sample_rate
+        .ok_or_else(|| anyhow::anyhow!("the file has no sample rate"))?;
+    let max_frames = (MAX_AMBIENT_LENGTH.as_secs_f64() * sample_rate as f64) as u64;
+    let too_long = || {
+        anyhow::anyhow!(
+            "the recording is longer than {} minutes, the longest that can be looped",
+            MAX_AMBIENT_LENGTH.as_secs() / 60
+        )
+    };
+    // Reject a recording that says how long it is before decoding any of it.
+    if track
+        .codec_params
+        .n_frames
+        .is_some_and(|n_frames| n_frames > max_frames)
+    {
+        return Err(too_long());
+    }
+    let mut decoder =
+        symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions::default())?;
+
+    let mut frames = Vec::new();
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            // The end of the file shows up as an unexpected end of the stream.
+            Err(DecodeError::IoError(err)) if err.kind() == std::io::ErrorKind::UnexpectedEof => {
+                break;
+            }
+            Err(err) => return Err(err.into()),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        let decoded = decoder.decode(&packet)?;
+        let spec = *decoded.spec();
+        let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+        buffer.copy_interleaved_ref(decoded);
+
+        frames.extend(buffer.samples().chunks(spec.channels.count().max(1)).map(
+            |frame| match frame {
+                [mono] => (*mono, *mono),
+                [left, right, ..] => (*left, *right),
+                [] => (0.0, 0.0),
+            },
+        ));
+        if frames.len() as u64 > max_frames {
+            return Err(too_long());
+        }
+    }
+
+    if frames.is_empty() {
+        return Err(anyhow::anyhow!("the file has no sound in it"));
+    }
+
+    Ok(AmbientSound::new(sample_rate, frames))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ramp(frames: usize) -> AmbientSound {
+        AmbientSound {
+            sample_rate: 1_000,
+            frames: (0..frames)
+                .map(|frame| (frame as f32, -(frame as f32)))
+                .collect(),
+        }
+    }
+
+    fn write_wav(path: &Path, sample_rate: u32, samples: impl IntoIterator<Item = i16>) {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(path, spec).unwrap();
+        for sample in samples {
+            writer.write_sample(sample).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+
+    #[test]
+    fn loop_at_double_the_rate_fills_in_between() {
+        let ambient = Ambient {
+            sound: Arc::new(ramp(4)),
+            gain: 1.0,
+        };
+        let mut ambient_loop = ambient.at_rate(2_000, 0);
+
+        let lefts: Vec<f32> = (0..10).map(|_| ambient_loop.next_frame().0).collect();
+
+        // Between the last frame and the first the loop heads back to the start.
+        assert_eq!(
+            lefts,
+            vec![0.0, 0.5, 1.0, 1.5, 2.0, 2.5, 3.0, 1.5, 0.0, 0.5]
+        );
+    }
+
+    #[test]
+    fn loop_starts_at_the_same_time_at_any_rate() {
+        let ambient = Ambient {
+            sound: Arc::new(ramp(100)),
+            gain: 1.0,
+        };
+
+        assert_eq!(ambient.at_rate(1_000, 25).next_frame(), (25.0, -25.0));
+        assert_eq!(ambient.at_rate(2_000, 50).next_frame(), (25.0, -25.0));
+        assert_eq!(ambient.at_rate(2_000, 250).next_frame(), (25.0, -25.0));
+    }
+
+    #[test]
+    fn loops_at_different_rates_share_the_recording() {
+        let ambient = Ambient {
+            sound: Arc::new(ramp(100)),
+            gain: 1.0,
+        };
+
+        let first = ambient.at_rate(44_100, 0);
+        let second = ambient.at_rate(48_000, 0);
+
+        assert!(Arc::ptr_eq(&first.sound, &second.sound));
+    }
+
+    #[test]
+    fn loop_blends_its_end_into_its_start() {
+        let frames = [(1.0, 1.0); 6].into_iter().chain([(0.0, 0.0); 4]).collect();
+
+        let looped = looped(frames, 4);
+
+        assert_eq!(looped.len(), 6);
+        // The first frames are still mostly the end of the recording, fading over to its start.
+        assert!(looped[0].0 < 0.2);
+        assert!(looped[3].0 > 0.9);
+        assert_eq!(looped[4..], [(1.0, 1.0); 2]);
+    }
+
+    #[test]
+    fn loop_wraps_around_at_the_gain() {
+        let ambient = Ambient {
+            sound: Arc::new(AmbientSound::new(
+                1_000,
+                (0..4)
+                    .map(|frame| (frame as f32, -(frame as f32)))
+                    .collect(),
+            )),
+            gain: 0.5,
+        };
+        let mut ambient_loop = ambient.at_rate(1_000, 6);
+
+        let frames: Vec<(f32, f32)> = (0..4).map(|_| ambient_loop.next_frame()).collect();
+
+        // Far shorter than the crossfade, so half of the recording is blended into the other half.
+        assert_eq!(ambient_loop.sound.frames.len(), 2);
+        assert_eq!(frames[0], frames[2]);
+        assert_eq!(frames[1], frames[3]);
+        assert!(frames[0].0 > 0.0 && frames[0].1 < 0.0);
+    }
+
+    #[test]
+    fn wav_file_is_decoded_to_a_stereo_loop() {
+        let path = std::env::temp_dir().join(format!("bb-ambient-{}.wav", uuid::Uuid::new_v4()));
+        write_wav(&path, 8_000, [i16::MAX / 2; 16_000]);
+
+        let sound = AmbientSound::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        let sound = sound.unwrap();
+
+        // Two seconds, less the half second blended into the start.
+        assert_eq!(sound.sample_rate, 8_000);
+        assert_eq!(sound.frames.len(), 12_000);
+        assert!((sound.frames[6_000].0 - 0.5).abs() < 1e-3);
+        assert_eq!(sound.frames[6_000].0, sound.frames[6_000].1);
+    }
+
+    #[test]
+    fn recording_too_long_to_loop_is_rejected() {
+        let path = std::env::temp_dir().join(format!("bb-ambient-{}.wav", uuid::Uuid::new_v4()));
+        write_wav(&path, 1_000, vec![0; 10 * 60 * 1_000 + 1]);
+
+        let err = AmbientSound::load(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(
+            err.unwrap_err().to_string().ends_with(
+                "the recording is longer than 10 minutes, the longest that can be looped"
+            )
+        );
+    }
+
+    #[test]
+    fn missing_file_is_reported_with_its_path() {
+        let err = AmbientSound::load(Path::new("/no/such/rain.wav")).unwrap_err();
+
+        assert!(
+            err.to_string()
+                .starts_with("/no/such/rain.wav can't be played")
+        );
+    }
+}
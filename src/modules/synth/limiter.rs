@@ -0,0 +1,66 @@
+//! A module that contains code related to keeping the mixed output from clipping.
+
+/// The level up to which samples pass through untouched, above it they are bent smoothly towards 1.0.
+/// The tones alone peak at half scale, so they are never touched, only louder mixes are.
+pub const LIMITER_THRESHOLD: f32 = 0.8;
+
+/// Returns the sample with any level above `LIMITER_THRESHOLD` softly saturated so that it never goes past ±1.0.
+/// The curve joins the straight part without a corner, which keeps the saturation from adding harsh overtones.
+pub fn soft_limit(sample: f32) -> f32 {
+    let level = sample.abs();
+
+    if level <= LIMITER_THRESHOLD {
+        return sample;
+    }
+
+    let headroom = 1.0 - LIMITER_THRESHOLD;
+    let limited = LIMITER_THRESHOLD + headroom * ((level - LIMITER_THRESHOLD) / headroom).tanh();
+
+    limited.copysign(sample)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    macro_rules! test_soft_limit_cases {
+        ($($name:ident:($sample:expr, $expected:expr),)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let actual = soft_limit($sample);
+                    assert!((actual - $expected).abs() < 1e-6, "{} != {}", actual, $expected)
+                }
+            )*
+        };
+    }
+
+    test_soft_limit_cases! {
+        soft_limit_leaves_silence: (0.0, 0.0),
+        soft_limit_leaves_the_tones: (0.5, 0.5),
+        soft_limit_leaves_negative_tones: (-0.5, -0.5),
+        soft_limit_leaves_the_threshold: (LIMITER_THRESHOLD, LIMITER_THRESHOLD),
+        soft_limit_bends_full_scale: (1.0, 0.952_318),
+        soft_limit_bends_negative_full_scale: (-1.0, -0.952_318),
+    }
+
+    #[test]
+    fn soft_limit_never_goes_past_full_scale() {
+        for step in 0..=1_000 {
+            let sample = step as f32 / 100.0;
+            assert!(soft_limit(sample) <= 1.0);
+            assert!(soft_limit(-sample) >= -1.0);
+        }
+    }
+
+    #[test]
+    fn soft_limit_keeps_louder_samples_louder() {
+        let mut previous = soft_limit(0.0);
+
+        for step in 1..=1_000 {
+            let limited = soft_limit(step as f32 / 500.0);
+            assert!(limited >= previous);
+            previous = limited;
+        }
+    }
+}
@@ -0,0 +1,5 @@
+//! A module that contains references related to the tone synthesis funcitonality.
+pub mod ambient;
+pub mod limiter;
+pub mod oscillator;
+pub mod waveform;
@@ -0,0 +1,62 @@
+//! A module that contains code related to producing a tone one sample at a time.
+
+use std::f64::consts::TAU;
+
+use crate::modules::synth::waveform::Waveform;
+
+/// Produces a tone of a chosen waveform whose frequency can change on every sample without clicking.
+/// The phase is kept in radians between 0 and 2π so that it stays exact however long the tone plays.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Oscillator {
+    waveform: Waveform,
+    phase: f64,
+}
+
+impl Oscillator {
+    pub fn new(waveform: Waveform) -> Self {
+        Oscillator {
+            waveform,
+            phase: 0.0,
+        }
+    }
+
+    /// Returns the sample at the current phase, then moves the phase on by one sample at `frequency_hz`.
+    pub fn next_sample(&mut self, frequency_hz: f64, sample_rate: f64) -> f32 {
+        let step = TAU / sample_rate;
+        let sample = self
+            .waveform
+            .sample(self.phase, (frequency_hz / sample_rate).abs());
+
+        self.phase = (self.phase + step * frequency_hz).rem_euclid(TAU);
+
+        //Always keep the final sample outputs as f32 but make the calculations using f64 so that we don't lose the signal.
+        sample as f32
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sine_oscillator_matches_a_sine() {
+        let mut oscillator = Oscillator::new(Waveform::Sine);
+
+        for frame in 0..480 {
+            let expected = (TAU * 100.0 * frame as f64 / 48_000.0).sin() as f32;
+            assert!((oscillator.next_sample(100.0, 48_000.0) - expected).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn square_oscillator_changes_sign_every_half_cycle() {
+        let mut oscillator = Oscillator::new(Waveform::Square);
+        let samples: Vec<f32> = (0..100)
+            .map(|_| oscillator.next_sample(10.0, 1_000.0))
+            .collect();
+
+        // A 10 Hz square at 1000 samples a second is high for 50 samples then low for 50.
+        assert!(samples[10..40].iter().all(|sample| *sample == 1.0));
+        assert!(samples[60..90].iter().all(|sample| *sample == -1.0));
+    }
+}
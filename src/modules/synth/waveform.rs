@@ -0,0 +1,137 @@
+//! A module that contains code related to the shape of the tone played in each ear.
+
+use std::f64::consts::TAU;
+use std::fmt;
+
+/// Represents the shape of one cycle of a tone, which sets its timbre.
+/// The sharp edges of the square and saw waves are smoothed with PolyBLEP so they don't alias into audible
+/// tones below the carrier. The triangle has no jumps and its overtones fade quickly, so it is left as is.
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Waveform {
+    /// A pure tone with no overtones.
+    Sine,
+    /// A soft tone with quiet odd overtones.
+    Triangle,
+    /// A hollow, buzzy tone with loud odd overtones, it sounds louder than the others at the same volume.
+    Square,
+    /// A bright tone with every overtone.
+    Saw,
+}
+
+impl Waveform {
+    /// Returns the waveform's value between -1.0 and 1.0 at `phase` radians into the cycle.
+    /// `increment` is how far the phase moves on each sample as a fraction of a cycle, which sets how wide the smoothed edges are.
+    pub fn sample(&self, phase: f64, increment: f64) -> f64 {
+        let position = (phase / TAU).rem_euclid(1.0);
+
+        match self {
+            Waveform::Sine => phase.sin(),
+            Waveform::Triangle => 4.0 * ((position + 0.75).rem_euclid(1.0) - 0.5).abs() - 1.0,
+            Waveform::Square => {
+                let square = if position < 0.5 { 1.0 } else { -1.0 };
+                square + poly_blep(position, increment)
+                    - poly_blep((position + 0.5).rem_euclid(1.0), increment)
+            }
+            Waveform::Saw => 2.0 * position - 1.0 - poly_blep(position, increment),
+        }
+    }
+}
+
+/// A helper function that returns the correction that smooths a jump from -1.0 to 1.0 at the start of the cycle,
+/// spread over the samples either side of it. `increment` is the fraction of a cycle covered by one sample.
+fn poly_blep(position: f64, increment: f64) -> f64 {
+    if increment <= 0.0 {
+        0.0
+    } else if position < increment {
+        let t = position / increment;
+        2.0 * t - t * t - 1.0
+    } else if position > 1.0 - increment {
+        let t = (position - 1.0) / increment;
+        t * t + 2.0 * t + 1.0
+    } else {
+        0.0
+    }
+}
+
+/// This formatter returns the name of the waveform as it is typed on the command line.
+impl fmt::Display for Waveform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Waveform::Sine => write!(f, "sine"),
+            Waveform::Triangle => write!(f, "triangle"),
+            Waveform::Square => write!(f, "square"),
+            Waveform::Saw => write!(f, "saw"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    macro_rules! test_waveform_sample_cases {
+        ($($name:ident:($waveform:expr, $cycle:expr, $expected:expr),)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    // Without an increment the edges aren't smoothed, which shows the plain shape.
+                    let actual = $waveform.sample($cycle * TAU, 0.0);
+                    assert!((actual - $expected).abs() < 1e-9, "{} != {}", actual, $expected)
+                }
+            )*
+        };
+    }
+
+    test_waveform_sample_cases! {
+        sine_at_quarter_cycle: (Waveform::Sine, 0.25, 1.0),
+        triangle_at_start: (Waveform::Triangle, 0.0, 0.0),
+        triangle_at_quarter_cycle: (Waveform::Triangle, 0.25, 1.0),
+        triangle_at_half_cycle: (Waveform::Triangle, 0.5, 0.0),
+        triangle_at_three_quarter_cycle: (Waveform::Triangle, 0.75, -1.0),
+        square_in_first_half: (Waveform::Square, 0.25, 1.0),
+        square_in_second_half: (Waveform::Square, 0.75, -1.0),
+        saw_at_quarter_cycle: (Waveform::Saw, 0.25, -0.5),
+        saw_at_three_quarter_cycle: (Waveform::Saw, 0.75, 0.5),
+    }
+
+    macro_rules! test_waveform_text_cases {
+        ($($name:ident:($waveform:expr, $expected:expr),)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    assert_eq!($waveform.to_string(), $expected)
+                }
+            )*
+        };
+    }
+
+    test_waveform_text_cases! {
+        sine_text: (Waveform::Sine, "sine"),
+        triangle_text: (Waveform::Triangle, "triangle"),
+        square_text: (Waveform::Square, "square"),
+        saw_text: (Waveform::Saw, "saw"),
+    }
+
+    #[test]
+    fn smoothed_edges_have_no_step() {
+        let increment = 0.01;
+
+        // Either side of the jump the smoothed saw lands on 0.0 instead of leaping from 1.0 to -1.0.
+        let before = Waveform::Saw.sample((1.0 - 1e-9) * TAU, increment);
+        let after = Waveform::Saw.sample(1e-9 * TAU, increment);
+        assert!(before.abs() < 1e-6);
+        assert!(after.abs() < 1e-6);
+    }
+
+    #[test]
+    fn smoothed_waveforms_stay_in_range() {
+        let increment = 440.0 / 48_000.0;
+
+        for waveform in [Waveform::Triangle, Waveform::Square, Waveform::Saw] {
+            for step in 0..1_000 {
+                let phase = step as f64 * increment * TAU;
+                assert!(waveform.sample(phase, increment).abs() <= 1.0 + 1e-9);
+            }
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! Checks that the rendered audio carries the frequencies each preset advertises,
+//! by looking for the strongest frequency in each ear with an FFT.
+
+use binaural_beat_generator_cli::modules::bb_generator::{
+    PlaybackOptions, render_samples, render_samples_with,
+};
+use binaural_beat_generator_cli::modules::frequency::frequency_common::ToFrequency;
+use binaural_beat_generator_cli::modules::preset::{BinauralPresetGroup, Preset, preset_list};
+use binaural_beat_generator_cli::modules::synth::waveform::Waveform;
+use rustfft::FftPlanner;
+use rustfft::num_complex::Complex;
+
+const SAMPLE_RATE: u32 = 48_000;
+
+/// Four seconds of audio, which puts the FFT bins a quarter of a hertz apart.
+const FRAMES: usize = SAMPLE_RATE as usize * 4;
+
+/// How far the measured frequency of an ear may be from the expected one.
+const TOLERANCE_HZ: f32 = 0.05;
+
+/// Returns one channel of interleaved stereo samples.
+fn channel(samples: &[f32], index: usize) -> Vec<f32> {
+    samples.iter().skip(index).step_by(2).copied().collect()
+}
+
+/// Returns the strongest frequency in the samples.
+/// A Hann window keeps the peak from leaking into its neighbours and the
+/// peak is placed between the bins by fitting a parabola through the three highest bins.
+fn dominant_hz(samples: &[f32]) -> f32 {
+    let len = samples.len();
+    let mut buffer: Vec<Complex<f32>> = samples
+        .iter()
+        .enumerate()
+        .map(|(i, sample)| {
+            let window =
+                0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / (len - 1) as f32).cos();
+            Complex::new(sample * window, 0.0)
+        })
+        .collect();
+    FftPlanner::new().plan_fft_forward(len).process(&mut buffer);
+
+    let magnitudes: Vec<f32> = buffer[..len / 2].iter().map(|bin| bin.norm()).collect();
+    let peak = (1..magnitudes.len() - 1)
+        .max_by(|&a, &b| magnitudes[a].total_cmp(&magnitudes[b]))
+        .unwrap();
+
+    let (before, at, after) = (
+        magnitudes[peak - 1].ln(),
+        magnitudes[peak].ln(),
+        magnitudes[peak + 1].ln(),
+    );
+    let offset = 0.5 * (before - after) / (before - 2.0 * at + after);
+
+    (peak as f32 + offset) * SAMPLE_RATE as f32 / len as f32
+}
+
+/// Renders the preset and checks that each ear plays at `carrier ± beat / 2`.
+fn assert_ears_match(preset_options: BinauralPresetGroup, samples: &[f32], label: &str) {
+    let carrier_hz = preset_options.carrier.to_hz();
+    let beat_hz = preset_options.beat.to_hz();
+    let expected_left = carrier_hz - beat_hz / 2.0;
+    let expected_right = carrier_hz + beat_hz / 2.0;
+
+    let left_hz = dominant_hz(&channel(samples, 0));
+    let right_hz = dominant_hz(&channel(samples, 1));
+
+    assert!(
+        (left_hz - expected_left).abs() <= TOLERANCE_HZ,
+        "{}: left ear at {:.3} Hz instead of {:.3} Hz",
+        label,
+        left_hz,
+        expected_left
+    );
+    assert!(
+        (right_hz - expected_right).abs() <= TOLERANCE_HZ,
+        "{}: right ear at {:.3} Hz instead of {:.3} Hz",
+        label,
+        right_hz,
+        expected_right
+    );
+}
+
+#[test]
+fn every_preset_plays_its_advertised_frequencies() {
+    for preset in preset_list() {
+        let preset_options = BinauralPresetGroup::from(preset);
+        let samples = render_samples(preset_options, SAMPLE_RATE, FRAMES).unwrap();
+
+        assert_ears_match(preset_options, &samples, &preset.to_string());
+    }
+}
+
+#[test]
+fn every_waveform_keeps_its_fundamental_at_the_advertised_frequencies() {
+    let preset_options = BinauralPresetGroup::from(Preset::Focus);
+
+    for waveform in [
+        Waveform::Sine,
+        Waveform::Triangle,
+        Waveform::Square,
+        Waveform::Saw,
+    ] {
+        let playback_options = PlaybackOptions {
+            waveform,
+            ..PlaybackOptions::default()
+        };
+        let samples =
+            render_samples_with(preset_options, playback_options, SAMPLE_RATE, FRAMES).unwrap();
+
+        assert_ears_match(preset_options, &samples, &waveform.to_string());
+    }
+}